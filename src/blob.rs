@@ -1,15 +1,18 @@
 use std::collections::HashMap;
 use std::fmt;
+use std::convert::TryFrom;
 use std::io;
 use std::ops::Index;
 
-use byteorder::WriteBytesExt;
+use byteorder::{ReadBytesExt, WriteBytesExt};
 use flate2::Compression;
 use flate2::read::{GzDecoder, ZlibDecoder};
 use flate2::write::{GzEncoder, ZlibEncoder};
 
+use config::ParseConfig;
 use error::{Error, Result};
 use raw;
+use tag::TagId;
 use value::Value;
 
 /// A generic, complete object in Named Binary Tag format.
@@ -53,30 +56,70 @@ impl Blob {
     }
 
     /// Extracts an `Blob` object from an `io::Read` source.
+    ///
+    /// A source consisting of a lone `TAG_End` byte is treated as an empty
+    /// document, and produces an empty, unnamed `Blob`.
     pub fn from_reader<R>(src: &mut R) -> Result<Blob>
         where R: io::Read
     {
-        let (tag, title) = try!(raw::emit_next_header(src));
+        Blob::from_reader_with_config(src, &ParseConfig::default())
+    }
+
+    /// Extracts an `Blob` object from an `io::Read` source, using the given
+    /// `ParseConfig`.
+    pub fn from_reader_with_config<R>(src: &mut R, config: &ParseConfig) -> Result<Blob>
+        where R: io::Read
+    {
+        let (tag, title) = if config.legacy_alpha_format {
+            (TagId::try_from(src.read_u8()?)?, String::new())
+        } else {
+            raw::emit_next_header(src)?
+        };
+        if tag == TagId::End {
+            return Ok(Blob::new());
+        }
         // Although it would be possible to read NBT format files composed of
         // arbitrary objects using the current API, by convention all files
         // have a top-level Compound.
-        if tag != 0x0a {
+        if tag != TagId::Compound {
             return Err(Error::NoRootCompound);
         }
-        let content = try!(Value::from_reader(tag, src));
+        let content = Value::from_reader_with_config(tag, src, config)?;
         match content {
             Value::Compound(map) => Ok(Blob { title: title, content: map }),
             _ => Err(Error::NoRootCompound),
         }
     }
 
+    /// Extracts an `Blob` object from an `io::Read` source, and checks that
+    /// its root compound is named `expected_name`.
+    ///
+    /// Some formats always use the same root name, so a different name is a
+    /// sign that the data is not what it should be. In that case this returns
+    /// `Error::RootNameMismatch`.
+    pub fn from_reader_named<R>(src: &mut R, expected_name: &str) -> Result<Blob>
+        where R: io::Read
+    {
+        let blob = Blob::from_reader(src)?;
+        if blob.title != expected_name {
+            return Err(Error::RootNameMismatch {
+                expected: expected_name.to_string(),
+                got: blob.title,
+            });
+        }
+        Ok(blob)
+    }
+
     /// Extracts an `Blob` object from an `io::Read` source that is
     /// compressed using the Gzip format.
+    ///
+    /// Returns `Error::NotGzip` if the source does not begin with a gzip
+    /// header, as happens when it is passed uncompressed data.
     pub fn from_gzip_reader<R>(src: &mut R) -> Result<Blob>
         where R: io::Read
     {
         // Reads the gzip header, and fails if it is incorrect.
-        let mut data = try!(GzDecoder::new(src));
+        let mut data = GzDecoder::new(raw::check_gzip_magic(src)?)?;
         Blob::from_reader(&mut data)
     }
 
@@ -131,15 +174,8 @@ impl Blob {
         // The follow prevents `List`s with heterogeneous tags from being
         // inserted into the file.
         let nvalue = value.into();
-        if let Value::List(ref vals) = nvalue {
-            if vals.len() != 0 {
-                let first_id = vals[0].id();
-                for nbt in vals {
-                    if nbt.id() != first_id {
-                        return Err(Error::HeterogeneousList)
-                    }
-                }
-            }
+        if let Value::List(_) = nvalue {
+            nvalue.assert_homogeneous()?;
         }
         self.content.insert(name.into(), nvalue);
         Ok(())
@@ -0,0 +1,232 @@
+//! Fluent builders for compound and list `Value`s.
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use error::{Error, Result};
+use value::Value;
+
+/// A builder for `Value::Compound`s.
+///
+/// ```rust
+/// use nbt::builder::CompoundBuilder;
+///
+/// let player = CompoundBuilder::new()
+///     .insert_string("Name", "Steve")
+///     .insert_float("Health", 20.0)
+///     .insert_compound("Abilities", CompoundBuilder::new().insert_bool("flying", false))
+///     .build();
+/// assert_eq!(player.get_path_typed::<i8>("Abilities.flying"), Ok(0));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompoundBuilder {
+    entries: HashMap<String, Value>,
+}
+
+impl CompoundBuilder {
+    /// Creates a builder for an empty compound.
+    pub fn new() -> CompoundBuilder {
+        CompoundBuilder { entries: HashMap::new() }
+    }
+
+    /// Adds an entry of any type which converts into a `Value`. A previous
+    /// entry with the same key is replaced.
+    pub fn insert<K, V>(mut self, key: K, value: V) -> CompoundBuilder
+        where K: Into<String>,
+              V: Into<Value>
+    {
+        self.entries.insert(key.into(), value.into());
+        self
+    }
+
+    /// Adds a `Byte` entry.
+    pub fn insert_byte<K: Into<String>>(self, key: K, value: i8) -> CompoundBuilder {
+        self.insert(key, Value::Byte(value))
+    }
+
+    /// Adds a `Byte` entry of `0` or `1`.
+    pub fn insert_bool<K: Into<String>>(self, key: K, value: bool) -> CompoundBuilder {
+        self.insert(key, Value::from(value))
+    }
+
+    /// Adds a `Short` entry.
+    pub fn insert_short<K: Into<String>>(self, key: K, value: i16) -> CompoundBuilder {
+        self.insert(key, Value::Short(value))
+    }
+
+    /// Adds an `Int` entry.
+    pub fn insert_int<K: Into<String>>(self, key: K, value: i32) -> CompoundBuilder {
+        self.insert(key, Value::Int(value))
+    }
+
+    /// Adds a `Long` entry.
+    pub fn insert_long<K: Into<String>>(self, key: K, value: i64) -> CompoundBuilder {
+        self.insert(key, Value::Long(value))
+    }
+
+    /// Adds a `Float` entry.
+    pub fn insert_float<K: Into<String>>(self, key: K, value: f32) -> CompoundBuilder {
+        self.insert(key, Value::Float(value))
+    }
+
+    /// Adds a `Double` entry.
+    pub fn insert_double<K: Into<String>>(self, key: K, value: f64) -> CompoundBuilder {
+        self.insert(key, Value::Double(value))
+    }
+
+    /// Adds a `String` entry.
+    pub fn insert_string<K, S>(self, key: K, value: S) -> CompoundBuilder
+        where K: Into<String>,
+              S: Into<String>
+    {
+        self.insert(key, Value::String(value.into()))
+    }
+
+    /// Adds a `ByteArray` entry.
+    pub fn insert_byte_array<K: Into<String>>(self, key: K, value: Vec<i8>) -> CompoundBuilder {
+        self.insert(key, Value::ByteArray(value))
+    }
+
+    /// Adds an `IntArray` entry.
+    pub fn insert_int_array<K: Into<String>>(self, key: K, value: Vec<i32>) -> CompoundBuilder {
+        self.insert(key, Value::IntArray(value))
+    }
+
+    /// Adds a `LongArray` entry.
+    pub fn insert_long_array<K: Into<String>>(self, key: K, value: Vec<i64>) -> CompoundBuilder {
+        self.insert(key, Value::LongArray(value))
+    }
+
+    /// Adds a nested `Compound` entry.
+    pub fn insert_compound<K: Into<String>>(self, key: K, value: CompoundBuilder) -> CompoundBuilder {
+        self.insert(key, value.build())
+    }
+
+    /// Adds a `List` entry.
+    pub fn insert_list<K, T>(self, key: K, value: ListBuilder<T>) -> CompoundBuilder
+        where K: Into<String>
+    {
+        self.insert(key, value.build())
+    }
+
+    /// Finishes building, returning a `Value::Compound`.
+    pub fn build(self) -> Value {
+        Value::Compound(self.entries)
+    }
+}
+
+impl From<CompoundBuilder> for Value {
+    fn from(builder: CompoundBuilder) -> Value {
+        builder.build()
+    }
+}
+
+/// A builder for `Value::List`s, which only accepts elements of the Rust type
+/// `T` that corresponds to a single tag type.
+///
+/// Typed builders are created with `new_byte`, `new_int` and so on. A builder
+/// created with `infer` accepts any `Value`, and takes the element type from
+/// the first one.
+///
+/// ```rust
+/// use nbt::builder::ListBuilder;
+/// use nbt::{TagId, Value};
+///
+/// let pos = ListBuilder::new_double().push(1.5).push(64.0).push(-3.5).build();
+/// assert_eq!(pos.element_tag_id(), Some(TagId::Double));
+///
+/// let inferred = ListBuilder::infer().try_push(1i32).unwrap();
+/// assert!(inferred.try_push("a string").is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListBuilder<T> {
+    elements: Vec<Value>,
+    element: PhantomData<T>,
+}
+
+/// Rust types which may be pushed into a typed `ListBuilder`.
+pub trait ListElement: Into<Value> {}
+
+macro_rules! list_builder_constructors {
+    ($($t:ty, $ctor:ident, $tag:expr;)*) => {
+        $(
+            impl ListElement for $t {}
+
+            impl ListBuilder<$t> {
+                #[doc = concat!("Creates a builder for a list of `", $tag, "` elements.")]
+                pub fn $ctor() -> ListBuilder<$t> {
+                    ListBuilder { elements: Vec::new(), element: PhantomData }
+                }
+            }
+        )*
+    };
+}
+
+list_builder_constructors! {
+    i8, new_byte, "Byte";
+    i16, new_short, "Short";
+    i32, new_int, "Int";
+    i64, new_long, "Long";
+    f32, new_float, "Float";
+    f64, new_double, "Double";
+    String, new_string, "String";
+    Vec<i8>, new_byte_array, "ByteArray";
+    Vec<i32>, new_int_array, "IntArray";
+    Vec<i64>, new_long_array, "LongArray";
+    CompoundBuilder, new_compound, "Compound";
+}
+
+impl<T> ListBuilder<T> where T: ListElement {
+    /// Appends an element.
+    pub fn push(mut self, element: T) -> ListBuilder<T> {
+        self.elements.push(element.into());
+        self
+    }
+}
+
+impl ListBuilder<Value> {
+    /// Creates a builder whose element type is that of the first element.
+    pub fn infer() -> ListBuilder<Value> {
+        ListBuilder { elements: Vec::new(), element: PhantomData }
+    }
+
+    /// Appends an element, or returns `Error::HeterogeneousList` if it has a
+    /// different tag type to the first element.
+    pub fn try_push<V: Into<Value>>(mut self, element: V) -> Result<ListBuilder<Value>> {
+        let element = element.into();
+        if let Some(first) = self.elements.first() {
+            if first.tag_id() != element.tag_id() {
+                return Err(Error::HeterogeneousList {
+                    index: self.elements.len(),
+                    expected: first.tag_id(),
+                    got: element.tag_id(),
+                });
+            }
+        }
+        self.elements.push(element);
+        Ok(self)
+    }
+}
+
+impl<T> ListBuilder<T> {
+    /// The number of elements added so far.
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Whether no elements have been added yet.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Finishes building, returning a `Value::List`.
+    pub fn build(self) -> Value {
+        Value::List(self.elements)
+    }
+}
+
+impl<T> From<ListBuilder<T>> for Value {
+    fn from(builder: ListBuilder<T>) -> Value {
+        builder.build()
+    }
+}
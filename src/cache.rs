@@ -0,0 +1,102 @@
+//! Memoization of parsed `Value` trees.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+use lru::LruCache;
+
+use error::Result;
+use raw;
+use value::Value;
+
+/// A least-recently-used cache of parsed NBT data, keyed by `K`.
+///
+/// This is intended for tools that repeatedly load the same data, such as the
+/// chunks of a region file. The loader is always called, but if it returns the
+/// same bytes as were previously cached for a key, the existing `Value` is
+/// returned instead of being parsed again.
+///
+/// ```rust
+/// use nbt::cache::NbtBlobCache;
+///
+/// let mut cache = NbtBlobCache::new(16);
+/// let bytes = vec![0x0a, 0x00, 0x00, 0x00];
+/// let first = cache.get_or_load((0, 0), || Ok(bytes.clone())).unwrap();
+/// let second = cache.get_or_load((0, 0), || Ok(bytes.clone())).unwrap();
+/// assert!(std::sync::Arc::ptr_eq(&first, &second));
+/// ```
+pub struct NbtBlobCache<K: Hash + Eq> {
+    entries: LruCache<K, CacheEntry>,
+}
+
+struct CacheEntry {
+    fingerprint: (u64, usize),
+    value: Arc<Value>,
+}
+
+impl<K: Hash + Eq> NbtBlobCache<K> {
+    /// Creates a cache holding at most `max_entries` parsed values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_entries` is zero.
+    pub fn new(max_entries: usize) -> NbtBlobCache<K> {
+        let cap = NonZeroUsize::new(max_entries)
+            .expect("NbtBlobCache requires a non-zero number of entries");
+        NbtBlobCache { entries: LruCache::new(cap) }
+    }
+
+    /// The maximum number of entries this cache holds before evicting the
+    /// least recently used one.
+    pub fn max_entries(&self) -> usize {
+        self.entries.cap().get()
+    }
+
+    /// The number of entries currently cached.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if nothing is cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes all cached entries.
+    pub fn clear(&mut self) {
+        self.entries.clear()
+    }
+
+    /// Calls `loader` to fetch the serialized NBT data for `key`, and returns
+    /// its root value.
+    ///
+    /// The bytes are only parsed if they differ (by hash and length) from the
+    /// bytes that produced the currently cached value for `key`.
+    pub fn get_or_load<F>(&mut self, key: K, loader: F) -> Result<Arc<Value>>
+        where F: FnOnce() -> Result<Vec<u8>>
+    {
+        let bytes = loader()?;
+        let fingerprint = fingerprint(&bytes);
+
+        if let Some(entry) = self.entries.get(&key) {
+            if entry.fingerprint == fingerprint {
+                return Ok(entry.value.clone());
+            }
+        }
+
+        let mut src = io::Cursor::new(&bytes[..]);
+        let (tag, _) = raw::emit_next_header(&mut src)?;
+        let value = Arc::new(Value::from_reader(tag, &mut src)?);
+        self.entries.put(key, CacheEntry { fingerprint, value: value.clone() });
+        Ok(value)
+    }
+}
+
+fn fingerprint(bytes: &[u8]) -> (u64, usize) {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    (hasher.finish(), bytes.len())
+}
@@ -0,0 +1,65 @@
+//! Stable content hashes for `Value` trees.
+
+use std::io;
+
+use byteorder::{BigEndian, WriteBytesExt};
+use sha2::{Digest, Sha256};
+
+use error::Result;
+use raw;
+use value::{self, Value};
+
+impl Value {
+    /// Computes the SHA-256 digest of the canonical binary form of this
+    /// `Value`.
+    ///
+    /// The canonical form is the tag ID followed by the payload, as written by
+    /// `Value::to_writer`, except that compound entries are always written in
+    /// key order, and every list element is preceded by its tag ID. Equal
+    /// values therefore always produce equal digests, no matter the iteration
+    /// order of their `HashMap`s, and heterogeneous lists are hashed in full
+    /// even though they cannot be written as NBT.
+    pub fn sha256_digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        write_canonical(&mut HashWriter(&mut hasher), self)
+            .expect("writing to a hasher cannot fail");
+        hasher.finalize().into()
+    }
+}
+
+fn write_canonical<W>(dst: &mut W, val: &Value) -> Result<()>
+    where W: io::Write
+{
+    dst.write_u8(val.id())?;
+    match *val {
+        Value::List(ref vals) => {
+            dst.write_i32::<BigEndian>(vals.len() as i32)?;
+            for v in vals {
+                write_canonical(dst, v)?;
+            }
+            Ok(())
+        },
+        Value::Compound(ref vals) => {
+            for (name, v) in value::sorted_entries(vals) {
+                raw::write_bare_string(dst, name)?;
+                write_canonical(dst, v)?;
+            }
+            raw::close_nbt(dst)
+        },
+        ref other => other.to_writer(dst).map(|_| ()),
+    }
+}
+
+/// Adapts a `Digest` to `io::Write`.
+struct HashWriter<'a, D: 'a>(&'a mut D);
+
+impl<'a, D: Digest> io::Write for HashWriter<'a, D> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
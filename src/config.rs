@@ -0,0 +1,36 @@
+//! Options controlling how NBT data is read.
+
+/// Configuration for reading NBT data into `Value`s and `Blob`s.
+///
+/// The default configuration matches the behaviour of `Value::from_reader`
+/// and `Blob::from_reader`.
+///
+/// Compounds are always read into a `HashMap`, since that is the type held by
+/// `Value::Compound`. Use `Value::iter_sorted` or `Value::to_writer_sorted`
+/// where entries are needed in key order.
+///
+/// ```rust
+/// use nbt::ParseConfig;
+///
+/// let config = ParseConfig { reject_duplicate_keys: true, max_depth: Some(64), ..Default::default() };
+/// # let _ = config;
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParseConfig {
+    /// Return `Error::DuplicateKey` when a compound contains the same key more
+    /// than once. Otherwise, the last value for a key wins.
+    pub reject_duplicate_keys: bool,
+    /// Return `Error::DepthLimitExceeded` when compounds and lists are nested
+    /// more than this many levels deep, counting the root compound as one.
+    pub max_depth: Option<usize>,
+    /// Return `Error::LengthLimitExceeded` when an array or list declares more
+    /// than this many elements.
+    pub max_array_len: Option<usize>,
+    /// Read documents whose root tag type is followed directly by its
+    /// payload, without a name, as written by
+    /// `NbtWriterBuilder::root_name(false)`. The root is then named `""`.
+    pub legacy_alpha_format: bool,
+    /// Wrap errors raised inside compounds and lists in `Error::AtPath`, with
+    /// the path of the value that could not be read.
+    pub track_paths: bool,
+}
@@ -0,0 +1,34 @@
+//! Conversions between NBT arrays and raw binary data.
+
+use byteorder::{BigEndian, ByteOrder};
+
+use error::{Error, Result};
+
+/// Packs the elements of a `TAG_IntArray` into bytes, four per element in
+/// big-endian order.
+///
+/// ```rust
+/// use nbt::convert::int_array_to_bytes;
+///
+/// assert_eq!(int_array_to_bytes(&[0x01020304, -1]), vec![1, 2, 3, 4, 0xff, 0xff, 0xff, 0xff]);
+/// ```
+pub fn int_array_to_bytes(arr: &[i32]) -> Vec<u8> {
+    let mut bytes = vec![0; arr.len() * 4];
+    BigEndian::write_i32_into(arr, &mut bytes);
+    bytes
+}
+
+/// Unpacks bytes into the elements of a `TAG_IntArray`, reading each group of
+/// four bytes as a big-endian integer.
+///
+/// Returns `Error::UnalignedBytes` if the length of `bytes` is not a multiple
+/// of four.
+pub fn bytes_to_int_array(bytes: &[u8]) -> Result<Vec<i32>> {
+    let chunks = bytes.chunks_exact(4);
+    if !chunks.remainder().is_empty() {
+        return Err(Error::UnalignedBytes(bytes.len()));
+    }
+    let mut arr = vec![0; chunks.len()];
+    BigEndian::read_i32_into(bytes, &mut arr);
+    Ok(arr)
+}
@@ -0,0 +1,255 @@
+//! Cursors for navigating nested values while keeping track of the path.
+
+use std::fmt;
+
+use error::{Error, Result};
+use tag::TagId;
+use value::Value;
+
+/// One step of the path from the root of a tree to a nested value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathSegment {
+    /// An entry of a compound.
+    Key(String),
+    /// An element of a list.
+    Index(usize),
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PathSegment::Key(ref key) => f.write_str(key),
+            PathSegment::Index(i) => write!(f, "{}", i),
+        }
+    }
+}
+
+/// Renders a path in the dot-separated form accepted by `Value::get_path`,
+/// with `next` appended if given.
+fn format_path(path: &[PathSegment], next: Option<&PathSegment>) -> String {
+    let segments: Vec<String> = path.iter().chain(next).map(|s| s.to_string()).collect();
+    segments.join(".")
+}
+
+fn child<'b>(value: &'b Value, segment: &PathSegment) -> Option<&'b Value> {
+    match (value, segment) {
+        (Value::Compound(map), PathSegment::Key(key)) => map.get(key),
+        (Value::List(vals), &PathSegment::Index(i)) => vals.get(i),
+        _ => None,
+    }
+}
+
+fn child_mut<'b>(value: &'b mut Value, segment: &PathSegment) -> Option<&'b mut Value> {
+    match (value, segment) {
+        (Value::Compound(map), PathSegment::Key(key)) => map.get_mut(key),
+        (Value::List(vals), &PathSegment::Index(i)) => vals.get_mut(i),
+        _ => None,
+    }
+}
+
+/// A cursor for reading the values of a tree, which remembers the path from
+/// the root to the current value. Its `Display` form is that path, as
+/// accepted by `Value::get_path`.
+///
+/// ```rust
+/// # #[macro_use] extern crate nbt;
+/// # fn main() {
+/// use nbt::cursor::NbtCursor;
+/// use nbt::Value;
+///
+/// let level = compound! { "Data" => compound! { "Difficulty" => 2i8, "raining" => 0i8 } };
+/// let mut cursor = NbtCursor::new(&level);
+/// cursor.enter("Data").unwrap();
+/// cursor.enter("Difficulty").unwrap();
+/// assert_eq!(cursor.to_string(), "Data.Difficulty");
+/// assert_eq!(cursor.current(), &Value::Byte(2));
+/// assert_eq!(cursor.peek_next_key(), Some("raining"));
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct NbtCursor<'a> {
+    /// The values along the path, starting with the root.
+    values: Vec<&'a Value>,
+    path: Vec<PathSegment>,
+}
+
+impl<'a> NbtCursor<'a> {
+    /// Creates a cursor positioned at `root`.
+    pub fn new(root: &'a Value) -> NbtCursor<'a> {
+        NbtCursor { values: vec![root], path: Vec::new() }
+    }
+
+    /// The path from the root to the current value.
+    pub fn path(&self) -> &[PathSegment] {
+        &self.path
+    }
+
+    /// The value the cursor is positioned at.
+    pub fn current(&self) -> &'a Value {
+        self.values[self.values.len() - 1]
+    }
+
+    /// Moves to the entry `key` of the current compound.
+    ///
+    /// Returns `Error::PathNotFound` if there is no such entry, and
+    /// `Error::TagMismatch` if the current value is not a compound.
+    pub fn enter(&mut self, key: &str) -> Result<()> {
+        match *self.current() {
+            Value::Compound(_) => self.push(PathSegment::Key(key.to_string())),
+            ref other => Err(Error::TagMismatch(other.tag_id(), TagId::Compound)),
+        }
+    }
+
+    /// Moves to the element at `index` of the current list.
+    ///
+    /// Returns `Error::PathNotFound` if the index is out of bounds, and
+    /// `Error::TagMismatch` if the current value is not a list.
+    pub fn enter_index(&mut self, index: usize) -> Result<()> {
+        match *self.current() {
+            Value::List(_) => self.push(PathSegment::Index(index)),
+            ref other => Err(Error::TagMismatch(other.tag_id(), TagId::List)),
+        }
+    }
+
+    fn push(&mut self, segment: PathSegment) -> Result<()> {
+        match child(self.current(), &segment) {
+            Some(value) => {
+                self.values.push(value);
+                self.path.push(segment);
+                Ok(())
+            },
+            None => Err(Error::PathNotFound(format_path(&self.path, Some(&segment)))),
+        }
+    }
+
+    /// Moves back to the compound or list containing the current value.
+    ///
+    /// Returns `Error::CursorAtRoot` if the cursor is at the root.
+    pub fn leave(&mut self) -> Result<()> {
+        self.path.pop().ok_or(Error::CursorAtRoot)?;
+        self.values.pop();
+        Ok(())
+    }
+
+    /// Returns the key that follows the current entry in its compound, in
+    /// key order, without moving the cursor. Returns `None` if the current
+    /// value is the last entry, a list element, or the root.
+    pub fn peek_next_key(&self) -> Option<&'a str> {
+        let key = match self.path.last() {
+            Some(PathSegment::Key(key)) => key,
+            _ => return None,
+        };
+        match *self.values[self.values.len() - 2] {
+            Value::Compound(ref map) => map.keys()
+                .filter(|k| *k > key)
+                .min()
+                .map(|k| &k[..]),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> fmt::Display for NbtCursor<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&format_path(&self.path, None))
+    }
+}
+
+/// A cursor for modifying the values of a tree in place, which remembers the
+/// path from the root to the current value. Like `NbtCursor`, it displays as
+/// that path.
+///
+/// ```rust
+/// # #[macro_use] extern crate nbt;
+/// # fn main() {
+/// use nbt::cursor::NbtCursorMut;
+/// use nbt::Value;
+///
+/// let mut player = compound! {
+///     "Inventory" => Value::List(vec![compound! { "Count" => 1i8 }])
+/// };
+/// {
+///     let mut cursor = NbtCursorMut::new(&mut player);
+///     cursor.enter("Inventory").unwrap();
+///     cursor.enter_index(0).unwrap();
+///     cursor.enter("Count").unwrap();
+///     *cursor.current() = Value::Byte(64);
+/// }
+/// assert_eq!(player.get_path("Inventory.0.Count"), Some(&Value::Byte(64)));
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct NbtCursorMut<'a> {
+    root: &'a mut Value,
+    path: Vec<PathSegment>,
+}
+
+impl<'a> NbtCursorMut<'a> {
+    /// Creates a cursor positioned at `root`.
+    pub fn new(root: &'a mut Value) -> NbtCursorMut<'a> {
+        NbtCursorMut { root, path: Vec::new() }
+    }
+
+    /// The path from the root to the current value.
+    pub fn path(&self) -> &[PathSegment] {
+        &self.path
+    }
+
+    /// The value the cursor is positioned at.
+    pub fn current(&mut self) -> &mut Value {
+        let mut value = &mut *self.root;
+        for segment in &self.path {
+            // Only values below the current one can be replaced through the
+            // cursor, so the path always leads somewhere.
+            value = child_mut(value, segment).expect("cursor path no longer exists");
+        }
+        value
+    }
+
+    /// Moves to the entry `key` of the current compound.
+    ///
+    /// Returns `Error::PathNotFound` if there is no such entry, and
+    /// `Error::TagMismatch` if the current value is not a compound.
+    pub fn enter(&mut self, key: &str) -> Result<()> {
+        let found = match *self.current() {
+            Value::Compound(ref map) => map.contains_key(key),
+            ref other => return Err(Error::TagMismatch(other.tag_id(), TagId::Compound)),
+        };
+        let segment = PathSegment::Key(key.to_string());
+        if !found {
+            return Err(Error::PathNotFound(format_path(&self.path, Some(&segment))));
+        }
+        self.path.push(segment);
+        Ok(())
+    }
+
+    /// Moves to the element at `index` of the current list.
+    ///
+    /// Returns `Error::PathNotFound` if the index is out of bounds, and
+    /// `Error::TagMismatch` if the current value is not a list.
+    pub fn enter_index(&mut self, index: usize) -> Result<()> {
+        let found = match *self.current() {
+            Value::List(ref vals) => index < vals.len(),
+            ref other => return Err(Error::TagMismatch(other.tag_id(), TagId::List)),
+        };
+        let segment = PathSegment::Index(index);
+        if !found {
+            return Err(Error::PathNotFound(format_path(&self.path, Some(&segment))));
+        }
+        self.path.push(segment);
+        Ok(())
+    }
+
+    /// Moves back to the compound or list containing the current value.
+    ///
+    /// Returns `Error::CursorAtRoot` if the cursor is at the root.
+    pub fn leave(&mut self) -> Result<()> {
+        self.path.pop().map(|_| ()).ok_or(Error::CursorAtRoot)
+    }
+}
+
+impl<'a> fmt::Display for NbtCursorMut<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&format_path(&self.path, None))
+    }
+}
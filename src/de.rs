@@ -1,11 +1,14 @@
 //! Deserialize Named Binary Tag data to a Rust data structure.
 
+use std::convert::TryFrom;
 use std::io;
+use std::iter;
 
 use serde::de;
 use flate2::read;
 
 use raw;
+use tag::TagId;
 
 use error::{Error, Result};
 
@@ -13,6 +16,7 @@ use error::{Error, Result};
 ///
 /// Note that only maps and structs can be decoded, because the NBT format does
 /// not support bare types. Other types will return `Error::NoRootCompound`.
+/// A source consisting of a lone `TAG_End` byte decodes as an empty map.
 pub fn from_reader<R, T>(src: R) -> Result<T>
     where R: io::Read,
           T: de::DeserializeOwned,
@@ -25,11 +29,12 @@ pub fn from_reader<R, T>(src: R) -> Result<T>
 ///
 /// Note that only maps and structs can be decoded, because the NBT format does
 /// not support bare types. Other types will return `Error::NoRootCompound`.
+/// Sources that are not gzip-compressed return `Error::NotGzip`.
 pub fn from_gzip_reader<R, T>(src: R) -> Result<T>
     where R: io::Read,
           T: de::DeserializeOwned,
 {
-    let gzip = try!(read::GzDecoder::new(src));
+    let gzip = read::GzDecoder::new(raw::check_gzip_magic(src)?)?;
     from_reader(gzip)
 }
 
@@ -102,7 +107,9 @@ impl<'de: 'a, 'a, R: io::Read> de::Deserializer<'de> for &'a mut Decoder<R> {
         let (tag, _) = try!(raw::emit_next_header(&mut self.reader));
 
         match tag {
-            0x0a => visitor.visit_map(MapDecoder::new(self)),
+            TagId::Compound => visitor.visit_map(MapDecoder::new(self)),
+            // A lone TAG_End is an empty document.
+            TagId::End => visitor.visit_map(de::value::MapDeserializer::new(iter::empty::<((), ())>())),
             _ => Err(Error::NoRootCompound)
         }
     }
@@ -268,7 +275,7 @@ impl<'a, 'b: 'a, 'de, R: io::Read> de::Deserializer<'de> for &'b mut InnerDecode
                     b => Err(Error::NonBooleanByte(b)),
                 }
             },
-            _ => Err(Error::TagMismatch(self.tag, 0x01)),
+            _ => Err(Error::TagMismatch(TagId::try_from(self.tag)?, TagId::Byte)),
         }
     }
 
@@ -300,8 +307,18 @@ impl<'a, 'b: 'a, 'de, R: io::Read> de::Deserializer<'de> for &'b mut InnerDecode
         visitor.visit_newtype_struct(self)
     }
 
+    /// Skip values that will be discarded (such as unknown fields) without
+    /// decoding them.
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+        where V: de::Visitor<'de>
+    {
+        let tag = TagId::try_from(self.tag)?;
+        raw::skip_payload(&mut self.outer.reader, tag)?;
+        visitor.visit_unit()
+    }
+
     forward_to_deserialize_any! {
         u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string bytes byte_buf seq
-        map tuple_struct struct tuple enum identifier ignored_any
+        map tuple_struct struct tuple enum identifier
     }
 }
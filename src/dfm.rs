@@ -0,0 +1,115 @@
+//! Migration of NBT data between Minecraft data versions, in the manner of
+//! Minecraft's own DataFixerUpper.
+//!
+//! Each `DataFixer` upgrades data to one particular data version. A
+//! `DataFixerChain` holds fixers registered for a range of versions, and
+//! applies the ones needed to bring data from its current version up to a
+//! target version.
+//!
+//! ```rust
+//! # #[macro_use] extern crate nbt;
+//! # fn main() {
+//! use nbt::dfm::DataFixerChain;
+//! use nbt::Value;
+//!
+//! let chain = DataFixerChain::new()
+//!     // Data version 1451 renamed the "Riding" entry of entities.
+//!     .register(1451, |mut val: Value, _from| {
+//!         if let Value::Compound(ref mut map) = val {
+//!             if let Some(riding) = map.remove("Riding") {
+//!                 map.insert("RootVehicle".to_string(), riding);
+//!             }
+//!         }
+//!         Ok(val)
+//!     });
+//!
+//! let entity = compound! { "Riding" => compound! {} };
+//! let fixed = entity.apply_data_version_migration(&chain, 1343, 1631).unwrap();
+//! assert_eq!(fixed, compound! { "RootVehicle" => compound! {} });
+//! # }
+//! ```
+
+use error::{Error, Result};
+use value::Value;
+
+/// Upgrades NBT data to the data version it is registered for in a
+/// `DataFixerChain`.
+///
+/// Closures taking the value and its current data version implement this
+/// trait, so simple fixers need not define a type of their own.
+pub trait DataFixer {
+    /// Upgrades `val`, which is at data version `from_version`.
+    fn fix(&self, val: Value, from_version: i32) -> Result<Value>;
+}
+
+impl<F> DataFixer for F where F: Fn(Value, i32) -> Result<Value> {
+    fn fix(&self, val: Value, from_version: i32) -> Result<Value> {
+        self(val, from_version)
+    }
+}
+
+/// A sequence of `DataFixer`s, ordered by the data version each upgrades to.
+#[derive(Default)]
+pub struct DataFixerChain {
+    fixers: Vec<(i32, Box<dyn DataFixer>)>,
+}
+
+impl DataFixerChain {
+    /// Creates a chain with no fixers.
+    pub fn new() -> DataFixerChain {
+        DataFixerChain::default()
+    }
+
+    /// Adds a fixer that upgrades data to data version `version`. Fixers
+    /// registered for the same version run in the order they were added.
+    pub fn register<F>(mut self, version: i32, fixer: F) -> DataFixerChain
+        where F: DataFixer + 'static
+    {
+        let pos = self.fixers.iter()
+            .position(|&(v, _)| v > version)
+            .unwrap_or(self.fixers.len());
+        self.fixers.insert(pos, (version, Box::new(fixer)));
+        self
+    }
+
+    /// The number of fixers in this chain.
+    pub fn len(&self) -> usize {
+        self.fixers.len()
+    }
+
+    /// Returns `true` if this chain has no fixers.
+    pub fn is_empty(&self) -> bool {
+        self.fixers.is_empty()
+    }
+
+    /// Migrates `val` from data version `from` to data version `to`, running
+    /// every fixer registered for a version after `from` and up to and
+    /// including `to`. Each fixer is passed the version the data was at
+    /// before it ran.
+    ///
+    /// Returns `Error::DataVersionDowngrade` if `to` is older than `from`.
+    pub fn apply(&self, mut val: Value, from: i32, to: i32) -> Result<Value> {
+        if to < from {
+            return Err(Error::DataVersionDowngrade { from, to });
+        }
+        let mut current = from;
+        for &(version, ref fixer) in &self.fixers {
+            if version <= from || version > to {
+                continue;
+            }
+            val = fixer.fix(val, current)?;
+            current = version;
+        }
+        Ok(val)
+    }
+}
+
+impl Value {
+    /// Migrates this value from data version `from` to data version `to`
+    /// with the fixers of `chain`. See `DataFixerChain::apply`.
+    pub fn apply_data_version_migration(self, chain: &DataFixerChain, from: i32, to: i32)
+        -> Result<Value>
+    {
+        chain.apply(self, from, to)
+    }
+}
@@ -0,0 +1,146 @@
+//! Compact binary diffs between serialized NBT data.
+//!
+//! A diff is a sequence of instructions which rebuild the new data from the
+//! old data:
+//!
+//! * `0x00`, offset (`u32`), length (`u32`): copy `length` bytes from the old
+//!   data, starting at `offset`.
+//! * `0x01`, length (`u32`), bytes: append `length` literal bytes.
+//!
+//! All integers are big-endian. This is much simpler than a format such as
+//! bsdiff, but works well for NBT data where most changes are small in-place
+//! edits.
+
+use std::collections::HashMap;
+use std::io::{self, Read};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use error::{Error, Result};
+use value::Value;
+
+/// The size of the blocks used to find matches in the old data. Matches
+/// shorter than this are written as literal bytes.
+const BLOCK_SIZE: usize = 16;
+
+/// The number of candidate positions remembered for each block, to bound the
+/// time spent on highly repetitive data.
+const MAX_CANDIDATES: usize = 8;
+
+const OP_COPY: u8 = 0x00;
+const OP_ADD: u8 = 0x01;
+
+/// Computes a diff that turns `old` into `new` when applied with
+/// `binary_patch`.
+pub fn binary_diff(old: &[u8], new: &[u8]) -> Vec<u8> {
+    let mut index: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    if old.len() >= BLOCK_SIZE {
+        for pos in 0..old.len() - BLOCK_SIZE + 1 {
+            let candidates = index.entry(&old[pos..pos + BLOCK_SIZE]).or_default();
+            if candidates.len() < MAX_CANDIDATES {
+                candidates.push(pos);
+            }
+        }
+    }
+
+    let mut patch = Vec::new();
+    let mut literal_start = 0;
+    let mut pos = 0;
+    while pos + BLOCK_SIZE <= new.len() {
+        let best = index.get(&new[pos..pos + BLOCK_SIZE]).and_then(|candidates| {
+            candidates.iter()
+                .map(|&start| {
+                    let len = old[start..].iter().zip(&new[pos..])
+                        .take_while(|&(a, b)| a == b)
+                        .count();
+                    (start, len)
+                })
+                .max_by_key(|&(_, len)| len)
+        });
+        match best {
+            Some((start, len)) => {
+                write_add(&mut patch, &new[literal_start..pos]);
+                write_copy(&mut patch, start, len);
+                pos += len;
+                literal_start = pos;
+            },
+            None => pos += 1,
+        }
+    }
+    write_add(&mut patch, &new[literal_start..]);
+    patch
+}
+
+/// Applies a diff produced by `binary_diff` to `base`.
+///
+/// Returns `Error::InvalidPatch` if the diff is malformed or refers to data
+/// outside of `base`.
+pub fn binary_patch(base: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(base.len());
+    let mut src = io::Cursor::new(patch);
+    loop {
+        let op = match src.read_u8() {
+            Ok(op) => op,
+            Err(_) => return Ok(out),
+        };
+        match op {
+            OP_COPY => {
+                let offset = read_len(&mut src)?;
+                let len = read_len(&mut src)?;
+                let end = offset.checked_add(len).ok_or(Error::InvalidPatch)?;
+                out.extend_from_slice(base.get(offset..end).ok_or(Error::InvalidPatch)?);
+            },
+            OP_ADD => {
+                // Check the length against the rest of the patch before
+                // allocating, since it comes from the patch itself.
+                let len = read_len(&mut src)?;
+                let remaining = patch.len() - src.position() as usize;
+                if len > remaining {
+                    return Err(Error::InvalidPatch);
+                }
+                let start = out.len();
+                out.resize(start.checked_add(len).ok_or(Error::InvalidPatch)?, 0);
+                src.read_exact(&mut out[start..]).map_err(|_| Error::InvalidPatch)?;
+            },
+            _ => return Err(Error::InvalidPatch),
+        }
+    }
+}
+
+/// Computes a diff between the binary representations of two `Value`s.
+///
+/// Each value is serialized as its tag ID followed by its payload, with
+/// compound entries in key order (see `Value::to_writer_sorted`), so that
+/// unchanged entries line up.
+pub fn nbt_diff_bytes(old: &Value, new: &Value) -> Result<Vec<u8>> {
+    Ok(binary_diff(&serialize(old)?, &serialize(new)?))
+}
+
+fn serialize(val: &Value) -> Result<Vec<u8>> {
+    let mut dst = Vec::new();
+    dst.write_u8(val.id())?;
+    val.to_writer_sorted(&mut dst)?;
+    Ok(dst)
+}
+
+fn write_copy(patch: &mut Vec<u8>, offset: usize, len: usize) {
+    patch.push(OP_COPY);
+    // Writing to a `Vec` cannot fail.
+    let _ = patch.write_u32::<BigEndian>(offset as u32);
+    let _ = patch.write_u32::<BigEndian>(len as u32);
+}
+
+fn write_add(patch: &mut Vec<u8>, bytes: &[u8]) {
+    if bytes.is_empty() {
+        return;
+    }
+    patch.push(OP_ADD);
+    let _ = patch.write_u32::<BigEndian>(bytes.len() as u32);
+    patch.extend_from_slice(bytes);
+}
+
+fn read_len<R: io::Read>(src: &mut R) -> Result<usize> {
+    src.read_u32::<BigEndian>()
+        .map(|n| n as usize)
+        .map_err(|_| Error::InvalidPatch)
+}
@@ -7,6 +7,9 @@ use std::result::Result as StdResult;
 #[cfg(feature = "serde")]
 use serde;
 
+use tag::TagId;
+use value::Value;
+
 /// A convenient alias type for results when reading/writing the Named Binary
 /// Tag format.
 pub type Result<T> = StdResult<T, Error>;
@@ -27,7 +30,9 @@ pub enum Error {
     /// binary representations. Includes the ID in question.
     InvalidTypeId(u8),
     /// An error emitted when trying to create `NbtBlob`s with incorrect lists.
-    HeterogeneousList,
+    /// Includes the index of the offending element, the tag type of the list
+    /// and the tag type of that element.
+    HeterogeneousList { index: usize, expected: TagId, got: TagId },
     /// An error for when NBT binary representations do not begin with an
     /// `NbtValue::Compound`.
     NoRootCompound,
@@ -39,7 +44,7 @@ pub enum Error {
     IncompleteNbtValue,
     /// An error encountered when parsing NBT binary representations, where
     /// deserialization encounters a different tag than expected.
-    TagMismatch(u8, u8),
+    TagMismatch(TagId, TagId),
     /// An error encountered when parsing NBT binary representations, where
     /// deserialization encounters a field name it is not expecting.
     UnexpectedField(String),
@@ -51,19 +56,183 @@ pub enum Error {
     /// An error encountered when trying to (de)serialize a map key with a
     /// non-string type.
     NonStringMapKey,
+    /// An error encountered when converting a `TAG_IntArray` to a UUID, where
+    /// the array does not contain exactly four elements. Includes the length
+    /// in question.
+    #[cfg(feature = "uuid")]
+    InvalidUuidLength(usize),
+    /// An error encountered when a numeric value does not fit in the tag type
+    /// it is being converted to. Includes the value in question.
+    NumericOutOfRange(i64),
+    /// An error encountered when parsing NBT binary representations, where a
+    /// compound contains the same key more than once. Only returned when
+    /// `ParseConfig::reject_duplicate_keys` is set.
+    DuplicateKey(String),
+    /// An error encountered when arithmetic on a numeric `Value` overflows its
+    /// tag type, or divides by zero.
+    ArithmeticOverflow,
+    /// An error encountered when converting a `Value` to a typed structure,
+    /// where a required compound entry is absent. Includes the missing key.
+    MissingField(String),
+    /// An error encountered when applying a malformed binary diff.
+    InvalidPatch,
+    /// An error encountered when parsing the `Debug` representation of a
+    /// `Value`. Includes the byte offset of the unexpected input.
+    InvalidDebugFormat(usize),
+    /// An error encountered when converting the elements of a `TAG_List`,
+    /// where the element at the given index could not be converted.
+    InvalidListElement(usize, Box<Error>),
+    /// An error encountered when looking up a path in a `Value`, where no
+    /// value exists at that path. Includes the path in question.
+    PathNotFound(String),
+    /// An error encountered when the root compound of NBT binary
+    /// representations does not have the expected name.
+    RootNameMismatch { expected: String, got: String },
+    /// An error for when NBT binary representations end in the middle of an
+    /// array. Includes the array's tag type, the number of elements its
+    /// length prefix declares, and the number of bytes that were available.
+    TruncatedArray { tag_id: TagId, declared_len: usize, bytes_available: usize },
+    /// An error annotated with a description of what was being done when it
+    /// occurred, such as the key being read. See `ResultExt::with_context`.
+    Context(String, Box<Error>),
+    /// An error encountered when converting bytes to a `TAG_IntArray`, where
+    /// the number of bytes is not a multiple of four. Includes the length in
+    /// question.
+    UnalignedBytes(usize),
+    /// An error for when data expected to be gzip-compressed does not begin
+    /// with the gzip magic bytes, which usually means it is uncompressed.
+    NotGzip,
+    /// An error encountered when parsing NBT binary representations, where
+    /// compounds and lists are nested more deeply than
+    /// `ParseConfig::max_depth` allows. Includes the limit in question.
+    DepthLimitExceeded(usize),
+    /// An error encountered when parsing NBT binary representations, where an
+    /// array or list declares more elements than `ParseConfig::max_array_len`
+    /// allows. Includes the declared length.
+    LengthLimitExceeded(usize),
+    /// An error encountered when migrating data between data versions with a
+    /// `dfm::DataFixerChain`, where the target version is older than the
+    /// version of the data. Fixers only upgrade data.
+    DataVersionDowngrade { from: i32, to: i32 },
+    /// An error encountered by `Value::from_reader_partial`, where the byte
+    /// limit was reached in the middle of a compound or list. Includes the
+    /// entries and elements that were read completely, and the number of
+    /// bytes consumed.
+    Truncated { partial: Value, bytes_read: usize },
+    /// An error encountered when leaving the current value of a cursor from
+    /// the `cursor` module while it is already at the root.
+    CursorAtRoot,
+    /// An error encountered when writing NBT binary representations with
+    /// `NbtWriterBuilder::validate_strings`, where a string or key is longer
+    /// than the 65535 bytes its length prefix can express once encoded as
+    /// Modified UTF-8. Includes the encoded length.
+    StringTooLong(usize),
+    /// An error encountered when decoding the JSON encoding of `Value`s
+    /// written by `Value::to_typed_json`, where the JSON is not in that
+    /// encoding. Includes a description of the problem.
+    #[cfg(feature = "json")]
+    InvalidTypedJson(String),
+    /// An error encountered when parsing NBT binary representations with
+    /// `ParseConfig::track_paths`, wrapping the error with the path of the
+    /// value being read, in the dot-separated form used by `Value::get_path`.
+    AtPath(String, Box<Error>),
+    /// An I/O error encountered when writing a value with `Value::to_writer`
+    /// and its variants. Includes the number of bytes of the payload that
+    /// were written before the failure.
+    WriteError { io: io::Error, bytes_written: usize },
+    /// An error encountered when adding two `Value`s that cannot be
+    /// concatenated or merged. Includes the tags of the left- and right-hand
+    /// sides.
+    UnsupportedOperands(TagId, TagId),
+}
+
+/// Adds context to the errors of `Result`s, in the manner of `Error::Context`.
+///
+/// ```rust
+/// use nbt::{ResultExt, Value};
+///
+/// let value = Value::Int(1);
+/// let err = value.get_list_typed::<i32>("Pos")
+///     .with_context(|| "while reading the player position")
+///     .unwrap_err();
+/// assert_eq!(err.to_string(),
+///            "while reading the player position: encountered NBT tag 'TAG_Int' but expected 'TAG_Compound'");
+/// ```
+pub trait ResultExt<T> {
+    /// Wraps the error, if any, in `Error::Context` with the description
+    /// returned by `f`. The closure is only called on failure.
+    fn with_context<F, S>(self, f: F) -> Result<T>
+        where F: FnOnce() -> S, S: Into<String>;
+}
+
+impl<T> ResultExt<T> for Result<T> {
+    fn with_context<F, S>(self, f: F) -> Result<T>
+        where F: FnOnce() -> S, S: Into<String>
+    {
+        self.map_err(|e| Error::Context(f().into(), Box::new(e)))
+    }
+}
+
+impl Error {
+    /// Formats this error as a message for users, naming the path of the
+    /// value at fault for `Error::AtPath`, e.g. `Error at path
+    /// "Level.Sections.2.Blocks": ...`. Other errors are formatted as by
+    /// `Display`.
+    ///
+    /// ```rust
+    /// use nbt::{Error, ParseConfig, TagId, Value};
+    ///
+    /// // A compound whose entry "x" is cut short.
+    /// let bytes = vec![0x03, 0x00, 0x01, b'x', 0x00, 0x00];
+    /// let config = ParseConfig { track_paths: true, ..Default::default() };
+    /// let err = Value::from_reader_with_config(TagId::Compound, &mut &bytes[..], &config).unwrap_err();
+    /// assert_eq!(err, Error::AtPath("x".to_string(), Box::new(Error::IncompleteNbtValue)));
+    /// assert_eq!(err.pretty_print(), "Error at path \"x\": data does not represent a complete NbtValue");
+    /// ```
+    pub fn pretty_print(&self) -> String {
+        match *self {
+            Error::AtPath(ref path, ref e) => format!("Error at path \"{}\": {}", path, e),
+            Error::Context(ref context, ref e) => format!("{}: {}", context, e.pretty_print()),
+            ref e => e.to_string(),
+        }
+    }
 }
 
 impl fmt::Display for Error {
+    #[allow(clippy::needless_borrowed_reference)]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             &Error::IoError(ref e)     => e.fmt(f),
             #[cfg(feature = "serde")]
             &Error::Serde(ref msg)     => write!(f, "{}", msg),
             &Error::InvalidTypeId(t)   => write!(f, "invalid NBT tag byte: '{}'", t),
+            &Error::HeterogeneousList { index, expected, got } => write!(f, "list element {} is a '{}', but the list holds '{}'", index, got, expected),
             &Error::TagMismatch(a, b)  => write!(f, "encountered NBT tag '{}' but expected '{}'", a, b),
             &Error::NonBooleanByte(b)  => write!(f, "encountered a byte value '{}' inside a boolean", b),
             &Error::UnexpectedField(ref name) => write!(f, "encountered an unexpected field '{}'", name),
             &Error::UnrepresentableType(ref name) => write!(f, "encountered type '{}', which has no meaningful NBT representation", name),
+            #[cfg(feature = "uuid")]
+            &Error::InvalidUuidLength(n) => write!(f, "a UUID must be an int array of length 4, not {}", n),
+            &Error::NumericOutOfRange(v) => write!(f, "the value '{}' is out of range for the target tag type", v),
+            &Error::DuplicateKey(ref name) => write!(f, "encountered duplicate key '{}'", name),
+            &Error::MissingField(ref name) => write!(f, "missing required field '{}'", name),
+            &Error::InvalidDebugFormat(pos) => write!(f, "invalid Value debug representation at byte {}", pos),
+            &Error::InvalidListElement(i, ref e) => write!(f, "list element {} could not be converted: {}", i, e),
+            &Error::PathNotFound(ref path) => write!(f, "no value exists at path '{}'", path),
+            &Error::RootNameMismatch { ref expected, ref got } => write!(f, "expected a root compound named '{}', but found '{}'", expected, got),
+            &Error::TruncatedArray { tag_id, declared_len, bytes_available } => write!(f, "array with tag '{}' declares {} elements, but only {} bytes are available", tag_id, declared_len, bytes_available),
+            &Error::Context(ref context, ref e) => write!(f, "{}: {}", context, e),
+            &Error::UnalignedBytes(n) => write!(f, "{} bytes cannot be split into 4-byte integers", n),
+            &Error::DepthLimitExceeded(max) => write!(f, "values are nested more than {} levels deep", max),
+            &Error::LengthLimitExceeded(n) => write!(f, "an array or list of {} elements is longer than allowed", n),
+            &Error::DataVersionDowngrade { from, to } => write!(f, "cannot migrate data from data version {} to the older version {}", from, to),
+            &Error::Truncated { bytes_read, .. } => write!(f, "the byte limit was reached after {} bytes, before the value was complete", bytes_read),
+            &Error::StringTooLong(n) => write!(f, "a string of {} encoded bytes is longer than the 65535 allowed", n),
+            #[cfg(feature = "json")]
+            &Error::InvalidTypedJson(ref msg) => write!(f, "invalid typed JSON: {}", msg),
+            &Error::AtPath(ref path, ref e) => write!(f, "at {}: {}", path, e),
+            &Error::WriteError { ref io, bytes_written } => write!(f, "{} (after writing {} bytes)", io, bytes_written),
+            &Error::UnsupportedOperands(a, b) => write!(f, "cannot add '{}' and '{}'", a, b),
             // Static messages should suffice for the remaining errors.
             other => write!(f, "{}", other.description()),
         }
@@ -77,7 +246,7 @@ impl StdError for Error {
             #[cfg(feature = "serde")]
             Error::Serde(ref msg)     => &msg[..],
             Error::InvalidTypeId(_)   => "invalid NBT tag byte",
-            Error::HeterogeneousList  => "values in NBT Lists must be homogeneous",
+            Error::HeterogeneousList { .. } => "values in NBT Lists must be homogeneous",
             Error::NoRootCompound     => "the root value must be Compound-like (tag = 0x0a)",
             Error::InvalidUtf8        => "a string is not valid UTF-8",
             Error::IncompleteNbtValue => "data does not represent a complete NbtValue",
@@ -86,6 +255,32 @@ impl StdError for Error {
             Error::UnexpectedField(_) => "encountered an unexpected field",
             Error::NonBooleanByte(_)  => "encountered a non-boolean byte value inside a boolean",
             Error::UnrepresentableType(_) => "encountered a type with no meaningful NBT representation",
+            #[cfg(feature = "uuid")]
+            Error::InvalidUuidLength(_) => "a UUID must be an int array of length 4",
+            Error::NumericOutOfRange(_) => "a numeric value is out of range for the target tag type",
+            Error::DuplicateKey(_) => "encountered a duplicate key in a compound",
+            Error::ArithmeticOverflow => "arithmetic overflowed the tag type or divided by zero",
+            Error::MissingField(_) => "a required field is missing",
+            Error::InvalidPatch => "the binary diff is malformed or does not match its base",
+            Error::InvalidDebugFormat(_) => "invalid Value debug representation",
+            Error::InvalidListElement(_, _) => "a list element could not be converted",
+            Error::PathNotFound(_) => "no value exists at the given path",
+            Error::RootNameMismatch { .. } => "the root compound does not have the expected name",
+            Error::TruncatedArray { .. } => "an array is shorter than its declared length",
+            Error::Context(_, _) => "an error occurred in the described context",
+            Error::UnalignedBytes(_) => "the number of bytes is not a multiple of the element size",
+            Error::NotGzip => "the data is not gzip-compressed (missing gzip magic bytes)",
+            Error::DepthLimitExceeded(_) => "values are nested more deeply than allowed",
+            Error::LengthLimitExceeded(_) => "an array or list is longer than allowed",
+            Error::DataVersionDowngrade { .. } => "data cannot be migrated to an older data version",
+            Error::Truncated { .. } => "the byte limit was reached before the value was complete",
+            Error::CursorAtRoot => "the cursor is already at the root",
+            Error::StringTooLong(_) => "a string is too long to be written",
+            #[cfg(feature = "json")]
+            Error::InvalidTypedJson(_) => "the JSON does not encode a value",
+            Error::AtPath(_, _) => "a value could not be read",
+            Error::WriteError { .. } => "an I/O error occurred while writing a value",
+            Error::UnsupportedOperands(_, _) => "the tags cannot be added together",
         }
     }
 
@@ -96,6 +291,10 @@ impl StdError for Error {
     fn cause(&self) -> Option<&StdError> {
         match *self {
             Error::IoError(ref e) => e.cause(),
+            Error::WriteError { ref io, .. } => Some(io),
+            Error::AtPath(_, ref e) => Some(&**e),
+            Error::Context(_, ref e) => Some(&**e),
+            Error::InvalidListElement(_, ref e) => Some(&**e),
             _ => None
         }
     }
@@ -103,8 +302,9 @@ impl StdError for Error {
 
 // Implement PartialEq manually, since std::io::Error is not PartialEq.
 impl PartialEq<Error> for Error {
+    #[allow(clippy::needless_borrowed_reference)]
     fn eq(&self, other: &Error) -> bool {
-        use Error::{IoError, InvalidTypeId, HeterogeneousList, NoRootCompound,
+        use Error::{IoError, InvalidTypeId, NoRootCompound,
                     InvalidUtf8, IncompleteNbtValue, TagMismatch, UnexpectedField, NonBooleanByte,
                     UnrepresentableType};
 
@@ -113,7 +313,6 @@ impl PartialEq<Error> for Error {
             #[cfg(feature = "serde")]
             (&Error::Serde(_), &Error::Serde(_))       => true,
             (&InvalidTypeId(a), &InvalidTypeId(b))     => a == b,
-            (&HeterogeneousList, &HeterogeneousList)   => true,
             (&NoRootCompound, &NoRootCompound)         => true,
             (&InvalidUtf8, &InvalidUtf8)               => true,
             (&IncompleteNbtValue, &IncompleteNbtValue) => true,
@@ -121,11 +320,41 @@ impl PartialEq<Error> for Error {
             (&UnexpectedField(ref a), &UnexpectedField(ref b)) => a == b,
             (&NonBooleanByte(a), &NonBooleanByte(b))   => a == b,
             (&UnrepresentableType(ref a), &UnrepresentableType(ref b)) => a == b,
+            #[cfg(feature = "uuid")]
+            (&Error::InvalidUuidLength(a), &Error::InvalidUuidLength(b)) => a == b,
+            (&Error::NumericOutOfRange(a), &Error::NumericOutOfRange(b)) => a == b,
+            (&Error::DuplicateKey(ref a), &Error::DuplicateKey(ref b)) => a == b,
+            (&Error::ArithmeticOverflow, &Error::ArithmeticOverflow) => true,
+            (&Error::MissingField(ref a), &Error::MissingField(ref b)) => a == b,
+            (&Error::InvalidPatch, &Error::InvalidPatch) => true,
+            (&Error::InvalidDebugFormat(a), &Error::InvalidDebugFormat(b)) => a == b,
+            (&Error::InvalidListElement(a, ref e), &Error::InvalidListElement(b, ref f)) => a == b && e == f,
+            (&Error::PathNotFound(ref a), &Error::PathNotFound(ref b)) => a == b,
+            (&Error::RootNameMismatch { expected: ref a, got: ref b }, &Error::RootNameMismatch { expected: ref c, got: ref d }) => a == c && b == d,
+            (&Error::TruncatedArray { tag_id: a, declared_len: b, bytes_available: c }, &Error::TruncatedArray { tag_id: d, declared_len: e, bytes_available: f }) => a == d && b == e && c == f,
+            (&Error::Context(ref a, ref e), &Error::Context(ref b, ref f)) => a == b && e == f,
+            (&Error::UnalignedBytes(a), &Error::UnalignedBytes(b)) => a == b,
+            (&Error::NotGzip, &Error::NotGzip) => true,
+            (&Error::HeterogeneousList { index: a, expected: b, got: c }, &Error::HeterogeneousList { index: d, expected: e, got: f }) => a == d && b == e && c == f,
+            (&Error::DepthLimitExceeded(a), &Error::DepthLimitExceeded(b)) => a == b,
+            (&Error::LengthLimitExceeded(a), &Error::LengthLimitExceeded(b)) => a == b,
+            (&Error::DataVersionDowngrade { from: a, to: b }, &Error::DataVersionDowngrade { from: c, to: d }) => a == c && b == d,
+            (&Error::Truncated { partial: ref a, bytes_read: m }, &Error::Truncated { partial: ref b, bytes_read: n }) => a == b && m == n,
+            (&Error::CursorAtRoot, &Error::CursorAtRoot) => true,
+            (&Error::StringTooLong(a), &Error::StringTooLong(b)) => a == b,
+            #[cfg(feature = "json")]
+            (&Error::InvalidTypedJson(ref a), &Error::InvalidTypedJson(ref b)) => a == b,
+            (&Error::AtPath(ref a, ref e), &Error::AtPath(ref b, ref f)) => a == b && e == f,
+            (&Error::WriteError { bytes_written: a, .. }, &Error::WriteError { bytes_written: b, .. }) => a == b,
+            (&Error::UnsupportedOperands(a, b), &Error::UnsupportedOperands(c, d)) => a == c && b == d,
             _ => false
         }
     }
 }
 
+/// I/O errors are wrapped in `Error::IoError`, except for unexpected
+/// end-of-file errors, which indicate truncated data and so become
+/// `Error::IncompleteNbtValue`.
 impl From<io::Error> for Error {
     fn from(e: io::Error) -> Error {
         use std::io::ErrorKind;
@@ -137,6 +366,8 @@ impl From<io::Error> for Error {
     }
 }
 
+/// Strings in NBT data are encoded as Modified UTF-8 (CESU-8), so decoding
+/// failures become `Error::InvalidUtf8`.
 impl From<cesu8::Cesu8DecodingError> for Error {
     fn from(_: cesu8::Cesu8DecodingError) -> Error {
         Error::InvalidUtf8
@@ -147,6 +378,7 @@ impl From<Error> for io::Error {
     fn from(e: Error) -> io::Error {
         match e {
             Error::IoError(e) => e,
+            Error::WriteError { io, .. } => io,
             Error::InvalidTypeId(id) =>
                 io::Error::new(InvalidInput, &format!("invalid NBT tag byte: {}", id)[..]),
             Error::TagMismatch(a, b) =>
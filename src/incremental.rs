@@ -0,0 +1,152 @@
+//! Compounds that remember their serialized form between writes.
+
+use std::collections::HashMap;
+use std::io;
+
+use byteorder::WriteBytesExt;
+
+use error::Result;
+use raw;
+use value::Value;
+
+/// A `TAG_Compound` that keeps the serialized bytes of each of its entries,
+/// so that writing it again only re-serializes the entries that changed.
+///
+/// Every mutating method marks the compound (and the affected entry) dirty;
+/// `to_writer_incremental` clears the flags and copies the bytes of clean
+/// entries as they were last written.
+///
+/// ```rust
+/// use nbt::incremental::IncrementalCompound;
+/// use nbt::Value;
+///
+/// let mut chunk = IncrementalCompound::new();
+/// chunk.insert("LastUpdate", Value::Long(100));
+/// chunk.insert("Sections", Value::List(vec![]));
+///
+/// let mut first = Vec::new();
+/// chunk.to_writer_incremental(&mut first).unwrap();
+/// assert!(!chunk.is_dirty());
+///
+/// *chunk.get_mut("LastUpdate").unwrap() = Value::Long(101);
+/// assert!(chunk.is_dirty());
+///
+/// let mut second = Vec::new();
+/// chunk.to_writer_incremental(&mut second).unwrap();
+/// assert_eq!(first.len(), second.len());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct IncrementalCompound {
+    entries: HashMap<String, Entry>,
+    /// The payload as last written, or `None` if anything has changed since.
+    cached: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    value: Value,
+    /// The header and payload of this entry as last written.
+    bytes: Option<Vec<u8>>,
+}
+
+impl IncrementalCompound {
+    /// Creates an empty compound.
+    pub fn new() -> IncrementalCompound {
+        IncrementalCompound::default()
+    }
+
+    /// The number of entries in this compound.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if this compound has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns `true` if this compound has been modified since it was last
+    /// written with `to_writer_incremental`.
+    pub fn is_dirty(&self) -> bool {
+        self.cached.is_none()
+    }
+
+    /// Returns the value stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+
+    /// Returns a mutable reference to the value stored under `key`, if any.
+    ///
+    /// The entry is assumed to be modified, and will be re-serialized on the
+    /// next write.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        let entry = self.entries.get_mut(key)?;
+        entry.bytes = None;
+        self.cached = None;
+        Some(&mut entry.value)
+    }
+
+    /// Stores `value` under `key`, returning the value it replaced, if any.
+    pub fn insert<S, V>(&mut self, key: S, value: V) -> Option<Value>
+        where S: Into<String>, V: Into<Value>
+    {
+        self.cached = None;
+        let entry = Entry { value: value.into(), bytes: None };
+        self.entries.insert(key.into(), entry).map(|old| old.value)
+    }
+
+    /// Removes and returns the value stored under `key`, if any.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let old = self.entries.remove(key)?;
+        self.cached = None;
+        Some(old.value)
+    }
+
+    /// Converts this compound back into a `Value::Compound`, discarding any
+    /// cached bytes.
+    pub fn into_value(self) -> Value {
+        Value::Compound(self.entries.into_iter().map(|(k, e)| (k, e.value)).collect())
+    }
+
+    /// Writes the payload of this compound to an `io::Write` destination, in
+    /// the same format as `Value::to_writer`.
+    ///
+    /// Only entries that changed since the last call are serialized again;
+    /// the bytes of all other entries are copied from the previous write.
+    pub fn to_writer_incremental<W>(&mut self, dst: &mut W) -> Result<()>
+        where W: io::Write
+    {
+        if let Some(ref payload) = self.cached {
+            return dst.write_all(payload).map_err(From::from);
+        }
+
+        let mut payload = Vec::new();
+        for (name, entry) in &mut self.entries {
+            if entry.bytes.is_none() {
+                let mut bytes = Vec::new();
+                bytes.write_u8(entry.value.id())?;
+                raw::write_bare_string(&mut bytes, name)?;
+                entry.value.to_writer(&mut bytes)?;
+                entry.bytes = Some(bytes);
+            }
+            if let Some(ref bytes) = entry.bytes {
+                payload.extend_from_slice(bytes);
+            }
+        }
+        payload.push(0x00); // TAG_End
+
+        dst.write_all(&payload)?;
+        self.cached = Some(payload);
+        Ok(())
+    }
+}
+
+impl From<HashMap<String, Value>> for IncrementalCompound {
+    fn from(map: HashMap<String, Value>) -> IncrementalCompound {
+        let entries = map.into_iter()
+            .map(|(k, value)| (k, Entry { value, bytes: None }))
+            .collect();
+        IncrementalCompound { entries, cached: None }
+    }
+}
@@ -0,0 +1,181 @@
+//! A type-preserving encoding of `Value`s as JSON.
+//!
+//! JSON has a single number type, so numbers and arrays are wrapped in an
+//! object naming their tag type, e.g. `{"__nbt_type": "byte", "value": 42}`
+//! or `{"__nbt_type": "int_array", "value": [1, 2]}`. Strings, lists and
+//! compounds are written as JSON strings, arrays and objects. A compound with
+//! an entry named `__nbt_type` is itself wrapped, with the type `compound`,
+//! so that it cannot be mistaken for a wrapped value.
+//!
+//! Non-finite floats are written as the strings `"NaN"`, `"Infinity"` and
+//! `"-Infinity"`, which JSON numbers cannot express. Empty lists do not
+//! record their element type, as with `Value::List` itself.
+
+use std::collections::HashMap;
+
+use serde_json::{self, Map, Number};
+
+use error::{Error, Result};
+use tag::TagId;
+use value::Value;
+
+/// The key naming the tag type of a wrapped value.
+const TYPE_KEY: &str = "__nbt_type";
+
+impl Value {
+    /// Encodes this value as JSON, preserving its tag types. See the
+    /// `Value::to_json_map` example.
+    pub fn to_typed_json(&self) -> serde_json::Value {
+        match *self {
+            Value::Byte(v) => wrap("byte", v.into()),
+            Value::Short(v) => wrap("short", v.into()),
+            Value::Int(v) => wrap("int", v.into()),
+            Value::Long(v) => wrap("long", v.into()),
+            Value::Float(v) => wrap("float", float_to_json(v as f64)),
+            Value::Double(v) => wrap("double", float_to_json(v)),
+            Value::ByteArray(ref v) => wrap("byte_array", v.clone().into()),
+            Value::IntArray(ref v) => wrap("int_array", v.clone().into()),
+            Value::LongArray(ref v) => wrap("long_array", v.clone().into()),
+            Value::String(ref v) => serde_json::Value::String(v.clone()),
+            Value::List(ref vals) => {
+                serde_json::Value::Array(vals.iter().map(Value::to_typed_json).collect())
+            },
+            Value::Compound(ref map) => {
+                let object = serde_json::Value::Object(compound_to_json(map));
+                if map.contains_key(TYPE_KEY) {
+                    wrap("compound", object)
+                } else {
+                    object
+                }
+            },
+        }
+    }
+
+    /// Encodes a `Compound` as a JSON object, preserving the tag types of
+    /// its entries.
+    ///
+    /// Returns `Error::TagMismatch` if this is not a compound.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate nbt;
+    /// # fn main() {
+    /// use nbt::Value;
+    ///
+    /// let value = compound! { "Count" => 42i8, "id" => "minecraft:stone" };
+    /// let map = value.to_json_map().unwrap();
+    /// assert_eq!(map["Count"].to_string(), r#"{"__nbt_type":"byte","value":42}"#);
+    /// assert_eq!(map["id"], "minecraft:stone");
+    /// assert_eq!(Value::from_json_map(&map), Ok(value));
+    /// # }
+    /// ```
+    pub fn to_json_map(&self) -> Result<Map<String, serde_json::Value>> {
+        match *self {
+            Value::Compound(ref map) => Ok(compound_to_json(map)),
+            _ => Err(Error::TagMismatch(self.tag_id(), TagId::Compound)),
+        }
+    }
+
+    /// Decodes a value encoded by `Value::to_typed_json`.
+    ///
+    /// Returns `Error::InvalidTypedJson` if `json` is not in that encoding,
+    /// and `Error::NumericOutOfRange` if a number does not fit its tag type.
+    pub fn from_typed_json(json: &serde_json::Value) -> Result<Value> {
+        match *json {
+            serde_json::Value::String(ref s) => Ok(Value::String(s.clone())),
+            serde_json::Value::Array(ref vals) => {
+                let vals = vals.iter().map(Value::from_typed_json).collect::<Result<Vec<_>>>()?;
+                if let Some(first) = vals.first() {
+                    if let Some(index) = vals.iter().position(|v| v.tag_id() != first.tag_id()) {
+                        return Err(Error::HeterogeneousList {
+                            index,
+                            expected: first.tag_id(),
+                            got: vals[index].tag_id(),
+                        });
+                    }
+                }
+                Ok(Value::List(vals))
+            },
+            serde_json::Value::Object(ref map) => match map.get(TYPE_KEY) {
+                Some(tag) => unwrap(tag, map),
+                None => Value::from_json_map(map),
+            },
+            _ => Err(Error::InvalidTypedJson(format!("unexpected JSON value {}", json))),
+        }
+    }
+
+    /// Decodes a compound encoded by `Value::to_json_map`.
+    pub fn from_json_map(map: &Map<String, serde_json::Value>) -> Result<Value> {
+        let mut compound = HashMap::with_capacity(map.len());
+        for (key, json) in map {
+            compound.insert(key.clone(), Value::from_typed_json(json)?);
+        }
+        Ok(Value::Compound(compound))
+    }
+}
+
+fn compound_to_json(map: &HashMap<String, Value>) -> Map<String, serde_json::Value> {
+    map.iter().map(|(k, v)| (k.clone(), v.to_typed_json())).collect()
+}
+
+fn wrap(tag: &str, value: serde_json::Value) -> serde_json::Value {
+    let mut map = Map::new();
+    map.insert(TYPE_KEY.to_string(), tag.into());
+    map.insert("value".to_string(), value);
+    serde_json::Value::Object(map)
+}
+
+fn float_to_json(v: f64) -> serde_json::Value {
+    match Number::from_f64(v) {
+        Some(n) => serde_json::Value::Number(n),
+        None if v.is_nan() => "NaN".into(),
+        None if v > 0.0 => "Infinity".into(),
+        None => "-Infinity".into(),
+    }
+}
+
+fn unwrap(tag: &serde_json::Value, map: &Map<String, serde_json::Value>) -> Result<Value> {
+    let invalid = || Error::InvalidTypedJson(format!("malformed wrapped value {:?}", map));
+    let value = match map.get("value") {
+        Some(value) if map.len() == 2 => value,
+        _ => return Err(invalid()),
+    };
+    match tag.as_str() {
+        Some("byte") => Ok(Value::Byte(narrow(value.as_i64().ok_or_else(invalid)?)?)),
+        Some("short") => Ok(Value::Short(narrow(value.as_i64().ok_or_else(invalid)?)?)),
+        Some("int") => Ok(Value::Int(narrow(value.as_i64().ok_or_else(invalid)?)?)),
+        Some("long") => Ok(Value::Long(value.as_i64().ok_or_else(invalid)?)),
+        Some("float") => Ok(Value::Float(json_to_f64(value).ok_or_else(invalid)? as f32)),
+        Some("double") => Ok(Value::Double(json_to_f64(value).ok_or_else(invalid)?)),
+        Some("byte_array") => Ok(Value::ByteArray(json_to_array(value).ok_or_else(invalid)??)),
+        Some("int_array") => Ok(Value::IntArray(json_to_array(value).ok_or_else(invalid)??)),
+        Some("long_array") => Ok(Value::LongArray(json_to_array(value).ok_or_else(invalid)??)),
+        Some("compound") => match *value {
+            serde_json::Value::Object(ref map) => Value::from_json_map(map),
+            _ => Err(invalid()),
+        },
+        _ => Err(invalid()),
+    }
+}
+
+fn json_to_f64(json: &serde_json::Value) -> Option<f64> {
+    match json.as_str() {
+        Some("NaN") => Some(f64::NAN),
+        Some("Infinity") => Some(f64::INFINITY),
+        Some("-Infinity") => Some(f64::NEG_INFINITY),
+        Some(_) => None,
+        None => json.as_f64(),
+    }
+}
+
+fn narrow<T>(v: i64) -> Result<T> where T: ::std::convert::TryFrom<i64> {
+    T::try_from(v).map_err(|_| Error::NumericOutOfRange(v))
+}
+
+/// Decodes a JSON array of integers, or returns `None` if `json` is not one.
+fn json_to_array<T>(json: &serde_json::Value) -> Option<Result<Vec<T>>>
+    where T: ::std::convert::TryFrom<i64>
+{
+    let vals = json.as_array()?;
+    let ints = vals.iter().map(serde_json::Value::as_i64).collect::<Option<Vec<_>>>()?;
+    Some(ints.into_iter().map(narrow).collect())
+}
@@ -0,0 +1,114 @@
+//! Compounds whose entries are only parsed when they are looked up.
+
+use std::collections::HashMap;
+
+use error::{Error, Result};
+use raw;
+use tag::TagId;
+use value::Value;
+
+/// A `TAG_Compound` that borrows the serialized bytes of its entries, and only
+/// parses an entry when it is accessed.
+///
+/// Creating one skips over every entry to find where each begins, which is
+/// much cheaper than decoding it. This suits loading large compounds of which
+/// only a few entries are needed, such as the `Data` compound of `level.dat`.
+/// Entries are parsed again each time they are looked up.
+///
+/// ```rust
+/// use nbt::lazy::LazyCompound;
+/// use nbt::Value;
+///
+/// let bytes = vec![
+///     0x0a, 0x00, 0x00,
+///     0x03, 0x00, 0x01, b'x', 0x00, 0x00, 0x00, 0x05,
+///     0x08, 0x00, 0x01, b'y', 0x00, 0x02, b'h', b'i',
+///     0x00,
+/// ];
+/// let (name, compound) = LazyCompound::from_document(&bytes).unwrap();
+/// assert_eq!(name, "");
+/// assert_eq!(compound.len(), 2);
+/// assert_eq!(compound.get("x").unwrap(), Some(Value::Int(5)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct LazyCompound<'a> {
+    entries: HashMap<String, (TagId, &'a [u8])>,
+}
+
+impl<'a> LazyCompound<'a> {
+    /// Indexes a complete document whose root is a compound, returning the
+    /// name of the root along with its entries.
+    ///
+    /// Returns `Error::NoRootCompound` if the root is another tag.
+    pub fn from_document(bytes: &'a [u8]) -> Result<(String, LazyCompound<'a>)> {
+        let mut src = bytes;
+        let (tag, name) = raw::emit_next_header(&mut src)?;
+        if tag != TagId::Compound {
+            return Err(Error::NoRootCompound);
+        }
+        Ok((name, LazyCompound::from_payload(src)?))
+    }
+
+    /// Indexes the payload of a compound, i.e. its entries up to and
+    /// including the closing `TAG_End`, as written by `Value::to_writer`.
+    /// Any bytes after the end of the compound are ignored.
+    pub fn from_payload(bytes: &'a [u8]) -> Result<LazyCompound<'a>> {
+        let mut entries = HashMap::new();
+        let mut src = bytes;
+        loop {
+            let (tag, name) = raw::emit_next_header(&mut src)?;
+            if tag == TagId::End {
+                break;
+            }
+            let start = src;
+            raw::skip_payload(&mut src, tag)?;
+            let payload = &start[..start.len() - src.len()];
+            entries.insert(name, (tag, payload));
+        }
+        Ok(LazyCompound { entries })
+    }
+
+    /// The number of entries in this compound.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if this compound has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns `true` if this compound has an entry named `key`.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Iterates over the names of the entries, in no particular order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|k| &k[..])
+    }
+
+    /// Returns the tag type and serialized payload of the entry `key`,
+    /// without parsing it.
+    pub fn get_raw(&self, key: &str) -> Option<(TagId, &'a [u8])> {
+        self.entries.get(key).cloned()
+    }
+
+    /// Parses and returns the entry `key`, or `None` if there is no such
+    /// entry.
+    pub fn get(&self, key: &str) -> Result<Option<Value>> {
+        match self.entries.get(key) {
+            Some(&(tag, mut payload)) => Value::from_reader(tag, &mut payload).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Parses every entry, returning the whole compound as a `Value`.
+    pub fn to_value(&self) -> Result<Value> {
+        let mut map = HashMap::with_capacity(self.entries.len());
+        for (key, &(tag, mut payload)) in &self.entries {
+            map.insert(key.clone(), Value::from_reader(tag, &mut payload)?);
+        }
+        Ok(Value::Compound(map))
+    }
+}
@@ -6,8 +6,10 @@ extern crate flate2;
 
 /* Re-export the core API from submodules. */
 pub use blob::Blob;
-pub use error::{Error, Result};
-pub use value::Value;
+pub use config::ParseConfig;
+pub use error::{Error, Result, ResultExt};
+pub use tag::TagId;
+pub use value::{NbtListIntoIter, Value};
 
 #[cfg(feature = "serde")]
 #[doc(inline)]
@@ -16,15 +18,81 @@ pub use de::{from_reader, from_gzip_reader, from_zlib_reader};
 #[doc(inline)]
 pub use ser::{to_writer, to_gzip_writer, to_zlib_writer};
 
+/// Creates a `Value::Compound` from a list of `"key" => value` pairs.
+///
+/// Keys may be anything that converts into a `String`, and values anything
+/// that converts into a `Value`. Repeating a key is almost certainly a
+/// mistake, so it panics in debug builds; otherwise the last value wins.
+///
+/// ```
+/// #[macro_use] extern crate nbt;
+///
+/// # fn main() {
+/// let value = compound! {
+///     "name" => "Steve",
+///     "health" => 20.0f32,
+///     "pos" => compound! { "x" => 1, "y" => 64, "z" => -3 },
+/// };
+/// match value {
+///     nbt::Value::Compound(ref map) => assert_eq!(map["health"], nbt::Value::Float(20.0)),
+///     _ => unreachable!(),
+/// }
+/// # }
+/// ```
+#[macro_export]
+macro_rules! compound {
+    ($($key:expr => $value:expr),* $(,)*) => {{
+        #[allow(unused_mut)]
+        let mut map = ::std::collections::HashMap::new();
+        $(
+            let key = ::std::string::String::from($key);
+            debug_assert!(!map.contains_key(&key), "duplicate key {:?} in compound!", key);
+            map.insert(key, $crate::Value::from($value));
+        )*
+        $crate::Value::Compound(map)
+    }};
+}
+
 mod raw;
 mod blob;
+mod config;
 mod error;
+mod pretty_debug;
+mod tag;
 mod value;
 
+pub mod builder;
+pub mod convert;
+pub mod cursor;
+pub mod dfm;
+pub mod diff;
+pub mod incremental;
+#[cfg(unix)]
+pub mod lock;
+pub mod minecraft;
+pub mod order;
+pub mod patch;
+pub mod reader;
+pub mod stream;
+pub mod string;
+pub mod writer;
+
 #[cfg(feature = "serde")] #[macro_use] extern crate serde;
+#[cfg(feature = "cache")] extern crate lru;
+#[cfg(feature = "checksum")] extern crate sha2;
+#[cfg(feature = "uuid")] extern crate uuid;
+#[cfg(feature = "json")] extern crate serde_json;
+#[cfg(unix)] extern crate libc;
 
 #[cfg(feature = "serde")] #[macro_use] mod macros;
 #[cfg(feature = "serde")] pub mod de;
 #[cfg(feature = "serde")] pub mod ser;
+#[cfg(feature = "serde")] pub mod tag_type;
+
+#[cfg(feature = "cache")] pub mod cache;
+#[cfg(feature = "lazy")] pub mod lazy;
+#[cfg(feature = "checksum")] mod checksum;
+#[cfg(feature = "uuid")] mod uuid_support;
+#[cfg(feature = "json")] mod json_support;
 
 #[cfg(test)] mod tests;
@@ -0,0 +1,105 @@
+//! Exclusive access to NBT files shared between processes.
+//!
+//! Locks are taken with `flock(2)`, so this module is only available on Unix.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::os::raw::c_int;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use libc;
+
+use error::{Error, Result};
+use raw;
+use tag::TagId;
+use value::Value;
+use writer::{Compression, NbtWriterBuilder};
+
+/// A guard holding an exclusive lock on a gzip-compressed NBT file, such as
+/// `level.dat`, so that other processes using `NbtFileLock` wait rather than
+/// overwrite each other's changes.
+///
+/// The lock is advisory: it is only respected by programs that also lock the
+/// file. It is released when the guard is dropped, whether or not the file
+/// was saved.
+///
+/// ```rust,no_run
+/// use nbt::lock::NbtFileLock;
+/// use nbt::Value;
+///
+/// let (name, mut level, lock) = NbtFileLock::open("world/level.dat").unwrap();
+/// if let Some(data) = level.get_mut("Data") {
+///     data.insert_or_replace("raining", 0i8).unwrap();
+/// }
+/// lock.save_and_close(&name, &level).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct NbtFileLock {
+    file: File,
+}
+
+impl NbtFileLock {
+    /// Opens the file at `path`, waits until it can be locked exclusively,
+    /// and reads it, returning the name of its root and the root itself along
+    /// with the guard.
+    ///
+    /// Returns `Error::NotGzip` if the file is not gzip-compressed, and
+    /// `Error::NoRootCompound` if its root is not a compound.
+    pub fn open<P>(path: P) -> Result<(String, Value, NbtFileLock)>
+        where P: AsRef<Path>
+    {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        flock(&file, libc::LOCK_EX)?;
+        let lock = NbtFileLock { file };
+
+        let mut src = GzDecoder::new(raw::check_gzip_magic(io::BufReader::new(&lock.file))?)?;
+        let (tag, name) = raw::emit_next_header(&mut src)?;
+        if tag != TagId::Compound {
+            return Err(Error::NoRootCompound);
+        }
+        let value = Value::from_reader(tag, &mut src)?;
+        Ok((name, value, lock))
+    }
+
+    /// Replaces the contents of the file with `value`, as the root of a
+    /// gzip-compressed document named `name`, and then releases the lock.
+    ///
+    /// The document is encoded in full before the file is touched, so an
+    /// error while encoding leaves the file as it was.
+    pub fn save_and_close(mut self, name: &str, value: &Value) -> Result<()> {
+        // The file is rewritten in place rather than replaced by a renamed
+        // temporary file: other processes waiting for the lock hold the
+        // original file open, and would otherwise go on to read a stale one.
+        let mut bytes = Vec::new();
+        NbtWriterBuilder::new().compression(Compression::Gzip).build()
+            .write(&mut bytes, name, value)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&bytes)?;
+        self.file.set_len(bytes.len() as u64)?;
+        self.file.sync_all()?;
+        Ok(())
+    }
+}
+
+impl Drop for NbtFileLock {
+    fn drop(&mut self) {
+        // Closing the file would release the lock anyway; unlocking first
+        // makes the release explicit. Errors cannot be reported from here.
+        let _ = flock(&self.file, libc::LOCK_UN);
+    }
+}
+
+/// Applies or removes an advisory lock on `file`, retrying if interrupted.
+fn flock(file: &File, operation: c_int) -> io::Result<()> {
+    loop {
+        if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+            return Ok(());
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
@@ -0,0 +1,379 @@
+//! Helpers for working with data that is specific to Minecraft's use of the
+//! NBT format, rather than the format itself.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use error::{Error, Result};
+use tag::TagId;
+use value::Value;
+
+/// The base colours used by map items, indexed by their colour ID.
+///
+/// Each base colour is drawn in four shades; the byte stored in a map's
+/// `colors` array is `base_id * 4 + shade`. Base colour `0` is transparent.
+pub const MAP_BASE_COLORS: [[u8; 3]; 62] = [
+    [0, 0, 0],       [127, 178, 56],  [247, 233, 163], [199, 199, 199],
+    [255, 0, 0],     [160, 160, 255], [167, 167, 167], [0, 124, 0],
+    [255, 255, 255], [164, 168, 184], [151, 109, 77],  [112, 112, 112],
+    [64, 64, 255],   [143, 119, 72],  [255, 252, 245], [216, 127, 51],
+    [178, 76, 216],  [102, 153, 216], [229, 229, 51],  [127, 204, 25],
+    [242, 127, 165], [76, 76, 76],    [153, 153, 153], [76, 127, 153],
+    [127, 63, 178],  [51, 76, 178],   [102, 76, 51],   [102, 127, 51],
+    [153, 51, 51],   [25, 25, 25],    [250, 238, 77],  [92, 219, 213],
+    [74, 128, 255],  [0, 217, 58],    [129, 86, 49],   [112, 2, 0],
+    [209, 177, 161], [159, 82, 36],   [149, 87, 108],  [112, 108, 138],
+    [186, 133, 36],  [103, 117, 53],  [160, 77, 78],   [57, 41, 35],
+    [135, 107, 98],  [87, 92, 92],    [122, 73, 88],   [76, 62, 92],
+    [76, 50, 35],    [76, 82, 42],    [142, 60, 46],   [37, 22, 16],
+    [189, 48, 49],   [148, 63, 97],   [92, 25, 29],    [22, 126, 134],
+    [58, 142, 140],  [86, 44, 62],    [20, 180, 133],  [100, 100, 100],
+    [216, 175, 147], [127, 167, 150],
+];
+
+/// The brightness multipliers (out of 255) for each of the four shades of a
+/// map base colour.
+pub const MAP_SHADE_MULTIPLIERS: [u16; 4] = [180, 220, 255, 135];
+
+/// Looks up the RGBA colour for a single map pixel byte.
+///
+/// Pixels using base colour `0`, or an ID outside of the known palette, are
+/// fully transparent.
+pub fn map_color(index: u8) -> [u8; 4] {
+    let base = (index / 4) as usize;
+    if base == 0 || base >= MAP_BASE_COLORS.len() {
+        return [0, 0, 0, 0];
+    }
+    let shade = MAP_SHADE_MULTIPLIERS[(index % 4) as usize];
+    let [r, g, b] = MAP_BASE_COLORS[base];
+    let scale = |c: u8| (c as u16 * shade / 255) as u8;
+    [scale(r), scale(g), scale(b), 255]
+}
+
+/// Finds the map pixel byte whose colour is closest to the given RGBA colour.
+///
+/// Mostly transparent colours (alpha below 128) map to `0`.
+pub fn nearest_map_color(rgba: [u8; 4]) -> u8 {
+    if rgba[3] < 128 {
+        return 0;
+    }
+    let mut best = (0u8, u32::MAX);
+    for index in 4..(MAP_BASE_COLORS.len() * 4) as u8 {
+        let candidate = map_color(index);
+        let distance: u32 = (0..3)
+            .map(|i| {
+                let d = candidate[i] as i32 - rgba[i] as i32;
+                (d * d) as u32
+            })
+            .sum();
+        if distance < best.1 {
+            best = (index, distance);
+        }
+    }
+    best.0
+}
+
+impl Value {
+    /// Interprets a `Value::ByteArray` of map pixel data as a `width` by
+    /// `height` image, returned as RGBA pixels in row-major order.
+    ///
+    /// Returns `None` if this is not a `ByteArray`, or if its length does not
+    /// match the requested dimensions (including when `width * height`
+    /// overflows). Vanilla maps are 128 by 128.
+    pub fn byte_array_as_image(&self, width: usize, height: usize) -> Option<Vec<[u8; 4]>> {
+        match *self {
+            Value::ByteArray(ref bytes) if width.checked_mul(height) == Some(bytes.len()) => {
+                Some(bytes.iter().map(|&b| map_color(b as u8)).collect())
+            },
+            _ => None,
+        }
+    }
+
+    /// Creates a `Value::ByteArray` of map pixel data from row-major RGBA
+    /// pixels, using the nearest colour in the map palette for each pixel.
+    pub fn from_image(pixels: &[[u8; 4]]) -> Value {
+        Value::ByteArray(pixels.iter().map(|&p| nearest_map_color(p) as i8).collect())
+    }
+}
+
+/// The array tag that `Value::upgrade_byte_arrays_to_int_arrays` converts a
+/// `ByteArray` entry to. Each byte is read as one unsigned entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetArrayType {
+    /// One `Int` per entry, as for the `Biomes` of chunks since 1.13.
+    IntArray,
+    /// Entries packed into `Long`s, `bits_per_entry` bits at a time from the
+    /// least significant bit, as for block states since 1.16. Entries do not
+    /// span two longs, so any remaining high bits of each long are zero.
+    LongArray { bits_per_entry: u32 },
+}
+
+impl TargetArrayType {
+    fn convert(self, bytes: &[i8]) -> Result<Value> {
+        match self {
+            TargetArrayType::IntArray => {
+                Ok(Value::IntArray(bytes.iter().map(|&b| b as u8 as i32).collect()))
+            },
+            TargetArrayType::LongArray { bits_per_entry: bits } => {
+                if bits == 0 || bits > 64 {
+                    return Err(Error::NumericOutOfRange(bits as i64));
+                }
+                let per_long = (64 / bits) as usize;
+                let mut longs = vec![0u64; (bytes.len() + per_long - 1) / per_long];
+                for (i, &b) in bytes.iter().enumerate() {
+                    let entry = b as u8 as u64;
+                    if bits < 64 && entry >> bits != 0 {
+                        return Err(Error::NumericOutOfRange(entry as i64));
+                    }
+                    longs[i / per_long] |= entry << ((i % per_long) as u32 * bits);
+                }
+                Ok(Value::LongArray(longs.into_iter().map(|l| l as i64).collect()))
+            },
+        }
+    }
+}
+
+impl Value {
+    /// Converts the `ByteArray`s stored under the keys of `rules`, anywhere in
+    /// this tree, to the array tag given for that key, as needed when
+    /// migrating chunk data from older versions of Minecraft. Entries under
+    /// those keys which are not byte arrays are left as they are.
+    ///
+    /// Returns `Error::NumericOutOfRange` if a byte does not fit in the
+    /// number of bits given for it (or the number of bits is not between 1
+    /// and 64), in which case earlier arrays may already have been converted.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate nbt;
+    /// # fn main() {
+    /// use std::collections::HashMap;
+    /// use nbt::minecraft::TargetArrayType;
+    /// use nbt::Value;
+    ///
+    /// let mut section = compound! { "Biomes" => vec![1i8, -1] };
+    /// let mut rules = HashMap::new();
+    /// rules.insert("Biomes", TargetArrayType::IntArray);
+    /// section.upgrade_byte_arrays_to_int_arrays(&rules).unwrap();
+    /// assert_eq!(section, compound! { "Biomes" => vec![1i32, 255] });
+    /// # }
+    /// ```
+    pub fn upgrade_byte_arrays_to_int_arrays(&mut self, rules: &HashMap<&str, TargetArrayType>)
+        -> Result<()>
+    {
+        match *self {
+            Value::Compound(ref mut map) => {
+                for (key, val) in map.iter_mut() {
+                    let upgraded = match (rules.get(&key[..]), &*val) {
+                        (Some(target), Value::ByteArray(bytes)) => Some(target.convert(bytes)?),
+                        _ => None,
+                    };
+                    match upgraded {
+                        Some(upgraded) => *val = upgraded,
+                        None => val.upgrade_byte_arrays_to_int_arrays(rules)?,
+                    }
+                }
+            },
+            Value::List(ref mut vals) => {
+                for val in vals {
+                    val.upgrade_byte_arrays_to_int_arrays(rules)?;
+                }
+            },
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+/// Converts a point in time to a `Long` of milliseconds since the Unix epoch,
+/// as used by timestamp fields such as `LastPlayed`.
+///
+/// Times before 1970 give negative values. Times too far from the epoch to
+/// fit in an `i64` saturate.
+pub fn system_time_to_nbt(t: SystemTime) -> Value {
+    let millis = match t.duration_since(UNIX_EPOCH) {
+        Ok(after) => i64::try_from(after.as_millis()).unwrap_or(i64::MAX),
+        Err(before) => i64::try_from(before.duration().as_millis()).map_or(i64::MIN, |ms| -ms),
+    };
+    Value::Long(millis)
+}
+
+/// Converts a `Long` of milliseconds since the Unix epoch to a point in time.
+///
+/// Returns `None` for other tags, or if the time cannot be represented by
+/// `SystemTime` on this platform.
+pub fn nbt_to_system_time(v: &Value) -> Option<SystemTime> {
+    match *v {
+        Value::Long(ms) if ms >= 0 => UNIX_EPOCH.checked_add(Duration::from_millis(ms as u64)),
+        Value::Long(ms) => UNIX_EPOCH.checked_sub(Duration::from_millis(ms.unsigned_abs())),
+        _ => None,
+    }
+}
+
+/// The contents of a Java Edition `level.dat` file.
+///
+/// The file's root compound holds a single `Data` compound, whose well-known
+/// fields are exposed here. Fields which have been added or removed over the
+/// years are optional. All other entries (such as `Player` and `GameRules`)
+/// are kept in `other`, so that converting back to a `Value` loses nothing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelDat {
+    /// The NBT format version of the world (`version`), e.g. `19133` for
+    /// Anvil.
+    pub version: i32,
+    /// The data version of the game that last saved the world
+    /// (`DataVersion`). Added in 1.9.
+    pub data_version: Option<i32>,
+    /// The name of the world (`LevelName`).
+    pub level_name: String,
+    /// The world seed (`RandomSeed`). Moved into `WorldGenSettings` in 1.16.
+    pub random_seed: Option<i64>,
+    /// The default game mode (`GameType`).
+    pub game_type: i32,
+    /// The difficulty (`Difficulty`).
+    pub difficulty: Option<i8>,
+    /// Whether the world is in hardcore mode (`hardcore`).
+    pub hardcore: Option<bool>,
+    /// Whether cheats are enabled (`allowCommands`).
+    pub allow_commands: Option<bool>,
+    /// The world spawn position (`SpawnX`, `SpawnY`, `SpawnZ`).
+    pub spawn: (i32, i32, i32),
+    /// The number of ticks since the world was created (`Time`).
+    pub time: i64,
+    /// The time of day, in ticks (`DayTime`).
+    pub day_time: Option<i64>,
+    /// When the world was last played, in Unix milliseconds (`LastPlayed`).
+    pub last_played: i64,
+    /// Whether it is raining (`raining`).
+    pub raining: Option<bool>,
+    /// The number of ticks until `raining` toggles (`rainTime`).
+    pub rain_time: Option<i32>,
+    /// Whether it is thundering (`thundering`).
+    pub thundering: Option<bool>,
+    /// The number of ticks until `thundering` toggles (`thunderTime`).
+    pub thunder_time: Option<i32>,
+    /// All other entries of the `Data` compound.
+    pub other: HashMap<String, Value>,
+}
+
+impl TryFrom<Value> for LevelDat {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<LevelDat> {
+        let mut root = match value {
+            Value::Compound(map) => map,
+            other => return Err(Error::TagMismatch(other.tag_id(), TagId::Compound)),
+        };
+        let mut data = match take(&mut root, "Data", TagId::Compound, as_compound)? {
+            Some(data) => data,
+            None => return Err(Error::MissingField("Data".to_string())),
+        };
+        let d = &mut data;
+
+        Ok(LevelDat {
+            version: require(take(d, "version", TagId::Int, as_int)?, "version")?,
+            data_version: take(d, "DataVersion", TagId::Int, as_int)?,
+            level_name: require(take(d, "LevelName", TagId::String, as_string)?, "LevelName")?,
+            random_seed: take(d, "RandomSeed", TagId::Long, as_long)?,
+            game_type: require(take(d, "GameType", TagId::Int, as_int)?, "GameType")?,
+            difficulty: take(d, "Difficulty", TagId::Byte, as_byte)?,
+            hardcore: take_bool(d, "hardcore")?,
+            allow_commands: take_bool(d, "allowCommands")?,
+            spawn: (require(take(d, "SpawnX", TagId::Int, as_int)?, "SpawnX")?,
+                    require(take(d, "SpawnY", TagId::Int, as_int)?, "SpawnY")?,
+                    require(take(d, "SpawnZ", TagId::Int, as_int)?, "SpawnZ")?),
+            time: require(take(d, "Time", TagId::Long, as_long)?, "Time")?,
+            day_time: take(d, "DayTime", TagId::Long, as_long)?,
+            last_played: require(take(d, "LastPlayed", TagId::Long, as_long)?, "LastPlayed")?,
+            raining: take_bool(d, "raining")?,
+            rain_time: take(d, "rainTime", TagId::Int, as_int)?,
+            thundering: take_bool(d, "thundering")?,
+            thunder_time: take(d, "thunderTime", TagId::Int, as_int)?,
+            other: data,
+        })
+    }
+}
+
+impl From<LevelDat> for Value {
+    fn from(level: LevelDat) -> Value {
+        let mut data = level.other;
+        {
+            let mut put = |key: &str, value: Option<Value>| {
+                if let Some(value) = value {
+                    data.insert(key.to_string(), value);
+                }
+            };
+            let flag = |b: Option<bool>| b.map(|b| Value::Byte(b as i8));
+
+            put("version", Some(Value::Int(level.version)));
+            put("DataVersion", level.data_version.map(Value::Int));
+            put("LevelName", Some(Value::String(level.level_name)));
+            put("RandomSeed", level.random_seed.map(Value::Long));
+            put("GameType", Some(Value::Int(level.game_type)));
+            put("Difficulty", level.difficulty.map(Value::Byte));
+            put("hardcore", flag(level.hardcore));
+            put("allowCommands", flag(level.allow_commands));
+            put("SpawnX", Some(Value::Int(level.spawn.0)));
+            put("SpawnY", Some(Value::Int(level.spawn.1)));
+            put("SpawnZ", Some(Value::Int(level.spawn.2)));
+            put("Time", Some(Value::Long(level.time)));
+            put("DayTime", level.day_time.map(Value::Long));
+            put("LastPlayed", Some(Value::Long(level.last_played)));
+            put("raining", flag(level.raining));
+            put("rainTime", level.rain_time.map(Value::Int));
+            put("thundering", flag(level.thundering));
+            put("thunderTime", level.thunder_time.map(Value::Int));
+        }
+
+        let mut root = HashMap::new();
+        root.insert("Data".to_string(), Value::Compound(data));
+        Value::Compound(root)
+    }
+}
+
+/// Removes `key` from `map` and converts it with `f`, which should return
+/// `None` if the value is not of the tag type `id`.
+fn take<T, F>(map: &mut HashMap<String, Value>, key: &str, id: TagId, f: F) -> Result<Option<T>>
+    where F: FnOnce(Value) -> Option<T>
+{
+    match map.remove(key) {
+        None => Ok(None),
+        Some(value) => {
+            let found = value.tag_id();
+            f(value).map(Some).ok_or(Error::TagMismatch(found, id))
+        },
+    }
+}
+
+fn take_bool(map: &mut HashMap<String, Value>, key: &str) -> Result<Option<bool>> {
+    match take(map, key, TagId::Byte, as_byte)? {
+        None => Ok(None),
+        Some(0) => Ok(Some(false)),
+        Some(1) => Ok(Some(true)),
+        Some(b) => Err(Error::NonBooleanByte(b)),
+    }
+}
+
+fn require<T>(value: Option<T>, key: &str) -> Result<T> {
+    value.ok_or_else(|| Error::MissingField(key.to_string()))
+}
+
+fn as_byte(v: Value) -> Option<i8> {
+    match v { Value::Byte(b) => Some(b), _ => None }
+}
+
+fn as_int(v: Value) -> Option<i32> {
+    match v { Value::Int(i) => Some(i), _ => None }
+}
+
+fn as_long(v: Value) -> Option<i64> {
+    match v { Value::Long(l) => Some(l), _ => None }
+}
+
+fn as_string(v: Value) -> Option<String> {
+    match v { Value::String(s) => Some(s), _ => None }
+}
+
+fn as_compound(v: Value) -> Option<HashMap<String, Value>> {
+    match v { Value::Compound(map) => Some(map), _ => None }
+}
@@ -0,0 +1,19 @@
+//! Comparators for the order in which compound entries are written, for use
+//! with `Value::to_writer_ordered`.
+
+use std::cmp::Ordering;
+
+/// Keeps entries in `HashMap` iteration order, as `Value::to_writer` does.
+pub fn natural_order(_: &str, _: &str) -> Ordering {
+    Ordering::Equal
+}
+
+/// Orders entries by key, as `Value::to_writer_sorted` does.
+pub fn lexicographic(a: &str, b: &str) -> Ordering {
+    a.cmp(b)
+}
+
+/// Orders entries by key, in reverse.
+pub fn reverse_lexicographic(a: &str, b: &str) -> Ordering {
+    b.cmp(a)
+}
@@ -0,0 +1,184 @@
+//! Declarative modifications of `Value` trees.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use error::{Error, Result};
+use tag::TagId;
+use value::Value;
+
+/// A single operation of an `NbtPatch`.
+///
+/// Paths are dot-separated, as for `Value::get_path`, and are resolved
+/// relative to the value the patch is applied to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NbtOp {
+    /// Sets the compound entry or list element at `path`, which must be in an
+    /// existing compound or list.
+    Set { path: String, value: Value },
+    /// Removes the compound entry or list element at `path`.
+    Delete { path: String },
+    /// Pushes `value` onto the end of the list at `path`.
+    Append { path: String, value: Value },
+}
+
+/// A sequence of operations, applied in order to a `Value` tree.
+///
+/// Patches convert to and from `Value`s, so that they can themselves be
+/// stored as NBT. Each operation becomes a compound with an `op` entry of
+/// `"set"`, `"delete"` or `"append"`, a `path` entry and (except for deletes)
+/// a `value` entry.
+///
+/// ```rust
+/// # #[macro_use] extern crate nbt;
+/// # fn main() {
+/// use nbt::patch::{NbtOp, NbtPatch};
+/// use nbt::Value;
+///
+/// let mut player = compound! { "Health" => 20.0f32, "Tags" => Value::List(vec![]) };
+/// let patch = NbtPatch(vec![
+///     NbtOp::Set { path: "Health".to_string(), value: Value::Float(10.0) },
+///     NbtOp::Append { path: "Tags".to_string(), value: Value::String("hurt".to_string()) },
+/// ]);
+/// patch.apply(&mut player).unwrap();
+/// assert_eq!(player.get_path("Tags.0"), Some(&Value::String("hurt".to_string())));
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NbtPatch(pub Vec<NbtOp>);
+
+impl NbtPatch {
+    /// Applies each operation in turn, stopping at the first that fails.
+    ///
+    /// Returns `Error::PathNotFound` if a path (or, for `Set`, its parent)
+    /// does not exist, `Error::TagMismatch` if it leads through a value that
+    /// is not a compound or list, and `Error::HeterogeneousList` if a value
+    /// does not match the type of the other elements of a list.
+    pub fn apply(&self, val: &mut Value) -> Result<()> {
+        self.0.iter().try_for_each(|op| op.apply(val))
+    }
+}
+
+impl NbtOp {
+    fn apply(&self, root: &mut Value) -> Result<()> {
+        match *self {
+            NbtOp::Set { ref path, ref value } => {
+                let (parent, key) = parent_of(root, path)?;
+                match *parent {
+                    Value::Compound(ref mut map) => {
+                        map.insert(key.to_string(), value.clone());
+                    },
+                    Value::List(ref mut vals) => {
+                        let index = list_index(vals, key, path)?;
+                        check_element(vals, value, Some(index))?;
+                        vals[index] = value.clone();
+                    },
+                    ref other => return Err(Error::TagMismatch(other.tag_id(), TagId::Compound)),
+                }
+            },
+            NbtOp::Delete { ref path } => {
+                let (parent, key) = parent_of(root, path)?;
+                match *parent {
+                    Value::Compound(ref mut map) => {
+                        if map.remove(key).is_none() {
+                            return Err(Error::PathNotFound(path.clone()));
+                        }
+                    },
+                    Value::List(ref mut vals) => {
+                        let index = list_index(vals, key, path)?;
+                        vals.remove(index);
+                    },
+                    ref other => return Err(Error::TagMismatch(other.tag_id(), TagId::Compound)),
+                }
+            },
+            NbtOp::Append { ref path, ref value } => {
+                match root.get_path_mut(path) {
+                    Some(&mut Value::List(ref mut vals)) => {
+                        check_element(vals, value, None)?;
+                        vals.push(value.clone());
+                    },
+                    Some(other) => return Err(Error::TagMismatch(other.tag_id(), TagId::List)),
+                    None => return Err(Error::PathNotFound(path.clone())),
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Splits `path` into the value containing its last segment, and that
+/// segment.
+fn parent_of<'a, 'b>(root: &'a mut Value, path: &'b str) -> Result<(&'a mut Value, &'b str)> {
+    match path.rfind('.') {
+        None => Ok((root, path)),
+        Some(i) => match root.get_path_mut(&path[..i]) {
+            Some(parent) => Ok((parent, &path[i + 1..])),
+            None => Err(Error::PathNotFound(path.to_string())),
+        },
+    }
+}
+
+fn list_index(vals: &[Value], key: &str, path: &str) -> Result<usize> {
+    match key.parse() {
+        Ok(index) if index < vals.len() => Ok(index),
+        _ => Err(Error::PathNotFound(path.to_string())),
+    }
+}
+
+/// Checks that `value` has the same type as the elements of `vals`, other
+/// than the one at `replacing`. Without `replacing`, `value` is appended.
+fn check_element(vals: &[Value], value: &Value, replacing: Option<usize>) -> Result<()> {
+    let mismatch = vals.iter().enumerate()
+        .find(|&(i, v)| Some(i) != replacing && v.tag_id() != value.tag_id());
+    match mismatch {
+        Some((_, v)) => Err(Error::HeterogeneousList {
+            index: replacing.unwrap_or(vals.len()),
+            expected: v.tag_id(),
+            got: value.tag_id(),
+        }),
+        None => Ok(()),
+    }
+}
+
+impl From<NbtPatch> for Value {
+    fn from(patch: NbtPatch) -> Value {
+        Value::List(patch.0.into_iter().map(|op| {
+            let mut map = HashMap::new();
+            let (name, path, value) = match op {
+                NbtOp::Set { path, value } => ("set", path, Some(value)),
+                NbtOp::Delete { path } => ("delete", path, None),
+                NbtOp::Append { path, value } => ("append", path, Some(value)),
+            };
+            map.insert("op".to_string(), Value::String(name.to_string()));
+            map.insert("path".to_string(), Value::String(path));
+            if let Some(value) = value {
+                map.insert("value".to_string(), value);
+            }
+            Value::Compound(map)
+        }).collect())
+    }
+}
+
+/// Reads a patch from the representation produced by `From<NbtPatch>`.
+/// Unknown operation names produce `Error::UnexpectedField`.
+impl TryFrom<Value> for NbtPatch {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<NbtPatch> {
+        let ops = Vec::<Value>::try_from(value)?;
+        ops.into_iter().map(|op| {
+            let mut map = HashMap::<String, Value>::try_from(op)?;
+            let mut take = |key: &str| {
+                map.remove(key).ok_or_else(|| Error::MissingField(key.to_string()))
+            };
+            let name = String::try_from(take("op")?)?;
+            let path = String::try_from(take("path")?)?;
+            match &name[..] {
+                "set" => Ok(NbtOp::Set { path, value: take("value")? }),
+                "delete" => Ok(NbtOp::Delete { path }),
+                "append" => Ok(NbtOp::Append { path, value: take("value")? }),
+                _ => Err(Error::UnexpectedField(name)),
+            }
+        }).collect::<Result<Vec<_>>>().map(NbtPatch)
+    }
+}
@@ -0,0 +1,194 @@
+//! Parsing of the `Debug` representation of `Value`s.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use error::{Error, Result};
+use value::Value;
+
+impl Value {
+    /// Parses a `Value` from its `Debug` representation, in either the
+    /// compact (`{:?}`) or the pretty (`{:#?}`) form.
+    ///
+    /// This makes the debug output usable as a simple, round-trippable text
+    /// format, e.g. in test fixtures. Whitespace between tokens is ignored.
+    ///
+    /// ```
+    /// use nbt::Value;
+    ///
+    /// let value = Value::from_pretty_debug(r#"TAG_Compound { "x": TAG_Int(1) }"#).unwrap();
+    /// assert_eq!(format!("{:?}", value), r#"TAG_Compound { "x": TAG_Int(1) }"#);
+    /// ```
+    ///
+    /// Returns `Error::InvalidDebugFormat` with the byte offset of the first
+    /// unexpected input if `s` is not a valid representation.
+    pub fn from_pretty_debug(s: &str) -> Result<Value> {
+        let mut parser = Parser { src: s, pos: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos != s.len() {
+            return Err(parser.error());
+        }
+        Ok(value)
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self) -> Error {
+        Error::InvalidDebugFormat(self.pos)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consumes `token` (after any whitespace) if it comes next.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<()> {
+        if self.eat(token) { Ok(()) } else { Err(self.error()) }
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        self.expect("TAG_")?;
+        let rest = self.rest();
+        let len = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+        let name = &rest[..len];
+        let start = self.pos;
+        self.pos += len;
+
+        let value = match name {
+            "Byte" => Value::Byte(self.wrapped(Parser::number)?),
+            "Short" => Value::Short(self.wrapped(Parser::number)?),
+            "Int" => Value::Int(self.wrapped(Parser::number)?),
+            "Long" => Value::Long(self.wrapped(Parser::number)?),
+            "Float" => Value::Float(self.wrapped(Parser::number)?),
+            "Double" => Value::Double(self.wrapped(Parser::number)?),
+            "String" => Value::String(self.wrapped(Parser::string)?),
+            "ByteArray" => Value::ByteArray(self.wrapped(|p| p.seq("[", "]", Parser::number))?),
+            "IntArray" => Value::IntArray(self.wrapped(|p| p.seq("[", "]", Parser::number))?),
+            "LongArray" => Value::LongArray(self.wrapped(|p| p.seq("[", "]", Parser::number))?),
+            "List" => Value::List(self.seq("[", "]", Parser::value)?),
+            "Compound" => {
+                let entries = self.seq("{", "}", |p| {
+                    let name = p.string()?;
+                    p.expect(":")?;
+                    Ok((name, p.value()?))
+                })?;
+                let mut map = HashMap::with_capacity(entries.len());
+                map.extend(entries);
+                Value::Compound(map)
+            },
+            _ => {
+                self.pos = start;
+                return Err(self.error());
+            },
+        };
+        Ok(value)
+    }
+
+    /// Parses `(inner)`.
+    fn wrapped<T, F>(&mut self, inner: F) -> Result<T>
+        where F: FnOnce(&mut Parser<'a>) -> Result<T>
+    {
+        self.expect("(")?;
+        let value = inner(self)?;
+        self.expect(")")?;
+        Ok(value)
+    }
+
+    /// Parses a comma-separated sequence between `open` and `close`, allowing
+    /// the trailing comma used by the pretty form.
+    fn seq<T, F>(&mut self, open: &str, close: &str, mut item: F) -> Result<Vec<T>>
+        where F: FnMut(&mut Parser<'a>) -> Result<T>
+    {
+        self.expect(open)?;
+        let mut items = Vec::new();
+        while !self.eat(close) {
+            items.push(item(self)?);
+            if !self.eat(",") {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest.find(|c: char| c == ',' || c == ')' || c == ']' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let value = rest[..len].parse().map_err(|_| self.error())?;
+        self.pos += len;
+        Ok(value)
+    }
+
+    /// Parses a double-quoted string with Rust's `Debug` escapes.
+    fn string(&mut self) -> Result<String> {
+        self.expect("\"")?;
+        let mut out = String::new();
+        let mut chars = self.rest().char_indices();
+        let start = self.pos;
+        loop {
+            let (i, c) = match chars.next() {
+                Some(next) => next,
+                None => {
+                    self.pos = self.src.len();
+                    return Err(self.error());
+                },
+            };
+            match c {
+                '"' => {
+                    self.pos = start + i + 1;
+                    return Ok(out);
+                },
+                '\\' => {
+                    let escaped = match chars.next() {
+                        Some((_, 'n')) => Some('\n'),
+                        Some((_, 'r')) => Some('\r'),
+                        Some((_, 't')) => Some('\t'),
+                        Some((_, '0')) => Some('\0'),
+                        Some((_, c @ '\\')) | Some((_, c @ '"')) | Some((_, c @ '\'')) => Some(c),
+                        Some((_, 'u')) => {
+                            let hex: String = chars.by_ref()
+                                .map(|(_, c)| c)
+                                .take_while(|&c| c != '}')
+                                .collect();
+                            hex.strip_prefix('{')
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .and_then(::std::char::from_u32)
+                        },
+                        _ => None,
+                    };
+                    match escaped {
+                        Some(c) => out.push(c),
+                        None => {
+                            self.pos = start + i;
+                            return Err(self.error());
+                        },
+                    }
+                },
+                c => out.push(c),
+            }
+        }
+    }
+}
@@ -1,11 +1,16 @@
 //! Primitive functions for serializing and deserializing NBT data.
 
+use std::convert::TryFrom;
 use std::io;
+use std::io::Read;
+use std::slice;
 
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
 use cesu8::{from_java_cesu8, to_java_cesu8};
 
 use error::{Error, Result};
+use string;
+use tag::TagId;
 
 /// A convenience function for closing NBT format objects.
 ///
@@ -64,10 +69,12 @@ pub fn write_bare_byte_array<W>(dst: &mut W, value: &[i8]) -> Result<()>
    where W: io::Write
 {
     try!(dst.write_i32::<BigEndian>(value.len() as i32));
-    for &v in value {
-        try!(dst.write_i8(v));
-    }
-    Ok(())
+    // This is safe because `i8` and `u8` have the same size and alignment, and
+    // every bit pattern is valid for both.
+    let bytes = unsafe {
+        slice::from_raw_parts(value.as_ptr() as *const u8, value.len())
+    };
+    dst.write_all(bytes).map_err(From::from)
 }
 
 #[inline]
@@ -75,10 +82,9 @@ pub fn write_bare_int_array<W>(dst: &mut W, value: &[i32]) -> Result<()>
    where W: io::Write
 {
     try!(dst.write_i32::<BigEndian>(value.len() as i32));
-    for &v in value {
-        try!(dst.write_i32::<BigEndian>(v));
-    }
-    Ok(())
+    let mut bytes = vec![0; value.len() * 4];
+    BigEndian::write_i32_into(value, &mut bytes);
+    dst.write_all(&bytes).map_err(From::from)
 }
 
 #[inline]
@@ -86,10 +92,9 @@ pub fn write_bare_long_array<W>(dst: &mut W, value: &[i64]) -> Result<()>
    where W: io::Write
 {
     dst.write_i32::<BigEndian>(value.len() as i32)?;
-    for &v in value {
-        dst.write_i64::<BigEndian>(v)?;
-    }
-    Ok(())
+    let mut bytes = vec![0; value.len() * 8];
+    BigEndian::write_i64_into(value, &mut bytes);
+    dst.write_all(&bytes).map_err(From::from)
 }
 
 #[inline]
@@ -101,17 +106,32 @@ pub fn write_bare_string<W>(dst: &mut W, value: &str) -> Result<()>
     dst.write_all(&encoded).map_err(From::from)
 }
 
+/// Checks that a string will fit its length prefix once encoded, returning
+/// `Error::StringTooLong` otherwise. `write_bare_string` does not check this.
+pub fn check_string_len(value: &str) -> Result<()> {
+    // Encoding at most doubles the length, which happens for NUL characters,
+    // so only long strings need to be measured.
+    if value.len() <= u16::MAX as usize / 2 {
+        return Ok(());
+    }
+    let len = string::cesu8_len(value);
+    if len > u16::MAX as usize {
+        return Err(Error::StringTooLong(len));
+    }
+    Ok(())
+}
+
 /// Extracts the next header (tag and name) from an NBT format source.
 ///
 /// This function will also return the `TAG_End` byte and an empty name if it
 /// encounters it.
-pub fn emit_next_header<R>(src: &mut R) -> Result<(u8, String)>
+pub fn emit_next_header<R>(src: &mut R) -> Result<(TagId, String)>
     where R: io::Read
 {
-    let tag  = try!(src.read_u8());
+    let tag = TagId::try_from(src.read_u8()?)?;
 
     match tag {
-        0x00 => { Ok((tag, "".to_string())) },
+        TagId::End => { Ok((tag, "".to_string())) },
         _    => {
             let name = try!(read_bare_string(src));
             Ok((tag, name))
@@ -162,45 +182,60 @@ pub fn read_bare_double<R>(src: &mut R) -> Result<f64>
 }
 
 #[inline]
-pub fn read_bare_byte_array<R>(src: &mut R) -> Result<Vec<i8>>
+pub fn read_bare_byte_array<R>(src: &mut R, max_len: Option<usize>) -> Result<Vec<i8>>
     where R: io::Read
 {
-    // FIXME: Is there a way to return [u8; len]?
-    let len = try!(src.read_i32::<BigEndian>()) as usize;
-    let mut buf = Vec::with_capacity(len);
-    // FIXME: Test performance vs transmute.
-    for _ in 0..len {
-        buf.push(try!(src.read_i8()));
-    }
-    Ok(buf)
+    let bytes = read_array_bytes(src, TagId::ByteArray, 1, max_len)?;
+    Ok(bytes.into_iter().map(|b| b as i8).collect())
 }
 
 #[inline]
-pub fn read_bare_int_array<R>(src: &mut R) -> Result<Vec<i32>>
+pub fn read_bare_int_array<R>(src: &mut R, max_len: Option<usize>) -> Result<Vec<i32>>
     where R: io::Read
 {
-    // FIXME: Is there a way to return [i32; len]?
-    let len = try!(src.read_i32::<BigEndian>()) as usize;
-    let mut buf = Vec::with_capacity(len);
-    // FIXME: Test performance vs transmute.
-    for _ in 0..len {
-        buf.push(try!(src.read_i32::<BigEndian>()));
-    }
+    let bytes = read_array_bytes(src, TagId::IntArray, 4, max_len)?;
+    let mut buf = vec![0; bytes.len() / 4];
+    BigEndian::read_i32_into(&bytes, &mut buf);
     Ok(buf)
 }
 
 #[inline]
-pub fn read_bare_long_array<R>(src: &mut R) -> Result<Vec<i64>>
+pub fn read_bare_long_array<R>(src: &mut R, max_len: Option<usize>) -> Result<Vec<i64>>
     where R: io::Read
 {
-    let len = src.read_i32::<BigEndian>()? as usize;
-    let mut buf = Vec::with_capacity(len);
-    for _ in 0..len {
-        buf.push(src.read_i64::<BigEndian>()?);
-    }
+    let bytes = read_array_bytes(src, TagId::LongArray, 8, max_len)?;
+    let mut buf = vec![0; bytes.len() / 8];
+    BigEndian::read_i64_into(&bytes, &mut buf);
     Ok(buf)
 }
 
+/// Reads the length prefix of an array with the given tag type, followed by
+/// the raw bytes of its elements.
+///
+/// The buffer grows as data arrives rather than being allocated up front, so
+/// that a corrupt length cannot exhaust memory. If the source ends early,
+/// this returns `Error::TruncatedArray`. Negative lengths are read as empty
+/// arrays.
+fn read_array_bytes<R>(src: &mut R, tag_id: TagId, elem_size: usize, max_len: Option<usize>)
+                       -> Result<Vec<u8>>
+    where R: io::Read
+{
+    let declared_len = src.read_i32::<BigEndian>()?.max(0) as usize;
+    check_len(declared_len, max_len)?;
+    let wanted = declared_len * elem_size;
+    let mut bytes = Vec::new();
+    io::Read::take(src, wanted as u64).read_to_end(&mut bytes)?;
+    if bytes.len() < wanted {
+        return Err(Error::TruncatedArray { tag_id, declared_len, bytes_available: bytes.len() });
+    }
+    Ok(bytes)
+}
+
+/// Reads a length-prefixed Modified UTF-8 string.
+///
+/// Like the other readers here, this makes several small reads per value, so
+/// unbuffered sources such as files or sockets should be wrapped in an
+/// `io::BufReader`.
 #[inline]
 pub fn read_bare_string<R>(src: &mut R) -> Result<String>
     where R: io::Read
@@ -210,14 +245,145 @@ pub fn read_bare_string<R>(src: &mut R) -> Result<String>
     if len == 0 { return Ok("".to_string()); }
 
     let mut bytes = vec![0; len];
-    let mut n_read = 0usize;
-    while n_read < bytes.len() {
-        match try!(src.read(&mut bytes[n_read..])) {
-            0 => return Err(Error::IncompleteNbtValue),
-            n => n_read += n
-        }
-    }
+    src.read_exact(&mut bytes)?;
 
     let decoded = from_java_cesu8(&bytes)?;
     Ok(decoded.into_owned())
 }
+
+/// Consumes the payload of a value with the given tag type from an `io::Read`
+/// source, without decoding or allocating it.
+pub fn skip_payload<R>(src: &mut R, tag: TagId) -> Result<()>
+    where R: io::Read
+{
+    if let Some(size) = fixed_payload_size(tag) {
+        return skip_bytes(src, size);
+    }
+    match tag {
+        TagId::ByteArray | TagId::IntArray | TagId::LongArray => {
+            let declared_len = src.read_i32::<BigEndian>()?.max(0) as usize;
+            let size = match tag { TagId::ByteArray => 1, TagId::IntArray => 4, _ => 8 };
+            let wanted = (declared_len * size) as u64;
+            let skipped = io::copy(&mut io::Read::take(src, wanted), &mut io::sink())?;
+            if skipped < wanted {
+                return Err(Error::TruncatedArray {
+                    tag_id: tag,
+                    declared_len,
+                    bytes_available: skipped as usize,
+                });
+            }
+            Ok(())
+        },
+        TagId::String => {
+            let len = src.read_u16::<BigEndian>()? as u64;
+            skip_bytes(src, len)
+        },
+        TagId::List => {
+            let elem = TagId::try_from(src.read_u8()?)?;
+            let len = src.read_i32::<BigEndian>()?.max(0) as u64;
+            match fixed_payload_size(elem) {
+                Some(size) => skip_bytes(src, len * size),
+                None => {
+                    for _ in 0..len {
+                        skip_payload(src, elem)?;
+                    }
+                    Ok(())
+                },
+            }
+        },
+        TagId::Compound => loop {
+            let tag = TagId::try_from(src.read_u8()?)?;
+            if tag == TagId::End {
+                return Ok(());
+            }
+            // Skip the entry's name, then its payload.
+            skip_payload(src, TagId::String)?;
+            skip_payload(src, tag)?;
+        },
+        t => Err(Error::InvalidTypeId(t.into())),
+    }
+}
+
+/// The size in bytes of the payload of scalar numeric tags.
+fn fixed_payload_size(tag: TagId) -> Option<u64> {
+    match tag {
+        TagId::Byte => Some(1),
+        TagId::Short => Some(2),
+        TagId::Int | TagId::Float => Some(4),
+        TagId::Long | TagId::Double => Some(8),
+        _ => None,
+    }
+}
+
+/// Returns `Error::LengthLimitExceeded` if `len` is more than `max_len`.
+pub fn check_len(len: usize, max_len: Option<usize>) -> Result<()> {
+    match max_len {
+        Some(max) if len > max => Err(Error::LengthLimitExceeded(len)),
+        _ => Ok(()),
+    }
+}
+
+/// Returns `Error::DepthLimitExceeded` if `depth` is more than `max_depth`.
+pub fn check_depth(depth: usize, max_depth: Option<usize>) -> Result<()> {
+    match max_depth {
+        Some(max) if depth > max => Err(Error::DepthLimitExceeded(max)),
+        _ => Ok(()),
+    }
+}
+
+fn skip_bytes<R>(src: &mut R, len: u64) -> Result<()>
+    where R: io::Read
+{
+    let skipped = io::copy(&mut io::Read::take(src, len), &mut io::sink())?;
+    if skipped < len {
+        return Err(Error::IncompleteNbtValue);
+    }
+    Ok(())
+}
+
+/// Checks that `src` begins with the two gzip magic bytes, returning
+/// `Error::NotGzip` otherwise. The returned reader yields the whole stream,
+/// including the bytes that were checked.
+pub fn check_gzip_magic<R>(mut src: R) -> Result<io::Chain<io::Cursor<[u8; 2]>, R>>
+    where R: io::Read
+{
+    let mut magic = [0; 2];
+    match src.read_exact(&mut magic) {
+        Ok(()) => (),
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Err(Error::NotGzip),
+        Err(e) => return Err(e.into()),
+    }
+    if magic != [0x1f, 0x8b] {
+        return Err(Error::NotGzip);
+    }
+    Ok(io::Cursor::new(magic).chain(src))
+}
+
+/// Wraps an `io::Write` destination, counting the bytes written through it.
+pub struct CountingWriter<'a, W: 'a> {
+    dst: &'a mut W,
+    count: usize,
+}
+
+impl<'a, W> CountingWriter<'a, W> where W: io::Write {
+    pub fn new(dst: &'a mut W) -> CountingWriter<'a, W> {
+        CountingWriter { dst, count: 0 }
+    }
+
+    /// The number of bytes written so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, W> io::Write for CountingWriter<'a, W> where W: io::Write {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.dst.write(buf)?;
+        self.count += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.dst.flush()
+    }
+}
@@ -0,0 +1,302 @@
+//! Incremental reading of NBT data.
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::io;
+
+use byteorder::ReadBytesExt;
+
+use config::ParseConfig;
+use error::{Error, Result};
+use raw;
+use tag::TagId;
+use value::Value;
+
+/// A significant event when pulling NBT data from an `NbtReader`.
+///
+/// Every value is preceded by a `Name` event if it is the root or a compound
+/// entry, and list elements are not. Array and string payloads borrow from
+/// the reader, and are only valid until the next event is read.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NbtEvent<'a> {
+    /// The name of the root value, or of an entry of a compound.
+    Name(&'a str),
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(&'a [i8]),
+    IntArray(&'a [i32]),
+    LongArray(&'a [i64]),
+    String(&'a str),
+    StartCompound,
+    EndCompound,
+    /// The start of a list of `len` elements with the tag type `tag_id`.
+    StartList { tag_id: TagId, len: i32 },
+    EndList,
+}
+
+/// A compound or list which `NbtReader::next` is in the middle of.
+enum Frame {
+    /// The keys read so far, if duplicate keys are being rejected.
+    Compound(HashSet<String>),
+    List { tag: TagId, remaining: i32 },
+}
+
+/// A reader which parses NBT data one tag at a time, rather than reading a
+/// whole `Blob` at once.
+///
+/// This allows large files to be searched for the entries of interest, while
+/// skipping over the rest without decoding them.
+///
+/// ```rust
+/// use nbt::reader::NbtReader;
+/// use nbt::{TagId, Value};
+///
+/// // A root compound with entries "a" (a string) and "b" (a byte).
+/// let bytes = vec![
+///     0x0a, 0x00, 0x00,
+///     0x08, 0x00, 0x01, b'a', 0x00, 0x02, b'h', b'i',
+///     0x01, 0x00, 0x01, b'b', 0x7f,
+///     0x00,
+/// ];
+/// let mut reader = NbtReader::new(&bytes[..]);
+/// assert_eq!(reader.read_header().unwrap(), (TagId::Compound, "".to_string()));
+///
+/// let (tag, _) = reader.read_header().unwrap();
+/// reader.skip_value(tag).unwrap();
+/// let (tag, name) = reader.read_header().unwrap();
+/// assert_eq!(name, "b");
+/// assert_eq!(reader.read_value(tag).unwrap(), Value::Byte(127));
+/// ```
+pub struct NbtReader<R> {
+    src: R,
+    config: ParseConfig,
+    // The state of `next`.
+    stack: Vec<Frame>,
+    started: bool,
+    pending: Option<TagId>,
+    // Buffers for the borrowed payloads of events.
+    string: String,
+    bytes: Vec<i8>,
+    ints: Vec<i32>,
+    longs: Vec<i64>,
+}
+
+impl<R> NbtReader<R> where R: io::Read {
+    /// Creates a reader over the given `io::Read` source.
+    pub fn new(src: R) -> NbtReader<R> {
+        NbtReader::with_config(src, ParseConfig::default())
+    }
+
+    /// Creates a reader over the given `io::Read` source, which checks the
+    /// limits of the given `ParseConfig`. See also `NbtReaderBuilder`.
+    pub fn with_config(src: R, config: ParseConfig) -> NbtReader<R> {
+        NbtReader {
+            src,
+            config,
+            stack: Vec::new(),
+            started: false,
+            pending: None,
+            string: String::new(),
+            bytes: Vec::new(),
+            ints: Vec::new(),
+            longs: Vec::new(),
+        }
+    }
+
+    /// Returns the underlying source.
+    pub fn into_inner(self) -> R {
+        self.src
+    }
+
+    /// Reads the tag type and name of the next entry. A tag type of
+    /// `TagId::End` marks the end of the enclosing compound, and has no name.
+    pub fn read_header(&mut self) -> Result<(TagId, String)> {
+        raw::emit_next_header(&mut self.src)
+    }
+
+    /// Reads the payload of a value of the given tag type.
+    pub fn read_value(&mut self, tag: TagId) -> Result<Value> {
+        Value::from_reader_with_config(tag, &mut self.src, &self.config)
+    }
+
+    /// Consumes the payload of a value of the given tag type without decoding
+    /// it. Nested compounds and lists are skipped in their entirety.
+    pub fn skip_value(&mut self, tag: TagId) -> Result<()> {
+        raw::skip_payload(&mut self.src, tag)
+    }
+
+    /// Reads the next event of a document, starting with its root, or returns
+    /// `None` once the whole document (or a lone `TAG_End`) has been read.
+    ///
+    /// This should not be mixed with the other reading methods within the
+    /// same document.
+    ///
+    /// ```rust
+    /// use nbt::reader::{NbtEvent, NbtReader};
+    ///
+    /// let bytes = vec![0x0a, 0x00, 0x00, 0x03, 0x00, 0x01, b'x', 0x00, 0x00, 0x00, 0x05, 0x00];
+    /// let mut reader = NbtReader::new(&bytes[..]);
+    /// assert_eq!(reader.next().unwrap(), Some(NbtEvent::Name("")));
+    /// assert_eq!(reader.next().unwrap(), Some(NbtEvent::StartCompound));
+    /// assert_eq!(reader.next().unwrap(), Some(NbtEvent::Name("x")));
+    /// assert_eq!(reader.next().unwrap(), Some(NbtEvent::Int(5)));
+    /// assert_eq!(reader.next().unwrap(), Some(NbtEvent::EndCompound));
+    /// assert_eq!(reader.next().unwrap(), None);
+    /// ```
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<NbtEvent<'_>>> {
+        if let Some(tag) = self.pending.take() {
+            return self.start_value(tag).map(Some);
+        }
+        let tag = match self.stack.last_mut() {
+            None if self.started => return Ok(None),
+            None => {
+                self.started = true;
+                let tag = TagId::try_from(self.src.read_u8()?)?;
+                if tag == TagId::End {
+                    return Ok(None);
+                }
+                tag
+            },
+            Some(&mut Frame::Compound(ref mut keys)) => {
+                let tag = TagId::try_from(self.src.read_u8()?)?;
+                if tag == TagId::End {
+                    self.stack.pop();
+                    return Ok(Some(NbtEvent::EndCompound));
+                }
+                self.string = raw::read_bare_string(&mut self.src)?;
+                if self.config.reject_duplicate_keys && !keys.insert(self.string.clone()) {
+                    return Err(Error::DuplicateKey(self.string.clone()));
+                }
+                self.pending = Some(tag);
+                return Ok(Some(NbtEvent::Name(&self.string)));
+            },
+            Some(&mut Frame::List { tag, ref mut remaining }) => {
+                if *remaining <= 0 {
+                    self.stack.pop();
+                    return Ok(Some(NbtEvent::EndList));
+                }
+                *remaining -= 1;
+                return self.start_value(tag).map(Some);
+            },
+        };
+        // The root is named, unless the configuration says otherwise.
+        self.string = if self.config.legacy_alpha_format {
+            String::new()
+        } else {
+            raw::read_bare_string(&mut self.src)?
+        };
+        self.pending = Some(tag);
+        Ok(Some(NbtEvent::Name(&self.string)))
+    }
+
+    fn start_value(&mut self, tag: TagId) -> Result<NbtEvent<'_>> {
+        let src = &mut self.src;
+        let max_len = self.config.max_array_len;
+        if tag == TagId::List || tag == TagId::Compound {
+            raw::check_depth(self.stack.len() + 1, self.config.max_depth)?;
+        }
+        Ok(match tag {
+            TagId::Byte => NbtEvent::Byte(raw::read_bare_byte(src)?),
+            TagId::Short => NbtEvent::Short(raw::read_bare_short(src)?),
+            TagId::Int => NbtEvent::Int(raw::read_bare_int(src)?),
+            TagId::Long => NbtEvent::Long(raw::read_bare_long(src)?),
+            TagId::Float => NbtEvent::Float(raw::read_bare_float(src)?),
+            TagId::Double => NbtEvent::Double(raw::read_bare_double(src)?),
+            TagId::ByteArray => {
+                self.bytes = raw::read_bare_byte_array(src, max_len)?;
+                NbtEvent::ByteArray(&self.bytes)
+            },
+            TagId::String => {
+                self.string = raw::read_bare_string(src)?;
+                NbtEvent::String(&self.string)
+            },
+            TagId::List => {
+                let tag_id = TagId::try_from(src.read_u8()?)?;
+                let len = raw::read_bare_int(src)?;
+                raw::check_len(len.max(0) as usize, max_len)?;
+                self.stack.push(Frame::List { tag: tag_id, remaining: len });
+                NbtEvent::StartList { tag_id, len }
+            },
+            TagId::Compound => {
+                self.stack.push(Frame::Compound(HashSet::new()));
+                NbtEvent::StartCompound
+            },
+            TagId::IntArray => {
+                self.ints = raw::read_bare_int_array(src, max_len)?;
+                NbtEvent::IntArray(&self.ints)
+            },
+            TagId::LongArray => {
+                self.longs = raw::read_bare_long_array(src, max_len)?;
+                NbtEvent::LongArray(&self.longs)
+            },
+            TagId::End => return Err(Error::InvalidTypeId(0x00)),
+        })
+    }
+}
+
+/// Configures an `NbtReader` before it is created.
+///
+/// Data is always read as big-endian, as written by the Java edition. Each
+/// strictness check has its own option rather than a single `strict` switch.
+///
+/// ```rust
+/// use nbt::reader::NbtReaderBuilder;
+/// use nbt::{Error, TagId};
+///
+/// // A root compound holding a list of 1000 bytes.
+/// let bytes = vec![0x0a, 0x00, 0x00, 0x09, 0x00, 0x00, 0x01, 0x00, 0x00, 0x03, 0xe8];
+/// let mut reader = NbtReaderBuilder::new().max_array_len(100).build(&bytes[..]);
+/// let (tag, _) = reader.read_header().unwrap();
+/// assert_eq!(reader.read_value(tag), Err(Error::LengthLimitExceeded(1000)));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct NbtReaderBuilder {
+    config: ParseConfig,
+}
+
+impl NbtReaderBuilder {
+    /// Creates a builder with the default `ParseConfig`, which has no limits.
+    pub fn new() -> NbtReaderBuilder {
+        NbtReaderBuilder::default()
+    }
+
+    /// Sets `ParseConfig::max_depth`.
+    pub fn max_depth(mut self, depth: usize) -> NbtReaderBuilder {
+        self.config.max_depth = Some(depth);
+        self
+    }
+
+    /// Sets `ParseConfig::max_array_len`.
+    pub fn max_array_len(mut self, len: usize) -> NbtReaderBuilder {
+        self.config.max_array_len = Some(len);
+        self
+    }
+
+    /// Sets `ParseConfig::reject_duplicate_keys`.
+    pub fn reject_duplicate_keys(mut self, reject: bool) -> NbtReaderBuilder {
+        self.config.reject_duplicate_keys = reject;
+        self
+    }
+
+    /// Sets `ParseConfig::track_paths`.
+    pub fn track_paths(mut self, track: bool) -> NbtReaderBuilder {
+        self.config.track_paths = track;
+        self
+    }
+
+    /// Sets `ParseConfig::legacy_alpha_format`.
+    pub fn legacy_alpha_format(mut self, unnamed_root: bool) -> NbtReaderBuilder {
+        self.config.legacy_alpha_format = unnamed_root;
+        self
+    }
+
+    /// Creates a reader over the given `io::Read` source.
+    pub fn build<R: io::Read>(self, src: R) -> NbtReader<R> {
+        NbtReader::with_config(src, self.config)
+    }
+}
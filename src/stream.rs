@@ -0,0 +1,156 @@
+//! Flat iteration over the leaf values of NBT data.
+
+use std::io;
+
+use error::Result;
+use reader::{NbtEvent, NbtReader};
+use value::Value;
+
+/// An iterator over every leaf of an NBT document, that is every value other
+/// than a compound or list, together with its path from the root.
+///
+/// Paths hold the keys of compound entries and the indices of list elements,
+/// but not the name of the root. Arrays and strings are leaves. The document
+/// is parsed by an `NbtReader` as it is iterated over, so it is never held in
+/// memory as a whole.
+///
+/// Iteration stops after the first error.
+///
+/// ```rust
+/// use nbt::stream::NbtStream;
+/// use nbt::Value;
+///
+/// // A root compound with an entry "pos" holding a list of two ints.
+/// let bytes = vec![
+///     0x0a, 0x00, 0x00,
+///     0x09, 0x00, 0x03, b'p', b'o', b's', 0x03, 0x00, 0x00, 0x00, 0x02,
+///         0x00, 0x00, 0x00, 0x05,
+///         0x00, 0x00, 0x00, 0x40,
+///     0x00,
+/// ];
+/// let leaves: Vec<_> = NbtStream::new(&bytes[..]).map(Result::unwrap).collect();
+/// assert_eq!(leaves, vec![
+///     (vec!["pos".to_string(), "0".to_string()], Value::Int(5)),
+///     (vec!["pos".to_string(), "1".to_string()], Value::Int(64)),
+/// ]);
+/// ```
+pub struct NbtStream<R> {
+    reader: NbtReader<R>,
+    /// The path of the innermost open compound or list.
+    path: Vec<String>,
+    containers: Vec<Container>,
+    /// The name of the compound entry whose value is read next.
+    name: Option<String>,
+    done: bool,
+}
+
+enum Container {
+    Compound,
+    /// A list, and the index of its next element.
+    List(usize),
+}
+
+impl<R> NbtStream<R> where R: io::Read {
+    /// Creates a stream reading from the given `io::Read` source.
+    pub fn new(src: R) -> NbtStream<R> {
+        NbtStream::from(NbtReader::new(src))
+    }
+
+    /// The key of the next value within its compound or list, or `None` for
+    /// the root.
+    fn next_key(&mut self) -> Option<String> {
+        match self.containers.last_mut() {
+            None => None,
+            Some(&mut Container::Compound) => self.name.take(),
+            Some(&mut Container::List(ref mut index)) => {
+                let key = index.to_string();
+                *index += 1;
+                Some(key)
+            },
+        }
+    }
+
+    fn open(&mut self, container: Container) {
+        if let Some(key) = self.next_key() {
+            self.path.push(key);
+        }
+        self.containers.push(container);
+    }
+
+    fn close(&mut self) {
+        self.containers.pop();
+        if !self.containers.is_empty() {
+            self.path.pop();
+        }
+    }
+
+    fn next_leaf(&mut self) -> Result<Option<(Vec<String>, Value)>> {
+        loop {
+            let value = match self.reader.next()? {
+                None => return Ok(None),
+                Some(NbtEvent::Name(name)) => {
+                    if !self.containers.is_empty() {
+                        self.name = Some(name.to_string());
+                    }
+                    continue;
+                },
+                Some(NbtEvent::StartCompound) => {
+                    self.open(Container::Compound);
+                    continue;
+                },
+                Some(NbtEvent::StartList { .. }) => {
+                    self.open(Container::List(0));
+                    continue;
+                },
+                Some(NbtEvent::EndCompound) | Some(NbtEvent::EndList) => {
+                    self.close();
+                    continue;
+                },
+                Some(NbtEvent::Byte(v)) => Value::Byte(v),
+                Some(NbtEvent::Short(v)) => Value::Short(v),
+                Some(NbtEvent::Int(v)) => Value::Int(v),
+                Some(NbtEvent::Long(v)) => Value::Long(v),
+                Some(NbtEvent::Float(v)) => Value::Float(v),
+                Some(NbtEvent::Double(v)) => Value::Double(v),
+                Some(NbtEvent::ByteArray(v)) => Value::ByteArray(v.to_vec()),
+                Some(NbtEvent::IntArray(v)) => Value::IntArray(v.to_vec()),
+                Some(NbtEvent::LongArray(v)) => Value::LongArray(v.to_vec()),
+                Some(NbtEvent::String(v)) => Value::String(v.to_string()),
+            };
+            let mut path = self.path.clone();
+            if let Some(key) = self.next_key() {
+                path.push(key);
+            }
+            return Ok(Some((path, value)));
+        }
+    }
+}
+
+impl<R> From<NbtReader<R>> for NbtStream<R> {
+    /// Streams the leaves read by `reader`, which keeps its configuration.
+    /// The reader must not have read anything yet.
+    fn from(reader: NbtReader<R>) -> NbtStream<R> {
+        NbtStream { reader, path: Vec::new(), containers: Vec::new(), name: None, done: false }
+    }
+}
+
+impl<R> Iterator for NbtStream<R> where R: io::Read {
+    type Item = Result<(Vec<String>, Value)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_leaf() {
+            Ok(Some(leaf)) => Some(Ok(leaf)),
+            Ok(None) => {
+                self.done = true;
+                None
+            },
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            },
+        }
+    }
+}
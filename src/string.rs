@@ -0,0 +1,48 @@
+//! Encoding of the Modified UTF-8 strings used by NBT data.
+//!
+//! Java, and so NBT, encodes strings in a variant of CESU-8: characters
+//! outside the Basic Multilingual Plane are written as surrogate pairs of
+//! three bytes each, and NUL is written as two bytes so that encoded strings
+//! never contain a zero byte. These helpers are for code that reads or writes
+//! raw NBT streams itself.
+//!
+//! ```rust
+//! use nbt::string;
+//!
+//! let encoded = string::to_cesu8("a\u{1F600}");
+//! assert_eq!(encoded.len(), 7);
+//! assert_eq!(string::cesu8_len("a\u{1F600}"), 7);
+//! assert_eq!(string::from_cesu8(&encoded).unwrap(), "a\u{1F600}");
+//! ```
+
+use std::borrow::Cow;
+
+use cesu8::{from_java_cesu8, to_java_cesu8};
+
+use error::Result;
+
+/// Encodes a string as Modified UTF-8, borrowing it if that is the same as
+/// its UTF-8 encoding.
+pub fn to_cesu8(s: &str) -> Cow<'_, [u8]> {
+    to_java_cesu8(s)
+}
+
+/// Decodes a Modified UTF-8 string, borrowing it if that is the same as its
+/// UTF-8 encoding.
+///
+/// Returns `Error::InvalidUtf8` if `bytes` are not valid Modified UTF-8.
+pub fn from_cesu8(bytes: &[u8]) -> Result<Cow<'_, str>> {
+    from_java_cesu8(bytes).map_err(From::from)
+}
+
+/// The length of a string once encoded as Modified UTF-8, computed without
+/// encoding it.
+pub fn cesu8_len(s: &str) -> usize {
+    s.chars().map(|c| match c as u32 {
+        0 => 2,
+        0x01..=0x7f => 1,
+        0x80..=0x7ff => 2,
+        0x800..=0xffff => 3,
+        _ => 6,
+    }).sum()
+}
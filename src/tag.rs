@@ -0,0 +1,84 @@
+//! Identifiers for the types of NBT tags.
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use error::{Error, Result};
+
+/// The type of an NBT tag, as identified by the byte preceding it in binary
+/// representations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum TagId {
+    /// Marks the end of a compound, or the type of an empty list.
+    End = 0,
+    Byte = 1,
+    Short = 2,
+    Int = 3,
+    Long = 4,
+    Float = 5,
+    Double = 6,
+    ByteArray = 7,
+    String = 8,
+    List = 9,
+    Compound = 10,
+    IntArray = 11,
+    LongArray = 12,
+}
+
+impl TagId {
+    /// The name of this tag type in the NBT specification, e.g. `TAG_Int`.
+    pub fn name(self) -> &'static str {
+        match self {
+            TagId::End       => "TAG_End",
+            TagId::Byte      => "TAG_Byte",
+            TagId::Short     => "TAG_Short",
+            TagId::Int       => "TAG_Int",
+            TagId::Long      => "TAG_Long",
+            TagId::Float     => "TAG_Float",
+            TagId::Double    => "TAG_Double",
+            TagId::ByteArray => "TAG_ByteArray",
+            TagId::String    => "TAG_String",
+            TagId::List      => "TAG_List",
+            TagId::Compound  => "TAG_Compound",
+            TagId::IntArray  => "TAG_IntArray",
+            TagId::LongArray => "TAG_LongArray",
+        }
+    }
+}
+
+/// Unknown tag bytes produce `Error::InvalidTypeId`.
+impl TryFrom<u8> for TagId {
+    type Error = Error;
+
+    fn try_from(id: u8) -> Result<TagId> {
+        Ok(match id {
+            0x00 => TagId::End,
+            0x01 => TagId::Byte,
+            0x02 => TagId::Short,
+            0x03 => TagId::Int,
+            0x04 => TagId::Long,
+            0x05 => TagId::Float,
+            0x06 => TagId::Double,
+            0x07 => TagId::ByteArray,
+            0x08 => TagId::String,
+            0x09 => TagId::List,
+            0x0a => TagId::Compound,
+            0x0b => TagId::IntArray,
+            0x0c => TagId::LongArray,
+            e => return Err(Error::InvalidTypeId(e)),
+        })
+    }
+}
+
+impl From<TagId> for u8 {
+    fn from(id: TagId) -> u8 {
+        id as u8
+    }
+}
+
+impl fmt::Display for TagId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
@@ -0,0 +1,112 @@
+//! Serde helpers for storing a numeric field as a particular tag type.
+//!
+//! By default a field is stored as the tag matching its Rust type, and
+//! unsigned types have no matching tag at all. Each module here can be used
+//! with `#[serde(with = "...")]` to store a field as another numeric tag
+//! instead, converting (with a range check for integers) on the way in and
+//! out.
+//!
+//! ```rust
+//! #[macro_use] extern crate serde_derive;
+//! extern crate nbt;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Block {
+//!     // Older versions of the format store block IDs as shorts.
+//!     #[serde(with = "nbt::tag_type::short")]
+//!     id: u8,
+//! }
+//!
+//! # fn main() {
+//! let mut dst = Vec::new();
+//! nbt::ser::to_writer(&mut dst, &Block { id: 7 }, None).unwrap();
+//! assert_eq!(dst, vec![0x0a, 0x00, 0x00, 0x02, 0x00, 0x02, b'i', b'd', 0x00, 0x07, 0x00]);
+//! # }
+//! ```
+
+macro_rules! integer_tag_type {
+    ($($module:ident, $ty:ty, $tag:expr, $serialize:ident;)*) => {
+        $(
+            #[doc = concat!("Stores an integer field as a `", $tag, "`.")]
+            pub mod $module {
+                use std::convert::{TryFrom, TryInto};
+
+                use serde::{de, ser, Deserialize, Deserializer, Serializer};
+
+                /// Serializes `value`, failing if it is out of range for the tag.
+                pub fn serialize<T, S>(value: &T, s: S) -> Result<S::Ok, S::Error>
+                    where T: Copy + TryInto<$ty>, S: Serializer
+                {
+                    match (*value).try_into() {
+                        Ok(v) => s.$serialize(v),
+                        Err(_) => Err(ser::Error::custom(concat!("value is out of range for ", $tag))),
+                    }
+                }
+
+                /// Deserializes a value, failing if it is out of range for `T`.
+                pub fn deserialize<'de, T, D>(d: D) -> Result<T, D::Error>
+                    where T: TryFrom<$ty>, D: Deserializer<'de>
+                {
+                    let v = <$ty>::deserialize(d)?;
+                    T::try_from(v).map_err(|_| de::Error::custom(
+                        concat!($tag, " value is out of range for the field type")))
+                }
+            }
+        )*
+    };
+}
+
+/// Floating-point field types, which are converted between as with `as`.
+pub trait FloatField: Copy {
+    fn to_f64(self) -> f64;
+    fn from_f64(v: f64) -> Self;
+}
+
+impl FloatField for f32 {
+    fn to_f64(self) -> f64 { self as f64 }
+    fn from_f64(v: f64) -> f32 { v as f32 }
+}
+
+impl FloatField for f64 {
+    fn to_f64(self) -> f64 { self }
+    fn from_f64(v: f64) -> f64 { v }
+}
+
+macro_rules! float_tag_type {
+    ($($module:ident, $ty:ty, $tag:expr, $serialize:ident;)*) => {
+        $(
+            #[doc = concat!("Stores a floating-point field as a `", $tag, "`.")]
+            pub mod $module {
+                use serde::{Deserialize, Deserializer, Serializer};
+
+                use super::FloatField;
+
+                /// Serializes `value`, rounding it if the tag is narrower.
+                pub fn serialize<T, S>(value: &T, s: S) -> Result<S::Ok, S::Error>
+                    where T: FloatField, S: Serializer
+                {
+                    s.$serialize(value.to_f64() as $ty)
+                }
+
+                /// Deserializes a value, rounding it if `T` is narrower.
+                pub fn deserialize<'de, T, D>(d: D) -> Result<T, D::Error>
+                    where T: FloatField, D: Deserializer<'de>
+                {
+                    <$ty>::deserialize(d).map(|v| T::from_f64(v as f64))
+                }
+            }
+        )*
+    };
+}
+
+integer_tag_type! {
+    byte, i8, "TAG_Byte", serialize_i8;
+    short, i16, "TAG_Short", serialize_i16;
+    int, i32, "TAG_Int", serialize_i32;
+    long, i64, "TAG_Long", serialize_i64;
+}
+
+float_tag_type! {
+    float, f32, "TAG_Float", serialize_f32;
+    double, f64, "TAG_Double", serialize_f64;
+}
@@ -6,6 +6,7 @@ use std::fs::File;
 
 use blob::Blob;
 use error::Error;
+use tag::TagId;
 use value::Value;
 
 #[test]
@@ -175,12 +176,20 @@ fn nbt_nested_list() {
 
 #[test]
 fn nbt_no_root() {
-    let bytes = vec![0x00];
+    let bytes = [0x01, 0x00, 0x00, 0x01];
     // Will fail, because the root is not a compound.
     assert_eq!(Blob::from_reader(&mut io::Cursor::new(&bytes[..])),
             Err(Error::NoRootCompound));
 }
 
+#[test]
+fn nbt_end_only() {
+    let bytes = [0x00];
+    // A lone TAG_End is an empty document.
+    assert_eq!(Blob::from_reader(&mut io::Cursor::new(&bytes[..])),
+            Ok(Blob::new()));
+}
+
 #[test]
 fn nbt_no_end_tag() {
     let bytes = vec![
@@ -221,7 +230,7 @@ fn nbt_invalid_list() {
     badlist.push(Value::Short(1));
     // Will fail to insert, because the List is heterogeneous.
     assert_eq!(nbt.insert("list", Value::List(badlist)),
-               Err(Error::HeterogeneousList));
+               Err(Error::HeterogeneousList { index: 1, expected: TagId::Byte, got: TagId::Short }));
 }
 
 #[test]
@@ -365,3 +374,2257 @@ fn nbt_modified_utf8() {
     let file = Blob::from_reader(&mut src).unwrap();
     assert_eq!(&file, &nbt);
 }
+
+#[test]
+fn map_image_roundtrip() {
+    // Base colour 1 (grass) at full brightness, then transparent.
+    let map = Value::ByteArray(vec![6, 0, 7, 1]);
+    let image = map.byte_array_as_image(2, 2).unwrap();
+    assert_eq!(image[0], [127, 178, 56, 255]);
+    assert_eq!(image[1], [0, 0, 0, 0]);
+    assert_eq!(image[3], [0, 0, 0, 0]);
+
+    assert_eq!(Value::from_image(&image), Value::ByteArray(vec![6, 0, 7, 0]));
+
+    // Mismatched dimensions and non-array values are rejected.
+    assert_eq!(map.byte_array_as_image(3, 3), None);
+    assert_eq!(map.byte_array_as_image(usize::MAX, 2), None);
+    assert_eq!(Value::Int(0).byte_array_as_image(1, 1), None);
+}
+
+#[test]
+fn value_debug_tag_names() {
+    let mut map = HashMap::new();
+    map.insert("y".to_string(), Value::Int(2));
+    map.insert("x".to_string(), Value::Int(1));
+    assert_eq!(format!("{:?}", Value::Compound(map)),
+               "TAG_Compound { \"x\": TAG_Int(1), \"y\": TAG_Int(2) }");
+    assert_eq!(format!("{:?}", Value::Compound(HashMap::new())), "TAG_Compound {}");
+    assert_eq!(format!("{:?}", Value::String("hello".to_string())),
+               "TAG_String(\"hello\")");
+    assert_eq!(format!("{:?}", Value::List(vec![Value::Float(0.5)])),
+               "TAG_List [TAG_Float(0.5)]");
+    assert_eq!(format!("{:?}", Value::IntArray(vec![1, -2])),
+               "TAG_IntArray([1, -2])");
+}
+
+#[test]
+fn value_nbt_tree_string() {
+    let mut map = HashMap::new();
+    map.insert("x".to_string(), Value::Int(5));
+    map.insert("name".to_string(), Value::String("Bananrama".to_string()));
+    map.insert("pos".to_string(), Value::List(vec![Value::Double(0.5), Value::Double(64.0)]));
+    map.insert("blocks".to_string(), Value::ByteArray(vec![0; 16]));
+
+    let expected = "\
+TAG_Compound(''): 4 entries
+{
+  TAG_ByteArray('blocks'): [16 bytes]
+  TAG_String('name'): Bananrama
+  TAG_List('pos'): 2 entries
+  {
+    TAG_Double(None): 0.5
+    TAG_Double(None): 64
+  }
+  TAG_Int('x'): 5
+}";
+    assert_eq!(Value::Compound(map).to_nbt_tree_string(), expected);
+}
+
+#[test]
+#[cfg(feature = "uuid")]
+fn uuid_int_array_conversion() {
+    use std::convert::TryFrom;
+    use uuid::Uuid;
+
+    // The example UUID from RFC 4122.
+    let uuid = Uuid::parse_str("f81d4fae-7dec-11d0-a765-00a0c91e6bf6").unwrap();
+    let ints = Value::IntArray(vec![-132296786, 2112623056, -1486552928, -920753162]);
+
+    assert_eq!(Value::from(uuid), ints);
+    assert_eq!(Uuid::try_from(ints).unwrap(), uuid);
+
+    assert_eq!(Uuid::try_from(Value::IntArray(vec![1, 2, 3])),
+               Err(Error::InvalidUuidLength(3)));
+    assert_eq!(Uuid::try_from(Value::Int(1)), Err(Error::TagMismatch(TagId::Int, TagId::IntArray)));
+}
+
+#[test]
+fn value_widen_narrow_numeric() {
+    let mut map = HashMap::new();
+    map.insert("a".to_string(), Value::Byte(1));
+    map.insert("b".to_string(), Value::List(vec![Value::Short(2), Value::Short(3)]));
+    map.insert("c".to_string(), Value::Float(0.5));
+    let mut val = Value::Compound(map);
+
+    val.widen_numeric_tags();
+    match val {
+        Value::Compound(ref map) => {
+            assert_eq!(map["a"], Value::Long(1));
+            assert_eq!(map["b"], Value::List(vec![Value::Long(2), Value::Long(3)]));
+            assert_eq!(map["c"], Value::Float(0.5));
+        },
+        _ => panic!("widening changed the root type"),
+    }
+
+    val.try_narrow_numeric_tags().unwrap();
+    match val {
+        Value::Compound(ref map) => assert_eq!(map["a"], Value::Int(1)),
+        _ => panic!("narrowing changed the root type"),
+    }
+
+    // Out-of-range values leave the whole tree untouched.
+    let mut list = Value::List(vec![Value::Long(1), Value::Long(1 << 40)]);
+    assert_eq!(list.try_narrow_numeric_tags(), Err(Error::NumericOutOfRange(1 << 40)));
+    assert_eq!(list, Value::List(vec![Value::Long(1), Value::Long(1 << 40)]));
+}
+
+#[test]
+fn nbt_duplicate_keys() {
+    use config::ParseConfig;
+
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x01,
+                0x00, 0x01,
+                0x61,
+                0x01,
+            0x01,
+                0x00, 0x01,
+                0x61,
+                0x02,
+        0x00
+    ];
+
+    // By default, the last value wins.
+    let file = Blob::from_reader(&mut io::Cursor::new(&bytes[..])).unwrap();
+    assert_eq!(file["a"], Value::Byte(2));
+
+    let config = ParseConfig { reject_duplicate_keys: true, ..Default::default() };
+    assert_eq!(Blob::from_reader_with_config(&mut io::Cursor::new(&bytes[..]), &config),
+               Err(Error::DuplicateKey("a".to_string())));
+}
+
+#[test]
+fn nbt_write_arrays() {
+    let mut nbt = Blob::new();
+    nbt.insert("ba", Value::ByteArray(vec![-2, -1, 0, 1, 2])).unwrap();
+
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x07,
+                0x00, 0x02,
+                0x62, 0x61,
+                0x00, 0x00, 0x00, 0x05,
+                0xfe, 0xff, 0x00, 0x01, 0x02,
+        0x00
+    ];
+    let mut dst = Vec::new();
+    nbt.to_writer(&mut dst).unwrap();
+    assert_eq!(&dst, &bytes);
+
+    let mut nbt = Blob::new();
+    nbt.insert("ia", Value::IntArray(vec![-2, 0x01020304])).unwrap();
+
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x0b,
+                0x00, 0x02,
+                0x69, 0x61,
+                0x00, 0x00, 0x00, 0x02,
+                0xff, 0xff, 0xff, 0xfe,
+                0x01, 0x02, 0x03, 0x04,
+        0x00
+    ];
+    let mut dst = Vec::new();
+    nbt.to_writer(&mut dst).unwrap();
+    assert_eq!(&dst, &bytes);
+}
+
+#[test]
+#[cfg(feature = "checksum")]
+fn value_sha256_digest() {
+    let mut a = HashMap::new();
+    let mut b = HashMap::new();
+    for i in 0..32 {
+        a.insert(format!("key{}", i), Value::Int(i));
+    }
+    for i in (0..32).rev() {
+        b.insert(format!("key{}", i), Value::Int(i));
+    }
+    let a = Value::Compound(a);
+    let mut b = Value::Compound(b);
+    assert_eq!(a.sha256_digest(), b.sha256_digest());
+
+    if let Value::Compound(ref mut map) = b {
+        map.insert("key0".to_string(), Value::Int(100));
+    }
+    assert!(a.sha256_digest() != b.sha256_digest());
+    assert!(Value::Int(1).sha256_digest() != Value::Long(1).sha256_digest());
+
+    // Values that differ only after a heterogeneous list, which cannot be
+    // written as NBT, still hash differently.
+    let mixed = Value::List(vec![Value::Int(1), Value::Byte(2)]);
+    let c = Value::List(vec![mixed.clone(), Value::Int(1)]);
+    let d = Value::List(vec![mixed, Value::Int(2)]);
+    assert!(c.sha256_digest() != d.sha256_digest());
+}
+
+#[test]
+#[cfg(feature = "cache")]
+fn nbt_blob_cache() {
+    use std::sync::Arc;
+    use cache::NbtBlobCache;
+
+    let mut first = Vec::new();
+    let mut nbt = Blob::new();
+    nbt.insert("a", 1i8).unwrap();
+    nbt.to_writer(&mut first).unwrap();
+
+    let mut second = Vec::new();
+    nbt.insert("a", 2i8).unwrap();
+    nbt.to_writer(&mut second).unwrap();
+
+    let mut cache = NbtBlobCache::new(1);
+    let a = cache.get_or_load("a", || Ok(first.clone())).unwrap();
+    let b = cache.get_or_load("a", || Ok(first.clone())).unwrap();
+    assert!(Arc::ptr_eq(&a, &b));
+
+    // Changed bytes are re-parsed.
+    let c = cache.get_or_load("a", || Ok(second.clone())).unwrap();
+    assert!(!Arc::ptr_eq(&a, &c));
+
+    // Loading a second key evicts the first.
+    cache.get_or_load("b", || Ok(first.clone())).unwrap();
+    assert_eq!(cache.len(), 1);
+    let d = cache.get_or_load("a", || Ok(second.clone())).unwrap();
+    assert!(!Arc::ptr_eq(&c, &d));
+    assert_eq!(c, d);
+
+    // Loader errors are passed through.
+    assert_eq!(cache.get_or_load("a", || Err(Error::IncompleteNbtValue)),
+               Err(Error::IncompleteNbtValue));
+}
+
+#[test]
+fn error_conversions() {
+    use std::io::ErrorKind;
+
+    let e: Error = io::Error::new(ErrorKind::UnexpectedEof, "eof").into();
+    assert_eq!(e, Error::IncompleteNbtValue);
+    let e: Error = io::Error::new(ErrorKind::PermissionDenied, "denied").into();
+    match e {
+        Error::IoError(ref inner) => assert_eq!(inner.kind(), ErrorKind::PermissionDenied),
+        _ => panic!("io::Error was not wrapped"),
+    }
+
+    // A lone continuation byte is not valid CESU-8.
+    let e: Error = ::cesu8::from_java_cesu8(&[0x80]).unwrap_err().into();
+    assert_eq!(e, Error::InvalidUtf8);
+}
+
+#[test]
+fn value_checked_arithmetic() {
+    assert_eq!(Value::Int(40) + 2, Ok(Value::Int(42)));
+    assert_eq!(Value::Long(40) - 2i64, Ok(Value::Long(38)));
+    assert_eq!(Value::Short(-3) * 3, Ok(Value::Short(-9)));
+    assert_eq!(Value::Byte(9) / 2, Ok(Value::Byte(4)));
+
+    // Results must fit in the tag type of the left-hand side.
+    assert_eq!(Value::Byte(100) + 28, Err(Error::ArithmeticOverflow));
+    assert_eq!(Value::Int(i32::MAX) + 1i64, Err(Error::ArithmeticOverflow));
+    assert_eq!(Value::Long(i64::MIN) / -1i64, Err(Error::ArithmeticOverflow));
+    assert_eq!(Value::Int(1) / 0, Err(Error::ArithmeticOverflow));
+
+    assert_eq!(Value::Float(1.0) + 1, Err(Error::TagMismatch(TagId::Float, TagId::Int)));
+}
+
+#[test]
+fn minecraft_level_dat() {
+    use std::convert::TryFrom;
+    use flate2::read::GzDecoder;
+    use minecraft::LevelDat;
+
+    let file = File::open("tests/level.dat").unwrap();
+    let mut src = GzDecoder::new(file).unwrap();
+    let (tag, _) = ::raw::emit_next_header(&mut src).unwrap();
+    let value = Value::from_reader(tag, &mut src).unwrap();
+
+    let level = LevelDat::try_from(value.clone()).unwrap();
+    assert_eq!(level.version, 19133);
+    assert_eq!(level.level_name, "1.0.0");
+    assert_eq!(level.random_seed, Some(3274077273284000072));
+    assert_eq!(level.spawn, (245, 64, 249));
+    assert_eq!(level.hardcore, Some(false));
+    assert_eq!(level.difficulty, Some(3));
+    assert!(level.other.contains_key("Player"));
+
+    // Nothing is lost in the roundtrip.
+    assert_eq!(Value::from(level), value);
+
+    assert_eq!(LevelDat::try_from(Value::Compound(HashMap::new())),
+               Err(Error::MissingField("Data".to_string())));
+}
+
+#[test]
+fn value_tag_counts() {
+    let mut inner = HashMap::new();
+    inner.insert("a".to_string(), Value::Int(1));
+    inner.insert("b".to_string(), Value::List(vec![Value::Int(2), Value::Int(3)]));
+    let mut map = HashMap::new();
+    map.insert("inner".to_string(), Value::Compound(inner));
+    map.insert("x".to_string(), Value::Int(4));
+    map.insert("y".to_string(), Value::Byte(5));
+    let val = Value::Compound(map);
+
+    let counts = val.value_tag_counts();
+    assert_eq!(counts.len(), 3);
+    assert_eq!(counts[&TagId::Int], 1);
+    assert_eq!(counts[&TagId::Byte], 1);
+    assert_eq!(counts[&TagId::Compound], 1);
+
+    let counts = val.value_tag_counts_recursive();
+    assert_eq!(counts[&TagId::Int], 4);
+    assert_eq!(counts[&TagId::List], 1);
+    assert_eq!(counts[&TagId::Compound], 1);
+
+    assert!(Value::Int(1).value_tag_counts().is_empty());
+}
+
+#[test]
+fn value_sorted_writer() {
+    let mut map = HashMap::new();
+    map.insert("b".to_string(), Value::Byte(2));
+    map.insert("a".to_string(), Value::Byte(1));
+
+    let bytes = vec![
+        0x01,
+            0x00, 0x01,
+            0x61,
+            0x01,
+        0x01,
+            0x00, 0x01,
+            0x62,
+            0x02,
+        0x00
+    ];
+    let mut dst = Vec::new();
+    Value::Compound(map).to_writer_sorted(&mut dst).unwrap();
+    assert_eq!(&dst, &bytes);
+}
+
+#[test]
+fn binary_diff_roundtrip() {
+    use diff::{binary_diff, binary_patch, nbt_diff_bytes};
+
+    let old: Vec<u8> = (0..200).map(|i| (i * 7) as u8).collect();
+    let mut new = old.clone();
+    new[100] = 0xff;
+    new.extend_from_slice(b"appended");
+
+    let patch = binary_diff(&old, &new);
+    assert!(patch.len() < new.len() / 2);
+    assert_eq!(binary_patch(&old, &patch).unwrap(), new);
+
+    // Unrelated and empty inputs work too.
+    assert_eq!(binary_patch(&old, &binary_diff(&old, b"xyz")).unwrap(), b"xyz");
+    assert_eq!(binary_patch(&old, &binary_diff(&old, b"")).unwrap(), b"");
+    assert_eq!(binary_patch(b"", &binary_diff(b"", &new)).unwrap(), new);
+
+    // Copies outside the base are rejected.
+    assert_eq!(binary_patch(&old[..10], &patch), Err(Error::InvalidPatch));
+    assert_eq!(binary_patch(&old, &[0x02]), Err(Error::InvalidPatch));
+    // An ADD claiming far more bytes than the patch holds.
+    assert_eq!(binary_patch(&old, &[0x01, 0xff, 0xff, 0xff, 0xff, b'a']), Err(Error::InvalidPatch));
+
+    let mut map = HashMap::new();
+    for i in 0..20 {
+        map.insert(format!("entry{}", i), Value::Int(i));
+    }
+    let before = Value::Compound(map.clone());
+    map.insert("entry5".to_string(), Value::Int(500));
+    let after = Value::Compound(map);
+    let patch = nbt_diff_bytes(&before, &after).unwrap();
+
+    let mut base = vec![0x0a];
+    before.to_writer_sorted(&mut base).unwrap();
+    let patched = binary_patch(&base, &patch).unwrap();
+    let mut src = io::Cursor::new(&patched[1..]);
+    assert_eq!(patched[0], 0x0a);
+    assert_eq!(Value::from_reader(TagId::Compound, &mut src).unwrap(), after);
+}
+
+#[test]
+fn value_from_pretty_debug() {
+    let mut inner = HashMap::new();
+    inner.insert("name".to_string(), Value::String("a \"quoted\"\n\\ ✓ \u{7f}".to_string()));
+    inner.insert("empty".to_string(), Value::Compound(HashMap::new()));
+    let mut map = HashMap::new();
+    map.insert("byte".to_string(), Value::Byte(-1));
+    map.insert("short".to_string(), Value::Short(300));
+    map.insert("long".to_string(), Value::Long(i64::MIN));
+    map.insert("float".to_string(), Value::Float(1.5e-7));
+    map.insert("double".to_string(), Value::Double(f64::INFINITY));
+    map.insert("bytes".to_string(), Value::ByteArray(vec![1, -2, 3]));
+    map.insert("ints".to_string(), Value::IntArray(vec![]));
+    map.insert("longs".to_string(), Value::LongArray(vec![7, 8]));
+    map.insert("list".to_string(), Value::List(vec![Value::Int(1), Value::Int(2)]));
+    map.insert("nested".to_string(), Value::Compound(inner));
+    let value = Value::Compound(map);
+
+    let compact = format!("{:?}", value);
+    assert_eq!(Value::from_pretty_debug(&compact).unwrap(), value);
+    let pretty = format!("{:#?}", value);
+    assert_eq!(Value::from_pretty_debug(&pretty).unwrap(), value);
+    assert_eq!(Value::from_pretty_debug(" TAG_List [ ] ").unwrap(), Value::List(vec![]));
+
+    assert_eq!(Value::from_pretty_debug("TAG_Int(1) x"), Err(Error::InvalidDebugFormat(11)));
+    assert_eq!(Value::from_pretty_debug("TAG_Byte(300)"), Err(Error::InvalidDebugFormat(9)));
+    assert_eq!(Value::from_pretty_debug("TAG_Bool(1)"), Err(Error::InvalidDebugFormat(4)));
+    assert_eq!(Value::from_pretty_debug("TAG_String(\"abc"), Err(Error::InvalidDebugFormat(15)));
+}
+
+#[test]
+fn value_ordering() {
+    let mut values = vec![
+        Value::String("b".to_string()),
+        Value::Double(2.5),
+        Value::Long(3),
+        Value::List(vec![Value::Int(1), Value::Int(2)]),
+        Value::Byte(1),
+        Value::String("a".to_string()),
+        Value::Int(1),
+        Value::Float(-1.0),
+        Value::List(vec![Value::Int(1)]),
+        Value::IntArray(vec![1]),
+    ];
+    values.sort();
+    assert_eq!(values, vec![
+        Value::Float(-1.0),
+        Value::Byte(1),
+        Value::Int(1),
+        Value::Double(2.5),
+        Value::Long(3),
+        Value::String("a".to_string()),
+        Value::String("b".to_string()),
+        Value::List(vec![Value::Int(1)]),
+        Value::List(vec![Value::Int(1), Value::Int(2)]),
+        Value::IntArray(vec![1]),
+    ]);
+
+    let compound = |entries: &[(&str, i32)]| {
+        Value::Compound(entries.iter().map(|&(k, v)| (k.to_string(), Value::Int(v))).collect())
+    };
+    assert!(compound(&[("a", 1), ("b", 2)]) < compound(&[("a", 1), ("b", 3)]));
+    assert!(compound(&[("b", 0)]) > compound(&[("a", 9), ("b", 0)]));
+    assert_eq!(compound(&[("a", 1), ("b", 2)]).cmp(&compound(&[("b", 2), ("a", 1)])),
+               ::std::cmp::Ordering::Equal);
+
+    // Equality agrees with the ordering for special floats.
+    let nan = Value::Double(f64::NAN);
+    assert_eq!(nan, nan.clone());
+    assert_eq!(nan.cmp(&nan.clone()), ::std::cmp::Ordering::Equal);
+    assert!(Value::Float(-0.0) != Value::Float(0.0));
+    assert!(Value::Float(-0.0) < Value::Float(0.0));
+    let mut floats = vec![Value::Float(0.0), Value::Float(f32::NAN), Value::Float(-0.0), Value::Float(f32::NAN)];
+    floats.sort();
+    floats.dedup();
+    assert_eq!(floats, vec![Value::Float(-0.0), Value::Float(0.0), Value::Float(f32::NAN)]);
+}
+
+#[test]
+fn value_coerce_string() {
+    assert_eq!(Value::Byte(1).coerce_string(), Some("1".to_string()));
+    assert_eq!(Value::Short(-300).coerce_string(), Some("-300".to_string()));
+    assert_eq!(Value::Long(1 << 40).coerce_string(), Some("1099511627776".to_string()));
+    assert_eq!(Value::Float(3.25).coerce_string(), Some("3.25".to_string()));
+    assert_eq!(Value::Double(2.0).coerce_string(), Some("2.0".to_string()));
+    assert_eq!(Value::Double(-0.0).coerce_string(), Some("-0.0".to_string()));
+    assert_eq!(Value::Double(0.001).coerce_string(), Some("0.001".to_string()));
+    assert_eq!(Value::Double(9999999.5).coerce_string(), Some("9999999.5".to_string()));
+    assert_eq!(Value::Double(1e7).coerce_string(), Some("1.0E7".to_string()));
+    assert_eq!(Value::Double(10000000000.0).coerce_string(), Some("1.0E10".to_string()));
+    assert_eq!(Value::Double(-1.25e300).coerce_string(), Some("-1.25E300".to_string()));
+    assert_eq!(Value::Double(2.5e-4).coerce_string(), Some("2.5E-4".to_string()));
+    assert_eq!(Value::Float(1e10).coerce_string(), Some("1.0E10".to_string()));
+    assert_eq!(Value::Float(1e-5).coerce_string(), Some("1.0E-5".to_string()));
+    assert_eq!(Value::Double(f64::NEG_INFINITY).coerce_string(), Some("-Infinity".to_string()));
+    assert_eq!(Value::Float(f32::NAN).coerce_string(), Some("NaN".to_string()));
+    assert_eq!(Value::String("hi".to_string()).coerce_string(), Some("hi".to_string()));
+    assert_eq!(Value::IntArray(vec![1]).coerce_string(), None);
+    assert_eq!(Value::List(vec![]).coerce_string(), None);
+    assert_eq!(Value::Compound(HashMap::new()).coerce_string(), None);
+}
+
+#[test]
+fn compound_macro() {
+    assert_eq!(compound! {}, Value::Compound(HashMap::new()));
+
+    let mut pos = HashMap::new();
+    pos.insert("x".to_string(), Value::Int(1));
+    let mut map = HashMap::new();
+    map.insert("name".to_string(), Value::String("Steve".to_string()));
+    map.insert("count".to_string(), Value::Byte(3));
+    map.insert("pos".to_string(), Value::Compound(pos));
+
+    let key = String::from("count");
+    assert_eq!(compound! {
+        "name" => "Steve",
+        key => 3i8,
+        "pos" => compound! { "x" => 1 },
+    }, Value::Compound(map));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "duplicate key \"count\" in compound!")]
+fn compound_macro_duplicate_key() {
+    let _ = compound! { "count" => 1i8, "count" => 3i8 };
+}
+
+#[test]
+fn value_bool_conversion() {
+    use std::convert::TryFrom;
+
+    assert_eq!(Value::from(true), Value::Byte(1));
+    assert_eq!(Value::from(false), Value::Byte(0));
+    assert_eq!(bool::try_from(Value::Byte(1)), Ok(true));
+    assert_eq!(bool::try_from(Value::Byte(0)), Ok(false));
+    assert_eq!(bool::try_from(Value::Byte(2)), Err(Error::NonBooleanByte(2)));
+    assert_eq!(bool::try_from(Value::Int(1)), Err(Error::TagMismatch(TagId::Int, TagId::Byte)));
+
+    assert_eq!(Value::Byte(1).as_bool(), Some(true));
+    assert_eq!(Value::Byte(-1).as_bool(), None);
+    assert_eq!(Value::Short(0).as_bool(), None);
+}
+
+#[test]
+fn value_iter_sorted() {
+    let value = compound! { "b" => 2, "c" => 3, "a" => 1 };
+    let entries: Vec<_> = value.iter_sorted().unwrap().collect();
+    assert_eq!(entries, vec![("a", &Value::Int(1)), ("b", &Value::Int(2)), ("c", &Value::Int(3))]);
+    assert!(Value::Int(1).iter_sorted().is_none());
+}
+
+#[test]
+fn nbt_reader_skip_value() {
+    use reader::NbtReader;
+
+    let mut map = HashMap::new();
+    map.insert("byte".to_string(), Value::Byte(1));
+    map.insert("short".to_string(), Value::Short(2));
+    map.insert("double".to_string(), Value::Double(3.0));
+    map.insert("bytes".to_string(), Value::ByteArray(vec![1, 2, 3]));
+    map.insert("ints".to_string(), Value::IntArray(vec![4, 5]));
+    map.insert("longs".to_string(), Value::LongArray(vec![6]));
+    map.insert("string".to_string(), Value::String("text".to_string()));
+    map.insert("ints_list".to_string(), Value::List(vec![Value::Int(1), Value::Int(2)]));
+    map.insert("nested_list".to_string(), Value::List(vec![
+        Value::List(vec![Value::String("a".to_string())]),
+        Value::List(vec![]),
+    ]));
+    map.insert("compound".to_string(), compound! { "x" => 1, "y" => "two" });
+    let mut blob = Blob::new();
+    blob.insert("data", Value::Compound(map)).unwrap();
+    blob.insert("after", 42i32).unwrap();
+
+    let mut bytes = Vec::new();
+    blob.to_writer(&mut bytes).unwrap();
+
+    let mut reader = NbtReader::new(io::Cursor::new(&bytes[..]));
+    assert_eq!(reader.read_header().unwrap(), (TagId::Compound, "".to_string()));
+    loop {
+        let (tag, name) = reader.read_header().unwrap();
+        match &name[..] {
+            "data" => reader.skip_value(tag).unwrap(),
+            "after" => assert_eq!(reader.read_value(tag).unwrap(), Value::Int(42)),
+            _ => {
+                assert_eq!(tag, TagId::End);
+                break;
+            },
+        }
+    }
+    let cursor = reader.into_inner();
+    assert_eq!(cursor.position() as usize, bytes.len());
+
+    // Truncated payloads are reported with how much data was available.
+    let mut reader = NbtReader::new(&[0x00, 0x00, 0x00, 0x04, 0x01][..]);
+    assert_eq!(reader.skip_value(TagId::IntArray),
+               Err(Error::TruncatedArray { tag_id: TagId::IntArray, declared_len: 4, bytes_available: 1 }));
+    let mut reader = NbtReader::new(&[0x0d][..]);
+    assert_eq!(reader.skip_value(TagId::Compound), Err(Error::InvalidTypeId(0x0d)));
+}
+
+#[test]
+fn value_get_list_typed() {
+    let value = compound! {
+        "ints" => Value::List(vec![Value::Int(1), Value::Int(2)]),
+        "strings" => Value::List(vec![Value::String("a".to_string())]),
+        "mixed" => Value::List(vec![Value::Int(1), Value::Short(2)]),
+        "single" => 5,
+    };
+    assert_eq!(value.get_list_typed::<i32>("ints"), Ok(vec![1, 2]));
+    assert_eq!(value.get_list_typed::<String>("strings"), Ok(vec!["a".to_string()]));
+    assert_eq!(value.get_list_typed::<i32>("mixed"),
+               Err(Error::InvalidListElement(1, Box::new(Error::TagMismatch(TagId::Short, TagId::Int)))));
+    assert_eq!(value.get_list_typed::<i64>("ints"),
+               Err(Error::InvalidListElement(0, Box::new(Error::TagMismatch(TagId::Int, TagId::Long)))));
+    assert_eq!(value.get_list_typed::<i32>("single"), Err(Error::TagMismatch(TagId::Int, TagId::List)));
+    assert_eq!(value.get_list_typed::<i32>("absent"), Err(Error::MissingField("absent".to_string())));
+    assert_eq!(Value::Int(0).get_list_typed::<i32>("ints"), Err(Error::TagMismatch(TagId::Int, TagId::Compound)));
+
+    let err = value.get_list_typed::<i32>("mixed").unwrap_err();
+    assert_eq!(err.to_string(),
+               "list element 1 could not be converted: encountered NBT tag 'TAG_Short' but expected 'TAG_Int'");
+}
+
+#[test]
+fn value_ordered_writer() {
+    use order;
+
+    let value = compound! { "b" => 2i8, "a" => 1i8, "c" => 3i8 };
+    let keys = |cmp: fn(&str, &str) -> ::std::cmp::Ordering| {
+        let mut dst = Vec::new();
+        value.to_writer_ordered(&mut dst, cmp).unwrap();
+        // Each entry is a tag byte, a two-byte name length, the name, and
+        // the payload.
+        dst.chunks(5).filter(|c| c.len() == 5).map(|c| c[3] as char).collect::<String>()
+    };
+    assert_eq!(keys(order::lexicographic), "abc");
+    assert_eq!(keys(order::reverse_lexicographic), "cba");
+
+    let mut natural = Vec::new();
+    value.to_writer_ordered(&mut natural, order::natural_order).unwrap();
+    let mut unordered = Vec::new();
+    value.to_writer(&mut unordered).unwrap();
+    assert_eq!(natural, unordered);
+}
+
+#[test]
+fn value_get_path() {
+    let chunk = compound! {
+        "Level" => compound! {
+            "xPos" => 3,
+            "Sections" => Value::List(vec![compound! { "Y" => 0i8 }, compound! { "Y" => 1i8 }]),
+        },
+    };
+    assert_eq!(chunk.get_path("Level.xPos"), Some(&Value::Int(3)));
+    assert_eq!(chunk.get_path("Level.Sections.1.Y"), Some(&Value::Byte(1)));
+    assert_eq!(chunk.get_path("Level.Sections.2.Y"), None);
+    assert_eq!(chunk.get_path("Level.xPos.z"), None);
+    assert_eq!(chunk.get_path("Level.Sections.first"), None);
+
+    assert_eq!(chunk.get_path_typed::<i32>("Level.xPos"), Ok(3));
+    assert_eq!(chunk.get_path_typed::<i8>("Level.Sections.0.Y"), Ok(0));
+    assert_eq!(chunk.get_path_typed::<i64>("Level.xPos"), Err(Error::TagMismatch(TagId::Int, TagId::Long)));
+    assert_eq!(chunk.get_path_typed::<i32>("Level.zPos"),
+               Err(Error::PathNotFound("Level.zPos".to_string())));
+}
+
+#[test]
+fn nbt_root_name() {
+    let mut blob = Blob::named("Level");
+    blob.insert("xPos", 1i32).unwrap();
+    let mut bytes = Vec::new();
+    blob.to_writer(&mut bytes).unwrap();
+
+    let read = Blob::from_reader_named(&mut io::Cursor::new(&bytes[..]), "Level");
+    assert_eq!(read, Ok(blob));
+
+    let read = Blob::from_reader_named(&mut io::Cursor::new(&bytes[..]), "Data");
+    assert_eq!(read, Err(Error::RootNameMismatch {
+        expected: "Data".to_string(),
+        got: "Level".to_string(),
+    }));
+}
+
+#[test]
+fn value_element_tag_id() {
+    assert_eq!(Value::List(vec![Value::Short(1), Value::Short(2)]).element_tag_id(),
+               Some(TagId::Short));
+    assert_eq!(Value::List(vec![]).element_tag_id(), Some(TagId::End));
+    assert_eq!(Value::IntArray(vec![1]).element_tag_id(), None);
+}
+
+#[test]
+fn compound_builder() {
+    use builder::CompoundBuilder;
+
+    let built = CompoundBuilder::new()
+        .insert_byte("byte", 1)
+        .insert_bool("bool", true)
+        .insert_short("short", 2)
+        .insert_int("int", 3)
+        .insert_long("long", 4)
+        .insert_float("float", 5.0)
+        .insert_double("double", 6.0)
+        .insert_string("string", "seven")
+        .insert_byte_array("bytes", vec![8])
+        .insert_int_array("ints", vec![9])
+        .insert_long_array("longs", vec![10])
+        .insert_compound("nested", CompoundBuilder::new().insert_int("x", 11))
+        .insert("other", 12i16)
+        .insert_int("int", 13)
+        .build();
+
+    assert_eq!(built, compound! {
+        "byte" => 1i8,
+        "bool" => 1i8,
+        "short" => 2i16,
+        "int" => 13,
+        "long" => 4i64,
+        "float" => 5.0f32,
+        "double" => 6.0f64,
+        "string" => "seven",
+        "bytes" => vec![8i8],
+        "ints" => vec![9i32],
+        "longs" => vec![10i64],
+        "nested" => compound! { "x" => 11 },
+        "other" => 12i16,
+    });
+    assert_eq!(CompoundBuilder::new().build(), Value::Compound(HashMap::new()));
+}
+
+#[test]
+fn list_builder() {
+    use builder::{CompoundBuilder, ListBuilder};
+
+    let bytes = ListBuilder::new_byte().push(1).push(2).build();
+    assert_eq!(bytes, Value::List(vec![Value::Byte(1), Value::Byte(2)]));
+    let strings = ListBuilder::new_string().push("a".to_string()).build();
+    assert_eq!(strings, Value::List(vec![Value::String("a".to_string())]));
+    let compounds = ListBuilder::new_compound()
+        .push(CompoundBuilder::new().insert_int("x", 1))
+        .build();
+    assert_eq!(compounds, Value::List(vec![compound! { "x" => 1 }]));
+    let empty = ListBuilder::new_long();
+    assert!(empty.is_empty());
+    assert_eq!(empty.build(), Value::List(vec![]));
+
+    let inferred = ListBuilder::infer().try_push(1i16).unwrap().try_push(2i16).unwrap();
+    assert_eq!(inferred.len(), 2);
+    assert_eq!(inferred.clone().try_push(3i32),
+               Err(Error::HeterogeneousList { index: 2, expected: TagId::Short, got: TagId::Int }));
+    assert_eq!(inferred.build(), Value::List(vec![Value::Short(1), Value::Short(2)]));
+
+    let built = CompoundBuilder::new()
+        .insert_list("Pos", ListBuilder::new_double().push(0.5).push(64.0))
+        .build();
+    assert_eq!(built.get_list_typed::<f64>("Pos"), Ok(vec![0.5, 64.0]));
+}
+
+#[test]
+fn minecraft_timestamps() {
+    use std::time::{Duration, UNIX_EPOCH};
+    use minecraft::{nbt_to_system_time, system_time_to_nbt};
+
+    let later = UNIX_EPOCH + Duration::from_millis(1_600_000_000_123);
+    assert_eq!(system_time_to_nbt(later), Value::Long(1_600_000_000_123));
+    assert_eq!(nbt_to_system_time(&Value::Long(1_600_000_000_123)), Some(later));
+
+    let earlier = UNIX_EPOCH - Duration::from_millis(86_400_000);
+    assert_eq!(system_time_to_nbt(earlier), Value::Long(-86_400_000));
+    assert_eq!(nbt_to_system_time(&Value::Long(-86_400_000)), Some(earlier));
+
+    assert_eq!(nbt_to_system_time(&Value::Int(0)), None);
+}
+
+#[test]
+fn nbt_reader_events() {
+    use reader::{NbtEvent, NbtReader};
+
+    let mut blob = Blob::named("root");
+    blob.insert("list", Value::List(vec![
+        Value::IntArray(vec![1, 2]),
+        Value::IntArray(vec![]),
+    ])).unwrap();
+    let mut bytes = Vec::new();
+    blob.to_writer(&mut bytes).unwrap();
+
+    let mut reader = NbtReader::new(&bytes[..]);
+    let mut events = Vec::new();
+    while let Some(event) = reader.next().unwrap() {
+        events.push(format!("{:?}", event));
+    }
+    assert_eq!(events, vec![
+        "Name(\"root\")",
+        "StartCompound",
+        "Name(\"list\")",
+        "StartList { tag_id: IntArray, len: 2 }",
+        "IntArray([1, 2])",
+        "IntArray([])",
+        "EndList",
+        "EndCompound",
+    ]);
+
+    let mut reader = NbtReader::new(&[0x00][..]);
+    assert_eq!(reader.next(), Ok(None));
+
+    let mut reader = NbtReader::new(&[0x0a, 0x00, 0x00, 0x0d, 0x00, 0x00][..]);
+    assert_eq!(reader.next(), Ok(Some(NbtEvent::Name(""))));
+    assert_eq!(reader.next(), Ok(Some(NbtEvent::StartCompound)));
+    assert_eq!(reader.next(), Err(Error::InvalidTypeId(0x0d)));
+}
+
+#[test]
+fn value_gzip_roundtrip() {
+    let value = compound! { "name" => "Herobrine", "health" => 100i8 };
+    let bytes = value.compress_to_gzip().unwrap();
+    assert_eq!(&bytes[..2], &[0x1f, 0x8b]);
+    assert_eq!(Value::decompress_from_gzip(&bytes), Ok(value.clone()));
+
+    // The root is unnamed, so it can also be read as a `Blob`.
+    let blob = Blob::from_gzip_reader(&mut &bytes[..]).unwrap();
+    assert_eq!(blob["name"], Value::String("Herobrine".to_string()));
+}
+
+#[test]
+fn nbt_patch() {
+    use std::convert::TryFrom;
+    use patch::{NbtOp, NbtPatch};
+
+    let set = |path: &str, value: Value| NbtOp::Set { path: path.to_string(), value };
+    let delete = |path: &str| NbtOp::Delete { path: path.to_string() };
+    let append = |path: &str, value: Value| NbtOp::Append { path: path.to_string(), value };
+
+    let mut val = compound! {
+        "Health" => 20.0f32,
+        "Pos" => Value::List(vec![Value::Double(0.0), Value::Double(64.0)]),
+        "Abilities" => compound! { "flying" => 0i8, "mayfly" => 0i8 },
+    };
+    let patch = NbtPatch(vec![
+        set("Abilities.flying", Value::Byte(1)),
+        delete("Abilities.mayfly"),
+        set("Pos.1", Value::Double(70.0)),
+        append("Pos", Value::Double(-5.0)),
+        delete("Health"),
+        set("XpLevel", Value::Int(3)),
+    ]);
+    patch.apply(&mut val).unwrap();
+    assert_eq!(val, compound! {
+        "Pos" => Value::List(vec![Value::Double(0.0), Value::Double(70.0), Value::Double(-5.0)]),
+        "Abilities" => compound! { "flying" => 1i8 },
+        "XpLevel" => 3,
+    });
+
+    // Patches round-trip through `Value`s.
+    assert_eq!(NbtPatch::try_from(Value::from(patch.clone())), Ok(patch));
+
+    let original = val.clone();
+    let failing = |op: NbtOp| NbtPatch(vec![op]).apply(&mut val.clone());
+    assert_eq!(failing(delete("Health")), Err(Error::PathNotFound("Health".to_string())));
+    assert_eq!(failing(set("Missing.x", Value::Int(1))),
+               Err(Error::PathNotFound("Missing.x".to_string())));
+    assert_eq!(failing(set("Pos.3", Value::Double(1.0))),
+               Err(Error::PathNotFound("Pos.3".to_string())));
+    assert_eq!(failing(set("XpLevel.x", Value::Int(1))), Err(Error::TagMismatch(TagId::Int, TagId::Compound)));
+    assert_eq!(failing(append("Pos", Value::Int(1))),
+               Err(Error::HeterogeneousList { index: 3, expected: TagId::Double, got: TagId::Int }));
+    assert_eq!(failing(append("XpLevel", Value::Int(1))), Err(Error::TagMismatch(TagId::Int, TagId::List)));
+    assert_eq!(val, original);
+
+    let unknown = Value::List(vec![compound! { "op" => "rename", "path" => "a" }]);
+    assert_eq!(NbtPatch::try_from(unknown), Err(Error::UnexpectedField("rename".to_string())));
+    let missing = Value::List(vec![compound! { "op" => "set", "path" => "a" }]);
+    assert_eq!(NbtPatch::try_from(missing), Err(Error::MissingField("value".to_string())));
+}
+
+#[test]
+fn nbt_truncated_arrays() {
+    let read = |tag: TagId, bytes: &[u8]| Value::from_reader(tag, &mut io::Cursor::new(bytes));
+
+    assert_eq!(read(TagId::ByteArray, &[0x00, 0x00, 0x03, 0xe8, 0x01, 0x02]),
+               Err(Error::TruncatedArray { tag_id: TagId::ByteArray, declared_len: 1000, bytes_available: 2 }));
+    assert_eq!(read(TagId::IntArray, &[0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00]),
+               Err(Error::TruncatedArray { tag_id: TagId::IntArray, declared_len: 2, bytes_available: 5 }));
+    assert_eq!(read(TagId::LongArray, &[0x00, 0x00, 0x00, 0x01]),
+               Err(Error::TruncatedArray { tag_id: TagId::LongArray, declared_len: 1, bytes_available: 0 }));
+
+    // Complete arrays are unaffected.
+    assert_eq!(read(TagId::IntArray, &[0x00, 0x00, 0x00, 0x01, 0xff, 0xff, 0xff, 0xfe]),
+               Ok(Value::IntArray(vec![-2])));
+    assert_eq!(read(TagId::ByteArray, &[0xff, 0xff, 0xff, 0xff]), Ok(Value::ByteArray(vec![])));
+}
+
+#[test]
+fn tag_ids() {
+    use std::convert::TryFrom;
+
+    for id in 0..13u8 {
+        let tag = TagId::try_from(id).unwrap();
+        assert_eq!(u8::from(tag), id);
+    }
+    assert_eq!(TagId::try_from(13), Err(Error::InvalidTypeId(13)));
+    assert_eq!(TagId::LongArray.to_string(), "TAG_LongArray");
+    assert_eq!(Value::Double(1.0).tag_id(), TagId::Double);
+    assert_eq!(u8::from(Value::Double(1.0).tag_id()), Value::Double(1.0).id());
+
+    // Unknown tags are rejected when reading headers.
+    let mut src = io::Cursor::new(&[0x0d, 0x00, 0x00][..]);
+    assert_eq!(::raw::emit_next_header(&mut src), Err(Error::InvalidTypeId(0x0d)));
+}
+
+#[test]
+fn compound_end_not_stored() {
+    // An empty-named entry, an empty nested compound, and a list of empty
+    // compounds: each TAG_End must terminate its compound and nothing more.
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x01,
+                0x00, 0x00,
+                0x05,
+            0x0a,
+                0x00, 0x05,
+                0x65, 0x6d, 0x70, 0x74, 0x79,
+            0x00,
+            0x09,
+                0x00, 0x04,
+                0x6c, 0x69, 0x73, 0x74,
+                0x0a,
+                0x00, 0x00, 0x00, 0x02,
+                0x00,
+                0x00,
+        0x00
+    ];
+
+    let mut src = io::Cursor::new(&bytes[3..]);
+    let map = match Value::from_reader(TagId::Compound, &mut src).unwrap() {
+        Value::Compound(map) => map,
+        v => panic!("unexpected value {:?}", v),
+    };
+    assert_eq!(src.position() as usize, bytes.len() - 3);
+    assert_eq!(map.len(), 3);
+    assert_eq!(map["empty"], Value::Compound(HashMap::new()));
+    assert_eq!(map["list"], Value::List(vec![Value::Compound(HashMap::new()),
+                                             Value::Compound(HashMap::new())]));
+    assert_eq!(map[""], Value::Byte(5));
+}
+
+#[test]
+fn incremental_compound_writes() {
+    use incremental::IncrementalCompound;
+
+    let mut map = HashMap::new();
+    map.insert("a".to_string(), Value::Int(1));
+    map.insert("b".to_string(), Value::String("two".to_string()));
+    let mut compound = IncrementalCompound::from(map);
+    assert!(compound.is_dirty());
+
+    let read = |bytes: &[u8]| Value::from_reader(TagId::Compound, &mut io::Cursor::new(bytes)).unwrap();
+
+    let mut dst = Vec::new();
+    compound.to_writer_incremental(&mut dst).unwrap();
+    assert!(!compound.is_dirty());
+    assert_eq!(read(&dst), compound.clone().into_value());
+
+    *compound.get_mut("a").unwrap() = Value::Long(10);
+    assert_eq!(compound.insert("c", 3i8), None);
+    assert_eq!(compound.remove("b"), Some(Value::String("two".to_string())));
+    assert!(compound.is_dirty());
+
+    let mut dst = Vec::new();
+    compound.to_writer_incremental(&mut dst).unwrap();
+    let mut expected = HashMap::new();
+    expected.insert("a".to_string(), Value::Long(10));
+    expected.insert("c".to_string(), Value::Byte(3));
+    assert_eq!(read(&dst), Value::Compound(expected));
+
+    // A clean compound writes the same bytes again.
+    let mut again = Vec::new();
+    compound.to_writer_incremental(&mut again).unwrap();
+    assert_eq!(dst, again);
+    assert_eq!(compound.remove("missing"), None);
+    assert!(!compound.is_dirty());
+}
+
+#[test]
+fn value_default() {
+    assert_eq!(Value::default(), Value::Compound(HashMap::new()));
+    let map: HashMap<String, Value> = HashMap::new();
+    assert_eq!(map.get("missing").cloned().unwrap_or_default(), Value::Compound(HashMap::new()));
+}
+
+#[test]
+fn error_context() {
+    use error::ResultExt;
+
+    let value = Value::Compound(HashMap::new());
+    let err = value.get_path_typed::<i32>("xPos")
+        .with_context(|| "while reading 'xPos'")
+        .unwrap_err();
+    assert_eq!(err, Error::Context("while reading 'xPos'".to_string(),
+                                   Box::new(Error::PathNotFound("xPos".to_string()))));
+    assert_eq!(err.to_string(), "while reading 'xPos': no value exists at path 'xPos'");
+
+    let ok: ::error::Result<i32> = Ok(1);
+    assert_eq!(ok.with_context(|| -> String { panic!("context computed on success") }), Ok(1));
+}
+
+#[test]
+fn int_array_bytes() {
+    use convert::{bytes_to_int_array, int_array_to_bytes};
+
+    let arr = vec![0, 1, -1, i32::MIN, 0x7f00ff01];
+    let bytes = int_array_to_bytes(&arr);
+    assert_eq!(&bytes[4..8], &[0, 0, 0, 1]);
+    assert_eq!(bytes_to_int_array(&bytes), Ok(arr));
+    assert_eq!(bytes_to_int_array(&[]), Ok(vec![]));
+    assert_eq!(bytes_to_int_array(&[1, 2, 3, 4, 5]), Err(Error::UnalignedBytes(5)));
+}
+
+#[test]
+fn gzip_reader_rejects_uncompressed() {
+    let uncompressed = vec![0x0a, 0x00, 0x00, 0x00];
+    assert_eq!(Blob::from_gzip_reader(&mut io::Cursor::new(&uncompressed[..])),
+               Err(Error::NotGzip));
+    assert_eq!(Value::decompress_from_gzip(&uncompressed), Err(Error::NotGzip));
+    assert_eq!(Value::decompress_from_gzip(&[0x1f]), Err(Error::NotGzip));
+
+    // The magic bytes are still passed on to the decoder.
+    let mut blob = Blob::new();
+    blob.insert("a", Value::Int(1)).unwrap();
+    let mut compressed = Vec::new();
+    blob.to_gzip_writer(&mut compressed).unwrap();
+    assert_eq!(Blob::from_gzip_reader(&mut io::Cursor::new(compressed)).unwrap(), blob);
+}
+
+#[test]
+fn list_element_getters() {
+    let mut entity = HashMap::new();
+    entity.insert("id".to_string(), Value::String("minecraft:pig".to_string()));
+    let entities = Value::List(vec![Value::Compound(entity.clone())]);
+    assert_eq!(entities.get_compound_at(0), Some(&entity));
+    assert_eq!(entities.get_compound_at(1), None);
+    assert_eq!(entities.get_int_at(0), None);
+
+    let names = Value::List(vec![Value::String("a".to_string()), Value::String("b".to_string())]);
+    assert_eq!(names.get_string_at(1), Some("b"));
+    assert_eq!(Value::List(vec![Value::Int(7)]).get_int_at(0), Some(7));
+    assert_eq!(Value::List(vec![Value::IntArray(vec![1, 2])]).get_int_array_at(0), Some(&[1, 2][..]));
+    assert_eq!(Value::Int(7).get_int_at(0), None);
+}
+
+#[test]
+fn list_homogeneity() {
+    assert_eq!(Value::List(vec![]).assert_homogeneous(), Ok(()));
+    assert_eq!(Value::List(vec![Value::Int(1), Value::Int(2)]).assert_homogeneous(), Ok(()));
+    let mixed = Value::List(vec![Value::Int(1), Value::Int(2), Value::Long(3)]);
+    let err = Error::HeterogeneousList { index: 2, expected: TagId::Int, got: TagId::Long };
+    assert_eq!(mixed.assert_homogeneous(), Err(err));
+    assert_eq!(Value::Int(1).assert_homogeneous(), Err(Error::TagMismatch(TagId::Int, TagId::List)));
+
+    let mut dst = Vec::new();
+    assert_eq!(mixed.to_writer(&mut dst), Err(Error::HeterogeneousList { index: 2, expected: TagId::Int, got: TagId::Long }));
+    assert_eq!(Error::HeterogeneousList { index: 2, expected: TagId::Int, got: TagId::Long }.to_string(),
+               "list element 2 is a 'TAG_Long', but the list holds 'TAG_Int'");
+}
+
+#[test]
+fn compound_get_many() {
+    let mut map = HashMap::new();
+    map.insert("xPos".to_string(), Value::Int(1));
+    map.insert("zPos".to_string(), Value::Int(2));
+    let mut chunk = Value::Compound(map);
+
+    assert_eq!(chunk.get_many(&["zPos", "missing", "xPos"]),
+               vec![Some(&Value::Int(2)), None, Some(&Value::Int(1))]);
+    assert_eq!(Value::Int(0).get_many(&["a", "b"]), vec![None, None]);
+
+    {
+        let mut entries = chunk.get_many_mut(&["xPos", "zPos", "yPos"]).unwrap();
+        assert!(entries[2].is_none());
+        *entries[0].take().unwrap() = Value::Int(10);
+        *entries[1].take().unwrap() = Value::Int(20);
+    }
+    assert_eq!(chunk.get_many(&["xPos", "zPos"]), vec![Some(&Value::Int(10)), Some(&Value::Int(20))]);
+    assert_eq!(chunk.get_many_mut(&["xPos", "xPos"]).unwrap_err(),
+               Error::DuplicateKey("xPos".to_string()));
+}
+
+#[test]
+fn nbt_reader_builder_limits() {
+    use config::ParseConfig;
+    use reader::{NbtEvent, NbtReaderBuilder};
+
+    // Root compound > "a" compound > "b" list of one empty compound.
+    let nested = vec![
+        0x0a, 0x00, 0x00,
+            0x0a, 0x00, 0x01, b'a',
+                0x09, 0x00, 0x01, b'b', 0x0a, 0x00, 0x00, 0x00, 0x01,
+                    0x00,
+            0x00,
+        0x00
+    ];
+    let config = ParseConfig { max_depth: Some(4), ..Default::default() };
+    assert!(Blob::from_reader_with_config(&mut io::Cursor::new(&nested[..]), &config).is_ok());
+    let config = ParseConfig { max_depth: Some(3), ..Default::default() };
+    assert_eq!(Blob::from_reader_with_config(&mut io::Cursor::new(&nested[..]), &config),
+               Err(Error::DepthLimitExceeded(3)));
+
+    let mut reader = NbtReaderBuilder::new().max_depth(3).build(&nested[..]);
+    let mut result = Ok(None);
+    for _ in 0..8 {
+        result = reader.next().map(|e| e.map(|e| format!("{:?}", e)));
+        if result.is_err() { break; }
+    }
+    assert_eq!(result, Err(Error::DepthLimitExceeded(3)));
+
+    let mut reader = NbtReaderBuilder::new().max_array_len(2).build(&[0x00, 0x00, 0x00, 0x03][..]);
+    assert_eq!(reader.read_value(TagId::IntArray), Err(Error::LengthLimitExceeded(3)));
+    let mut reader = NbtReaderBuilder::new().max_array_len(3).build(&[0x00, 0x00, 0x00, 0x03, 1, 2, 3][..]);
+    assert_eq!(reader.read_value(TagId::ByteArray), Ok(Value::ByteArray(vec![1, 2, 3])));
+
+    // Two entries named "x" in the root compound.
+    let duplicate = vec![
+        0x0a, 0x00, 0x00,
+            0x01, 0x00, 0x01, b'x', 0x01,
+            0x01, 0x00, 0x01, b'x', 0x02,
+        0x00
+    ];
+    let mut reader = NbtReaderBuilder::new().reject_duplicate_keys(true).build(&duplicate[..]);
+    assert_eq!(reader.next(), Ok(Some(NbtEvent::Name(""))));
+    assert_eq!(reader.next(), Ok(Some(NbtEvent::StartCompound)));
+    assert_eq!(reader.next(), Ok(Some(NbtEvent::Name("x"))));
+    assert_eq!(reader.next(), Ok(Some(NbtEvent::Byte(1))));
+    assert_eq!(reader.next(), Err(Error::DuplicateKey("x".to_string())));
+}
+
+#[test]
+fn nbt_writer_builder() {
+    use writer::{Compression, NbtWriterBuilder};
+
+    let mut map = HashMap::new();
+    map.insert("b".to_string(), Value::Int(1));
+    map.insert("a".to_string(), Value::List(vec![Value::Byte(2)]));
+    let value = Value::Compound(map.clone());
+
+    let mut dst = Vec::new();
+    NbtWriterBuilder::new().compression(Compression::Gzip).build()
+        .write(&mut dst, "level", &value).unwrap();
+    let blob = Blob::from_gzip_reader(&mut io::Cursor::new(dst)).unwrap();
+    assert_eq!(blob.get("b"), Some(&Value::Int(1)));
+
+    let mut dst = Vec::new();
+    NbtWriterBuilder::new().compression(Compression::Zlib).build()
+        .write(&mut dst, "level", &value).unwrap();
+    let blob = Blob::from_zlib_reader(&mut io::Cursor::new(dst)).unwrap();
+    assert_eq!(blob.get("a"), Some(&Value::List(vec![Value::Byte(2)])));
+
+    // Without a root name, the payload follows the tag type directly.
+    let mut sorted = Vec::new();
+    value.to_writer_sorted(&mut sorted).unwrap();
+    let mut dst = Vec::new();
+    NbtWriterBuilder::new().root_name(false).sort_keys(true).build()
+        .write(&mut dst, "ignored", &value).unwrap();
+    assert_eq!(dst[0], 0x0a);
+    assert_eq!(&dst[1..], &sorted[..]);
+}
+
+#[test]
+fn nbt_encoder_raw_tags() {
+    use reader::NbtReader;
+    use writer::NbtEncoder;
+
+    let mut inner = HashMap::new();
+    inner.insert("Health".to_string(), Value::Short(20));
+    let mut payload = Vec::new();
+    Value::Compound(inner.clone()).to_writer(&mut payload).unwrap();
+
+    let mut encoder = NbtEncoder::new(Vec::new());
+    encoder.write_header(TagId::Compound, "root").unwrap();
+    encoder.write_raw_tag(TagId::Compound, "Player", &payload).unwrap();
+    encoder.write_tag("Score", &Value::Long(3)).unwrap();
+    encoder.write_end().unwrap();
+
+    let blob = Blob::from_reader(&mut io::Cursor::new(encoder.into_inner())).unwrap();
+    assert_eq!(blob.get("Player"), Some(&Value::Compound(inner)));
+    assert_eq!(blob.get("Score"), Some(&Value::Long(3)));
+
+    // Writing a header and value separately is equivalent to `write_tag`.
+    let mut encoder = NbtEncoder::new(Vec::new());
+    encoder.write_header(TagId::Int, "x").unwrap();
+    encoder.write_value(&Value::Int(1)).unwrap();
+    let mut reader = NbtReader::new(io::Cursor::new(encoder.into_inner()));
+    assert_eq!(reader.read_header().unwrap(), (TagId::Int, "x".to_string()));
+    assert_eq!(reader.read_value(TagId::Int), Ok(Value::Int(1)));
+}
+
+#[test]
+fn list_truncate_and_pop() {
+    let mut list = Value::List(vec![Value::Int(1), Value::Int(2), Value::Int(3)]);
+    list.truncate_list(5);
+    assert_eq!(list, Value::List(vec![Value::Int(1), Value::Int(2), Value::Int(3)]));
+    list.truncate_list(2);
+    assert_eq!(list, Value::List(vec![Value::Int(1), Value::Int(2)]));
+    assert_eq!(list.pop_list(), Some(Value::Int(2)));
+    assert_eq!(list.pop_list(), Some(Value::Int(1)));
+    assert_eq!(list.pop_list(), None);
+
+    let mut int = Value::Int(1);
+    int.truncate_list(0);
+    assert_eq!(int, Value::Int(1));
+    assert_eq!(int.pop_list(), None);
+}
+
+#[test]
+fn value_rename_keys() {
+    let mut item = HashMap::new();
+    item.insert("item_count".to_string(), Value::Byte(1));
+    let mut root = HashMap::new();
+    root.insert("inventory_items".to_string(), Value::List(vec![Value::Compound(item)]));
+    root.insert("name".to_string(), Value::String("inventory_items".to_string()));
+
+    let pascal = |key: &str| -> String {
+        key.split('_').map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(c) => c.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        }).collect()
+    };
+    let renamed = Value::Compound(root).rename_keys_recursive(pascal);
+
+    let mut item = HashMap::new();
+    item.insert("ItemCount".to_string(), Value::Byte(1));
+    let mut expected = HashMap::new();
+    expected.insert("InventoryItems".to_string(), Value::List(vec![Value::Compound(item)]));
+    expected.insert("Name".to_string(), Value::String("inventory_items".to_string()));
+    assert_eq!(renamed, Value::Compound(expected));
+    assert_eq!(Value::Int(1).rename_keys_recursive(|k| k.to_uppercase()), Value::Int(1));
+}
+
+#[test]
+fn value_float_widening() {
+    assert_eq!(Value::Byte(-3).as_f64_lossy(), Some(-3.0));
+    assert_eq!(Value::Long(1 << 53).as_f64_lossy(), Some(9007199254740992.0));
+    assert_eq!(Value::Long((1 << 53) + 1).as_f64_lossy(), Some(9007199254740992.0));
+    assert_eq!(Value::Float(0.5).as_f64_lossy(), Some(0.5));
+    assert_eq!(Value::String("1".to_string()).as_f64_lossy(), None);
+    assert_eq!(Value::IntArray(vec![1]).as_f64_lossy(), None);
+
+    assert_eq!(Value::Int(16_777_217).as_f32_lossy(), Some(16_777_216.0));
+    assert_eq!(Value::Double(0.1).as_f32_lossy(), Some(0.1f32));
+    assert_eq!(Value::List(vec![]).as_f32_lossy(), None);
+}
+
+#[test]
+fn value_checked_integer_widening() {
+    assert_eq!(Value::Byte(-1).as_i64_checked(), Some(-1));
+    assert_eq!(Value::Short(300).as_i64_checked(), Some(300));
+    assert_eq!(Value::Int(i32::MIN).as_i64_checked(), Some(i32::MIN as i64));
+    assert_eq!(Value::Long(i64::MAX).as_i64_checked(), Some(i64::MAX));
+    assert_eq!(Value::Float(1.0).as_i64_checked(), None);
+    assert_eq!(Value::Double(2.0).as_i64_checked(), None);
+    assert_eq!(Value::String("3".to_string()).as_i64_checked(), None);
+}
+
+#[test]
+fn value_into_iter() {
+    let list = Value::List(vec![Value::Int(1), Value::Int(2)]);
+    let borrowed: Vec<&Value> = (&list).into_iter().collect();
+    assert_eq!(borrowed, vec![&Value::Int(1), &Value::Int(2)]);
+    let mut sum = 0;
+    for v in &list {
+        sum += v.as_i64_checked().unwrap();
+    }
+    assert_eq!(sum, 3);
+    assert_eq!(list.into_iter().collect::<Vec<_>>(), vec![Value::Int(1), Value::Int(2)]);
+
+    let bytes = Value::ByteArray(vec![1, -1]).into_iter();
+    assert_eq!(bytes.len(), 2);
+    assert_eq!(bytes.collect::<Vec<_>>(), vec![Value::Byte(1), Value::Byte(-1)]);
+    assert_eq!(Value::IntArray(vec![7]).into_iter().collect::<Vec<_>>(), vec![Value::Int(7)]);
+    assert_eq!(Value::LongArray(vec![]).into_iter().next(), None);
+}
+
+#[test]
+#[should_panic(expected = "cannot iterate over a TAG_Compound")]
+fn value_into_iter_compound() {
+    for _ in Value::Compound(HashMap::new()) {}
+}
+
+#[test]
+fn compound_swap() {
+    let mut map = HashMap::new();
+    map.insert("MainHand".to_string(), Value::String("sword".to_string()));
+    map.insert("OffHand".to_string(), Value::String("shield".to_string()));
+    let mut value = Value::Compound(map);
+
+    value.swap("MainHand", "OffHand").unwrap();
+    assert_eq!(value.get_many(&["MainHand", "OffHand"]),
+               vec![Some(&Value::String("shield".to_string())), Some(&Value::String("sword".to_string()))]);
+    assert_eq!(value.swap("MainHand", "MainHand"), Ok(()));
+    assert_eq!(value.swap("MainHand", "Armor"), Err(Error::MissingField("Armor".to_string())));
+    assert_eq!(value.swap("Armor", "Armor"), Err(Error::MissingField("Armor".to_string())));
+    assert_eq!(Value::Int(0).swap("a", "b"), Err(Error::TagMismatch(TagId::Int, TagId::Compound)));
+}
+
+#[test]
+fn value_count_tags() {
+    let mut section = HashMap::new();
+    section.insert("Y".to_string(), Value::Byte(0));
+    section.insert("Blocks".to_string(), Value::ByteArray(vec![1, 2]));
+    let mut level = HashMap::new();
+    level.insert("Sections".to_string(),
+                 Value::List(vec![Value::Compound(section.clone()), Value::Compound(section)]));
+    level.insert("xPos".to_string(), Value::Int(0));
+    let root = Value::Compound(level);
+
+    assert_eq!(root.count_tags(TagId::Compound), 3);
+    assert_eq!(root.count_tags(TagId::Byte), 2);
+    assert_eq!(root.count_tags(TagId::Long), 0);
+    assert_eq!(Value::Int(1).count_tags(TagId::Int), 1);
+
+    let counts = root.count_all_tags();
+    assert_eq!(counts[&TagId::Compound], 3);
+    assert_eq!(counts[&TagId::List], 1);
+    assert_eq!(counts[&TagId::ByteArray], 2);
+    assert_eq!(counts.values().sum::<usize>(), 9);
+}
+
+#[test]
+fn value_concatenation() {
+    let a = Value::List(vec![Value::Int(1)]);
+    let b = Value::List(vec![Value::Int(2), Value::Int(3)]);
+    assert_eq!(a.clone() + b, Ok(Value::List(vec![Value::Int(1), Value::Int(2), Value::Int(3)])));
+    assert_eq!(a.clone() + Value::List(vec![]), Ok(a.clone()));
+    assert_eq!(a.clone() + Value::List(vec![Value::Byte(2)]),
+               Err(Error::HeterogeneousList { index: 1, expected: TagId::Int, got: TagId::Byte }));
+    assert_eq!(Value::ByteArray(vec![1]) + Value::ByteArray(vec![2]), Ok(Value::ByteArray(vec![1, 2])));
+    assert_eq!(Value::LongArray(vec![1]) + Value::IntArray(vec![2]), Err(Error::UnsupportedOperands(TagId::LongArray, TagId::IntArray)));
+    assert_eq!(Value::String("a".to_string()) + Value::String("b".to_string()),
+               Err(Error::UnsupportedOperands(TagId::String, TagId::String)));
+    assert_eq!(Error::UnsupportedOperands(TagId::String, TagId::String).to_string(),
+               "cannot add 'TAG_String' and 'TAG_String'");
+
+    // Integer tags add as they do with integer operands.
+    assert_eq!(Value::Int(1) + Value::Int(2), Ok(Value::Int(3)));
+    assert_eq!(Value::Short(1) + Value::Long(2), Ok(Value::Short(3)));
+    assert_eq!(Value::Byte(127) + Value::Byte(1), Err(Error::ArithmeticOverflow));
+    assert_eq!(Value::String("a".to_string()) + Value::Int(1),
+               Err(Error::UnsupportedOperands(TagId::String, TagId::Int)));
+    assert_eq!(Value::Float(1.0) + Value::Float(2.0),
+               Err(Error::UnsupportedOperands(TagId::Float, TagId::Float)));
+
+    let mut left = HashMap::new();
+    left.insert("a".to_string(), Value::Int(1));
+    left.insert("b".to_string(), Value::Int(2));
+    let mut right = HashMap::new();
+    right.insert("b".to_string(), Value::Int(20));
+    right.insert("c".to_string(), Value::Int(30));
+    let mut merged = Value::Compound(left);
+    merged += Value::Compound(right);
+    let mut expected = HashMap::new();
+    expected.insert("a".to_string(), Value::Int(1));
+    expected.insert("b".to_string(), Value::Int(20));
+    expected.insert("c".to_string(), Value::Int(30));
+    assert_eq!(merged, Value::Compound(expected));
+
+    let mut ints = Value::IntArray(vec![1]);
+    ints += Value::IntArray(vec![2]);
+    assert_eq!(ints, Value::IntArray(vec![1, 2]));
+}
+
+#[test]
+#[should_panic(expected = "cannot add values")]
+fn value_add_assign_mismatch() {
+    let mut value = Value::Int(1);
+    value += Value::List(vec![]);
+}
+
+#[test]
+fn value_add_assign_unwind() {
+    use std::panic::{self, AssertUnwindSafe};
+
+    let mut list = Value::List(vec![Value::Int(1)]);
+    assert!(panic::catch_unwind(AssertUnwindSafe(|| list += Value::List(vec![Value::Byte(2)]))).is_err());
+    assert_eq!(list, Value::List(vec![Value::Int(1)]));
+
+    let mut value = Value::Int(1);
+    assert!(panic::catch_unwind(AssertUnwindSafe(|| value += Value::String("a".to_string()))).is_err());
+    assert_eq!(value, Value::Int(1));
+    value += Value::Int(2);
+    assert_eq!(value, Value::Int(3));
+}
+
+#[test]
+fn value_pretty_snbt() {
+    let snbt = |value: &Value, name: &str, indent: usize| {
+        let mut dst = Vec::new();
+        value.to_writer_pretty_snbt(&mut dst, name, indent).unwrap();
+        String::from_utf8(dst).unwrap()
+    };
+
+    let mut item = HashMap::new();
+    item.insert("id".to_string(), Value::String("say \"hi\"".to_string()));
+    item.insert("Damage".to_string(), Value::Short(3));
+    let mut root = HashMap::new();
+    root.insert("Items".to_string(), Value::List(vec![Value::Compound(item)]));
+    root.insert("Pos".to_string(), Value::List(vec![Value::Double(1.0), Value::Double(-2.5)]));
+    root.insert("UUID".to_string(), Value::IntArray(vec![1, -2]));
+    root.insert("Time".to_string(), Value::Long(20));
+    root.insert("with space".to_string(), Value::Float(0.5));
+    root.insert("Empty".to_string(), Value::List(vec![]));
+    root.insert("Bytes".to_string(), Value::ByteArray(vec![]));
+    let root = Value::Compound(root);
+
+    assert_eq!(snbt(&root, "", 0),
+               "{Bytes: [B;], Empty: [], Items: [{Damage: 3s, id: \"say \\\"hi\\\"\"}], \
+                Pos: [1.0d, -2.5d], Time: 20L, UUID: [I; 1, -2], \"with space\": 0.5f}");
+    assert_eq!(snbt(&Value::LongArray(vec![5]), "Data", 0), "Data: [L; 5L]");
+
+    let mut pos = HashMap::new();
+    pos.insert("Pos".to_string(), Value::List(vec![Value::Int(1), Value::Int(2)]));
+    pos.insert("Tag".to_string(), Value::Compound(HashMap::new()));
+    assert_eq!(snbt(&Value::Compound(pos), "", 2),
+               "{\n  Pos: [\n    1,\n    2\n  ],\n  Tag: {}\n}");
+}
+
+#[test]
+fn value_compound_with_capacity() {
+    match Value::compound_with_capacity(16) {
+        Value::Compound(ref map) => {
+            assert!(map.is_empty());
+            assert!(map.capacity() >= 16);
+        },
+        ref other => panic!("unexpected value {:?}", other),
+    }
+}
+
+#[test]
+fn value_list_with_capacity() {
+    match Value::list_with_capacity(8) {
+        Value::List(ref vals) => assert!(vals.is_empty() && vals.capacity() >= 8),
+        ref other => panic!("unexpected value {:?}", other),
+    }
+
+    // A list with a negative length is empty, and a huge length does not
+    // cause a huge allocation before the data turns out to be missing.
+    let read = |bytes: &[u8]| Value::from_reader(TagId::List, &mut io::Cursor::new(bytes));
+    assert_eq!(read(&[0x03, 0xff, 0xff, 0xff, 0xff]), Ok(Value::List(vec![])));
+    assert_eq!(read(&[0x03, 0x7f, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x01]),
+               Err(Error::IncompleteNbtValue));
+}
+
+#[test]
+fn value_copy_from() {
+    let mut map = HashMap::with_capacity(64);
+    map.insert("old".to_string(), Value::Int(1));
+    let mut dst = Value::Compound(map);
+
+    let mut fresh = HashMap::new();
+    fresh.insert("new".to_string(), Value::String("x".to_string()));
+    let src = Value::Compound(fresh);
+    dst.copy_from(&src);
+    assert_eq!(dst, src);
+    match dst {
+        Value::Compound(ref map) => assert!(map.capacity() >= 64),
+        _ => unreachable!(),
+    }
+
+    let mut list = Value::List(vec![Value::Int(1)]);
+    list.copy_from(&Value::List(vec![Value::Byte(2), Value::Byte(3)]));
+    assert_eq!(list, Value::List(vec![Value::Byte(2), Value::Byte(3)]));
+
+    // Different tags are simply replaced.
+    list.copy_from(&Value::IntArray(vec![4]));
+    assert_eq!(list, Value::IntArray(vec![4]));
+}
+
+#[test]
+fn value_from_collections() {
+    let mut map = HashMap::new();
+    map.insert("a".to_string(), Value::Byte(1));
+    assert_eq!(Value::from(map.clone()), Value::Compound(map));
+
+    let list = Value::from(vec![Value::Int(1), Value::Int(2)]);
+    assert_eq!(list, Value::List(vec![Value::Int(1), Value::Int(2)]));
+
+    // An empty list is written with an element type of TAG_End.
+    let mut dst = Vec::new();
+    Value::from(Vec::<Value>::new()).to_writer(&mut dst).unwrap();
+    assert_eq!(dst, vec![0x00, 0x00, 0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn compound_entry() {
+    let mut value = Value::Compound(HashMap::new());
+    *value.entry("count").unwrap().or_insert(Value::Int(0)) = Value::Int(1);
+    value.entry("count").unwrap().and_modify(|v| *v = Value::Int(2)).or_insert(Value::Int(0));
+    assert_eq!(value.get_many(&["count"]), vec![Some(&Value::Int(2))]);
+
+    match Value::Int(1).entry("count") {
+        Err(Error::TagMismatch(TagId::Int, TagId::Compound)) => (),
+        other => panic!("expected a tag mismatch, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn data_fixer_chain() {
+    use dfm::DataFixerChain;
+
+    // Each fixer records the version it was run from.
+    let record = |version: i32| move |val: Value, from: i32| -> ::error::Result<Value> {
+        match val {
+            Value::List(mut vals) => {
+                vals.push(Value::List(vec![Value::Int(from), Value::Int(version)]));
+                Ok(Value::List(vals))
+            },
+            other => Ok(other),
+        }
+    };
+    let chain = DataFixerChain::new()
+        .register(300, record(300))
+        .register(100, record(100))
+        .register(200, record(200));
+    assert_eq!(chain.len(), 3);
+
+    let fixed = chain.apply(Value::List(vec![]), 100, 300).unwrap();
+    assert_eq!(fixed, Value::List(vec![
+        Value::List(vec![Value::Int(100), Value::Int(200)]),
+        Value::List(vec![Value::Int(200), Value::Int(300)]),
+    ]));
+
+    let unchanged = chain.apply(Value::List(vec![]), 300, 300).unwrap();
+    assert_eq!(unchanged, Value::List(vec![]));
+
+    assert_eq!(chain.apply(Value::List(vec![]), 300, 100).unwrap_err(),
+               Error::DataVersionDowngrade { from: 300, to: 100 });
+}
+
+#[test]
+fn value_mixed_lists() {
+    let mixed = Value::List(vec![Value::Int(1), Value::String("two".to_string())]);
+    let mut value = compound! { "mixed" => mixed.clone(), "plain" => Value::List(vec![Value::Int(3)]) };
+    value.wrap_mixed_lists();
+    assert_eq!(value, compound! {
+        "mixed" => Value::List(vec![compound! { "" => 1 }, compound! { "" => "two" }]),
+        "plain" => Value::List(vec![Value::Int(3)])
+    });
+
+    let mut dst = Vec::new();
+    value.to_writer(&mut dst).unwrap();
+
+    value.unwrap_mixed_lists();
+    assert_eq!(value, compound! { "mixed" => mixed, "plain" => Value::List(vec![Value::Int(3)]) });
+}
+
+#[test]
+fn compound_get_numeric() {
+    let stats = compound! {
+        "kills" => 3i8,
+        "deaths" => 4i64,
+        "time" => 1.5f32,
+        "name" => "Steve"
+    };
+    assert_eq!(stats.get_numeric("kills"), Some(3.0));
+    assert_eq!(stats.get_numeric("deaths"), Some(4.0));
+    assert_eq!(stats.get_numeric("time"), Some(1.5));
+    assert_eq!(stats.get_numeric("name"), None);
+    assert_eq!(stats.get_numeric("missing"), None);
+    assert_eq!(Value::Int(1).get_numeric("kills"), None);
+}
+
+#[test]
+fn values_are_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Value>();
+    assert_send_sync::<Blob>();
+    assert_send_sync::<Error>();
+}
+
+#[test]
+fn nbt_stream_leaves() {
+    use stream::NbtStream;
+
+    let value = compound! {
+        "name" => "Steve",
+        "inventory" => Value::List(vec![
+            compound! { "id" => 1i16 },
+            compound! { "id" => 2i16 }
+        ]),
+        "empty" => Value::List(vec![])
+    };
+    let mut bytes = Vec::new();
+    value.to_writer_sorted(&mut bytes).unwrap();
+    // Add the root header and name.
+    let mut doc = vec![0x0a, 0x00, 0x04, b'r', b'o', b'o', b't'];
+    doc.extend_from_slice(&bytes);
+
+    let mut leaves: Vec<_> = NbtStream::new(&doc[..]).map(|leaf| leaf.unwrap()).collect();
+    leaves.sort_by(|a, b| a.0.cmp(&b.0));
+    let path = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+    assert_eq!(leaves, vec![
+        (path(&["inventory", "0", "id"]), Value::Short(1)),
+        (path(&["inventory", "1", "id"]), Value::Short(2)),
+        (path(&["name"]), Value::String("Steve".to_string())),
+    ]);
+
+    // Errors end the stream.
+    let mut truncated = NbtStream::new(&doc[..doc.len() - 3]);
+    assert!(truncated.by_ref().any(|leaf| leaf.is_err()));
+    assert!(truncated.next().is_none());
+}
+
+#[test]
+fn compound_get_mut_and_insert() {
+    let mut value = compound! { "health" => 20.0f32 };
+    if let Some(health) = value.get_mut("health") {
+        *health = Value::Float(15.0);
+    }
+    assert_eq!(value.get("health"), Some(&Value::Float(15.0)));
+    assert_eq!(value.get_mut("missing"), None);
+
+    assert_eq!(value.insert_or_replace("health", 10.0f32), Ok(Some(Value::Float(15.0))));
+    assert_eq!(value.insert_or_replace("food", 20), Ok(None));
+    assert_eq!(value, compound! { "health" => 10.0f32, "food" => 20 });
+
+    let mut list = Value::List(vec![]);
+    assert_eq!(list.get("health"), None);
+    assert_eq!(list.insert_or_replace("health", 1), Err(Error::TagMismatch(TagId::List, TagId::Compound)));
+}
+
+#[test]
+fn compound_get_or_default() {
+    let mut value = compound! { "level" => 3 };
+    assert_eq!(value.get_or_default("level", 0), Ok(&Value::Int(3)));
+    assert_eq!(value.get_or_default("xp", 0.5f32), Ok(&Value::Float(0.5)));
+    assert_eq!(value, compound! { "level" => 3, "xp" => 0.5f32 });
+    assert_eq!(Value::Byte(1).get_or_default("xp", 0), Err(Error::TagMismatch(TagId::Byte, TagId::Compound)));
+}
+
+#[test]
+fn list_extend() {
+    let mut list = Value::List(vec![]);
+    list.extend_list(vec![Value::Int(1), Value::Int(2)]).unwrap();
+    list.extend_list((3..5).map(Value::Int)).unwrap();
+    assert_eq!(list, Value::List((1..5).map(Value::Int).collect()));
+
+    assert_eq!(list.extend_list(vec![Value::Int(5), Value::Byte(6)]),
+               Err(Error::HeterogeneousList { index: 5, expected: TagId::Int, got: TagId::Byte }));
+    assert_eq!(list, Value::List((1..5).map(Value::Int).collect()));
+
+    assert_eq!(Value::Int(1).extend_list(vec![]), Err(Error::TagMismatch(TagId::Int, TagId::List)));
+}
+
+#[test]
+fn compound_union_and_intersection() {
+    let a = compound! { "x" => 1, "y" => 2 };
+    let b = compound! { "y" => 3, "z" => 4 };
+    assert_eq!(Value::union(&a, &b), Ok(compound! { "x" => 1, "y" => 3, "z" => 4 }));
+    assert_eq!(Value::intersection(&a, &b), Ok(compound! { "y" => 2 }));
+
+    assert_eq!(Value::union(&a, &Value::Int(1)), Err(Error::TagMismatch(TagId::Int, TagId::Compound)));
+    assert_eq!(Value::intersection(&Value::Byte(1), &b), Err(Error::TagMismatch(TagId::Byte, TagId::Compound)));
+}
+
+#[test]
+fn compound_retain_keys() {
+    let mut player = compound! { "Name" => "Steve", "Pos" => Value::List(vec![]), "Password" => "hunter2" };
+    player.retain_keys(&["Name", "Pos", "Health"]);
+    assert_eq!(player, compound! { "Name" => "Steve", "Pos" => Value::List(vec![]) });
+
+    let mut int = Value::Int(1);
+    int.retain_keys(&[]);
+    assert_eq!(int, Value::Int(1));
+}
+
+#[test]
+fn upgrade_byte_arrays() {
+    use minecraft::TargetArrayType;
+
+    let mut chunk = compound! {
+        "Level" => compound! {
+            "Biomes" => vec![1i8, -128],
+            "Sections" => Value::List(vec![compound! { "BlockStates" => vec![1i8, 2, 3, 15, 4] }]),
+            "Other" => vec![1i8]
+        }
+    };
+    let mut rules = HashMap::new();
+    rules.insert("Biomes", TargetArrayType::IntArray);
+    rules.insert("BlockStates", TargetArrayType::LongArray { bits_per_entry: 16 });
+    chunk.upgrade_byte_arrays_to_int_arrays(&rules).unwrap();
+    assert_eq!(chunk, compound! {
+        "Level" => compound! {
+            "Biomes" => vec![1i32, 128],
+            "Sections" => Value::List(vec![compound! {
+                "BlockStates" => vec![0x000f_0003_0002_0001i64, 0x0004]
+            }]),
+            "Other" => vec![1i8]
+        }
+    });
+
+    let mut narrow = compound! { "BlockStates" => vec![16i8] };
+    rules.insert("BlockStates", TargetArrayType::LongArray { bits_per_entry: 4 });
+    assert_eq!(narrow.upgrade_byte_arrays_to_int_arrays(&rules), Err(Error::NumericOutOfRange(16)));
+}
+
+#[test]
+#[cfg(feature = "lazy")]
+fn lazy_compound() {
+    use lazy::LazyCompound;
+
+    let value = compound! {
+        "a" => 1i8,
+        "b" => Value::List(vec![compound! { "c" => "d" }]),
+        "e" => vec![1i64, 2, 3]
+    };
+    let mut payload = Vec::new();
+    value.to_writer(&mut payload).unwrap();
+
+    let compound = LazyCompound::from_payload(&payload).unwrap();
+    assert_eq!(compound.len(), 3);
+    assert!(compound.contains_key("b"));
+    assert_eq!(compound.get("b").unwrap(), Some(Value::List(vec![compound! { "c" => "d" }])));
+    assert_eq!(compound.get("missing").unwrap(), None);
+    assert_eq!(compound.get_raw("a"), Some((TagId::Byte, &[0x01][..])));
+    assert_eq!(compound.to_value().unwrap(), value);
+
+    assert_eq!(LazyCompound::from_payload(&payload[..payload.len() - 1]).unwrap_err(),
+               Error::IncompleteNbtValue);
+}
+
+#[test]
+#[cfg(unix)]
+fn nbt_file_lock() {
+    use std::fs;
+    use std::os::unix::io::AsRawFd;
+    use lock::NbtFileLock;
+
+    let try_lock = |file: &File| unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0;
+
+    let path = std::env::temp_dir().join(format!("hematite-nbt-lock-{}.dat", std::process::id()));
+    let mut blob = Blob::named("level");
+    blob.insert("raining", 1i8).unwrap();
+    blob.to_gzip_writer(&mut File::create(&path).unwrap()).unwrap();
+
+    let (name, mut value, lock) = NbtFileLock::open(&path).unwrap();
+    assert_eq!(name, "level");
+    // Another handle cannot take the lock while the guard is held.
+    assert!(!try_lock(&File::open(&path).unwrap()));
+
+    value.insert_or_replace("raining", 0i8).unwrap();
+    lock.save_and_close("level", &value).unwrap();
+    assert!(try_lock(&File::open(&path).unwrap()));
+
+    let (name, reread, lock) = NbtFileLock::open(&path).unwrap();
+    assert_eq!(name, "level");
+    assert_eq!(reread, compound! { "raining" => 0i8 });
+
+    // A value that cannot be encoded leaves the file untouched.
+    let mixed = compound! { "l" => Value::List(vec![Value::Int(1), Value::Byte(2)]) };
+    assert!(lock.save_and_close("level", &mixed).is_err());
+    let (_, reread, _lock) = NbtFileLock::open(&path).unwrap();
+    assert_eq!(reread, compound! { "raining" => 0i8 });
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn value_from_reader_partial() {
+    let value = compound! { "list" => Value::List(vec![Value::Int(1), Value::Int(2)]) };
+    let mut doc = vec![0x0a, 0x00, 0x00];
+    value.to_writer(&mut doc).unwrap();
+
+    let (name, read, bytes_read) = Value::from_reader_partial(&doc[..], 100).unwrap();
+    assert_eq!((&name[..], &read, bytes_read), ("", &value, doc.len()));
+
+    // Stop in the middle of the second list element.
+    assert_eq!(Value::from_reader_partial(&doc[..], 21),
+               Err(Error::Truncated {
+                   partial: compound! { "list" => Value::List(vec![Value::Int(1)]) },
+                   bytes_read: 21,
+               }));
+
+    // Sources that end before the limit are simply incomplete.
+    assert_eq!(Value::from_reader_partial(&doc[..21], 100), Err(Error::IncompleteNbtValue));
+}
+
+#[test]
+fn value_dig() {
+    let mut chunk = compound! {
+        "Level" => compound! { "xPos" => 1, "a.b" => 2 }
+    };
+    assert_eq!(chunk.dig(&["Level", "xPos"]), Some(&Value::Int(1)));
+    assert_eq!(chunk.dig(&["Level", "a.b"]), Some(&Value::Int(2)));
+    assert_eq!(chunk.dig(&["Level", "xPos", "z"]), None);
+    assert_eq!(chunk.dig(&["Missing"]), None);
+    assert_eq!(chunk.dig(&[]), Some(&chunk.clone()));
+
+    *chunk.dig_mut(&["Level", "xPos"]).unwrap() = Value::Int(5);
+    assert_eq!(chunk.get_path("Level.xPos"), Some(&Value::Int(5)));
+}
+
+#[test]
+fn compound_map_values() {
+    let scores = compound! { "alice" => 3, "bob" => 5 };
+    let doubled = scores.map_values(|key, value| match value {
+        Value::Int(v) if key != "bob" => Value::Int(v * 2),
+        other => other,
+    });
+    assert_eq!(doubled, compound! { "alice" => 6, "bob" => 5 });
+    assert_eq!(Value::Int(1).map_values(|_, _| Value::Int(2)), Value::Int(1));
+}
+
+#[test]
+fn value_to_writer_counts_bytes() {
+    let value = compound! { "a" => 1i8, "list" => Value::List(vec![Value::Int(1)]) };
+    let mut dst = Vec::new();
+    let written = value.to_writer(&mut dst).unwrap();
+    assert_eq!(written, dst.len());
+    assert_eq!(value.to_writer_sorted(&mut Vec::new()).unwrap(), written);
+    assert_eq!(Value::Int(1).to_writer(&mut io::sink()).unwrap(), 4);
+}
+
+#[test]
+fn nbt_cursor_mut() {
+    use cursor::{NbtCursorMut, PathSegment};
+
+    let mut value = compound! {
+        "Level" => compound! { "Sections" => Value::List(vec![compound! { "Y" => 0i8 }]) }
+    };
+    {
+        let mut cursor = NbtCursorMut::new(&mut value);
+        cursor.enter("Level").unwrap();
+        cursor.enter("Sections").unwrap();
+        assert_eq!(cursor.enter("Y"), Err(Error::TagMismatch(TagId::List, TagId::Compound)));
+        assert_eq!(cursor.enter_index(1), Err(Error::PathNotFound("Level.Sections.1".to_string())));
+        cursor.enter_index(0).unwrap();
+        assert_eq!(cursor.path(), &[
+            PathSegment::Key("Level".to_string()),
+            PathSegment::Key("Sections".to_string()),
+            PathSegment::Index(0),
+        ]);
+        cursor.current().insert_or_replace("Y", 1i8).unwrap();
+
+        cursor.leave().unwrap();
+        cursor.leave().unwrap();
+        cursor.leave().unwrap();
+        assert_eq!(cursor.leave(), Err(Error::CursorAtRoot));
+        assert!(cursor.path().is_empty());
+    }
+    assert_eq!(value.get_path("Level.Sections.0.Y"), Some(&Value::Byte(1)));
+}
+
+#[test]
+fn nbt_cursor() {
+    use cursor::{NbtCursor, NbtCursorMut};
+
+    let mut value = compound! {
+        "a" => 1,
+        "b" => Value::List(vec![Value::Int(2)]),
+        "c" => 3
+    };
+    {
+        let mut cursor = NbtCursor::new(&value);
+        assert_eq!(cursor.peek_next_key(), None);
+        cursor.enter("a").unwrap();
+        assert_eq!(cursor.peek_next_key(), Some("b"));
+        cursor.leave().unwrap();
+        cursor.enter("b").unwrap();
+        assert_eq!(cursor.peek_next_key(), Some("c"));
+        cursor.enter_index(0).unwrap();
+        assert_eq!(cursor.current(), &Value::Int(2));
+        assert_eq!(cursor.peek_next_key(), None);
+        assert_eq!(cursor.to_string(), "b.0");
+        assert_eq!(cursor.enter("x"), Err(Error::TagMismatch(TagId::Int, TagId::Compound)));
+
+        cursor.leave().unwrap();
+        cursor.leave().unwrap();
+        assert_eq!(cursor.enter("d"), Err(Error::PathNotFound("d".to_string())));
+        cursor.enter("c").unwrap();
+        assert_eq!(cursor.peek_next_key(), None);
+        cursor.leave().unwrap();
+        assert_eq!(cursor.leave(), Err(Error::CursorAtRoot));
+    }
+
+    let mut cursor = NbtCursorMut::new(&mut value);
+    cursor.enter("b").unwrap();
+    cursor.enter_index(0).unwrap();
+    assert_eq!(cursor.to_string(), "b.0");
+}
+
+#[test]
+fn compound_pop() {
+    let mut value = compound! { "a" => 1, "b" => 2 };
+    assert_eq!(value.pop("a"), Some(Value::Int(1)));
+    assert_eq!(value.pop("a"), None);
+    assert_eq!(value, compound! { "b" => 2 });
+    assert_eq!(Value::List(vec![]).pop("a"), None);
+}
+
+#[test]
+fn compound_move_key() {
+    let mut entity = compound! { "Riding" => compound! {}, "id" => "pig" };
+    assert_eq!(entity.move_key("Riding", "RootVehicle"), Ok(true));
+    assert_eq!(entity, compound! { "RootVehicle" => compound! {}, "id" => "pig" });
+    assert_eq!(entity.move_key("Riding", "RootVehicle"), Ok(false));
+    assert_eq!(entity.move_key("id", "id"), Ok(true));
+    assert_eq!(entity.move_key("id", "RootVehicle"), Err(Error::DuplicateKey("RootVehicle".to_string())));
+    assert_eq!(entity, compound! { "RootVehicle" => compound! {}, "id" => "pig" });
+    assert_eq!(Value::Int(1).move_key("a", "b"), Err(Error::TagMismatch(TagId::Int, TagId::Compound)));
+}
+
+#[test]
+fn value_compact_roundtrip() {
+    let schema = compound! {
+        "a" => 0i8, "b" => 0i64, "c" => 0.0f64, "s" => "",
+        "nested" => compound! { "x" => 0i32 },
+        "items" => Value::List(vec![compound! { "Count" => 0i8 }])
+    };
+    let value = compound! {
+        "a" => 0i8, "b" => 7i64, "c" => -0.0f64, "s" => "",
+        "nested" => compound! { "x" => 0i32 },
+        "items" => Value::List(vec![compound! { "Count" => 0i8 }, compound! { "Count" => 3i8 }])
+    };
+
+    let mut dst = Vec::new();
+    let written = value.to_writer_compact(&mut dst).unwrap();
+    assert_eq!(written, dst.len());
+
+    let plain = Value::from_reader(TagId::Compound, &mut &dst[..]).unwrap();
+    assert_eq!(plain.get("a"), None);
+    assert_eq!(plain.get("s"), None);
+    assert_eq!(plain.dig(&["nested", "x"]), None);
+    assert_eq!(plain.get("b"), Some(&Value::Long(7)));
+    assert!(plain.get("c").is_some());
+
+    let read = Value::from_reader_compact(TagId::Compound, &mut &dst[..], &schema).unwrap();
+    assert_eq!(read, value);
+}
+
+#[test]
+fn value_byte_slice() {
+    let mut value = Value::ByteArray(vec![0, 1, -1, -128]);
+    assert_eq!(value.byte_slice(), Some(&[0u8, 1, 255, 128][..]));
+    value.byte_slice_mut().unwrap()[0] = 200;
+    assert_eq!(value, Value::ByteArray(vec![-56, 1, -1, -128]));
+    assert_eq!(Value::IntArray(vec![1]).byte_slice(), None);
+    assert_eq!(Value::Byte(1).byte_slice_mut(), None);
+}
+
+#[test]
+fn read_string_in_small_chunks() {
+    // A source that returns at most one byte per read.
+    struct Trickle<'a>(&'a [u8]);
+    impl<'a> io::Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(1);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    let bytes = [0x00, 0x05, b'h', b'e', b'l', b'l', b'o'];
+    assert_eq!(Value::from_reader(TagId::String, &mut Trickle(&bytes)),
+               Ok(Value::String("hello".to_string())));
+    assert_eq!(Value::from_reader(TagId::String, &mut Trickle(&bytes[..5])),
+               Err(Error::IncompleteNbtValue));
+}
+
+#[test]
+fn value_array_slices() {
+    let mut ints = Value::IntArray(vec![1, 2, 3]);
+    assert_eq!(ints.int_array_slice(), Some(&[1, 2, 3][..]));
+    ints.int_array_slice_mut().unwrap()[1] = 20;
+    assert_eq!(ints, Value::IntArray(vec![1, 20, 3]));
+    assert_eq!(ints.long_array_slice(), None);
+
+    let mut longs = Value::LongArray(vec![-1, 5]);
+    assert_eq!(longs.long_array_slice(), Some(&[-1, 5][..]));
+    longs.long_array_slice_mut().unwrap().reverse();
+    assert_eq!(longs, Value::LongArray(vec![5, -1]));
+    assert_eq!(longs.int_array_slice_mut(), None);
+}
+
+#[test]
+fn compound_entry_count() {
+    assert_eq!(compound! { "a" => 1, "b" => 2 }.entry_count(), Some(2));
+    assert_eq!(compound! {}.entry_count(), Some(0));
+    assert_eq!(Value::List(vec![Value::Int(1)]).entry_count(), None);
+    assert_eq!(Value::Int(0).entry_count(), None);
+}
+
+#[test]
+fn writer_validate_strings() {
+    use writer::NbtWriterBuilder;
+
+    // Four bytes of UTF-8 become six of Modified UTF-8, so this string fits
+    // the length prefix as UTF-8 but not once encoded.
+    let long = "\u{1F600}".repeat(12000);
+    let value = compound! { "a" => Value::List(vec![Value::String(long.clone())]) };
+
+    let writer = NbtWriterBuilder::new().validate_strings(true).build();
+    let mut dst = Vec::new();
+    assert_eq!(writer.write(&mut dst, "", &value), Err(Error::StringTooLong(72000)));
+    assert!(dst.is_empty());
+    assert_eq!(writer.write(&mut dst, &long, &compound! {}), Err(Error::StringTooLong(72000)));
+    assert!(writer.write(&mut dst, "", &compound! { "a" => "\u{1F600}\0" }).is_ok());
+
+    let unchecked = NbtWriterBuilder::new().build();
+    assert!(unchecked.write(&mut Vec::new(), "", &value).is_ok());
+}
+
+#[test]
+fn read_unnamed_root() {
+    use config::ParseConfig;
+    use reader::{NbtEvent, NbtReaderBuilder};
+    use writer::NbtWriterBuilder;
+
+    let mut bytes = Vec::new();
+    NbtWriterBuilder::new().root_name(false).build()
+        .write(&mut bytes, "ignored", &compound! { "x" => 5i32 }).unwrap();
+    assert_eq!(bytes, vec![0x0a, 0x03, 0x00, 0x01, b'x', 0x00, 0x00, 0x00, 0x05, 0x00]);
+
+    let config = ParseConfig { legacy_alpha_format: true, ..Default::default() };
+    let mut expected = Blob::new();
+    expected.insert("x", 5i32).unwrap();
+    assert_eq!(Blob::from_reader_with_config(&mut &bytes[..], &config), Ok(expected));
+    assert!(Blob::from_reader(&mut &bytes[..]).is_err());
+
+    let mut reader = NbtReaderBuilder::new().legacy_alpha_format(true).build(&bytes[..]);
+    assert_eq!(reader.next().unwrap(), Some(NbtEvent::Name("")));
+    assert_eq!(reader.next().unwrap(), Some(NbtEvent::StartCompound));
+    assert_eq!(reader.next().unwrap(), Some(NbtEvent::Name("x")));
+}
+
+#[test]
+fn string_cesu8_helpers() {
+    use string::{cesu8_len, from_cesu8, to_cesu8};
+
+    for s in &["", "plain", "\0", "\u{e9}\u{20ac}", "\u{10000}\u{1F600}x"] {
+        assert_eq!(cesu8_len(s), to_cesu8(s).len());
+        assert_eq!(from_cesu8(&to_cesu8(s)).unwrap(), *s);
+    }
+    assert_eq!(&to_cesu8("\0")[..], &[0xc0, 0x80][..]);
+    assert_eq!(from_cesu8(&[0xff]), Err(Error::InvalidUtf8));
+}
+
+#[test]
+fn read_with_tracked_paths() {
+    use config::ParseConfig;
+
+    let value = compound! {
+        "Level" => compound! {
+            "Sections" => Value::List(vec![
+                compound! { "Y" => 0i8 },
+                compound! { "Blocks" => Value::ByteArray(vec![1, 2, 3, 4]) }
+            ])
+        }
+    };
+    let mut bytes = Vec::new();
+    value.to_writer(&mut bytes).unwrap();
+    // Cut the byte array short.
+    bytes.truncate(bytes.len() - 4);
+
+    let config = ParseConfig { track_paths: true, ..Default::default() };
+    let err = Value::from_reader_with_config(TagId::Compound, &mut &bytes[..], &config).unwrap_err();
+    match err {
+        Error::AtPath(ref path, ref e) => {
+            assert_eq!(path, "Level.Sections.1.Blocks");
+            assert!(matches!(**e, Error::TruncatedArray { .. }));
+        },
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+    assert!(err.pretty_print().starts_with("Error at path \"Level.Sections.1.Blocks\": "));
+
+    let err = Value::from_reader(TagId::Compound, &mut &bytes[..]).unwrap_err();
+    assert!(matches!(err, Error::TruncatedArray { .. }));
+    assert_eq!(err.pretty_print(), err.to_string());
+}
+
+#[test]
+fn compound_zip_with() {
+    let a = compound! { "x" => 1, "y" => 2 };
+    let b = compound! { "y" => 20, "z" => 30 };
+
+    let calls = ::std::cell::Cell::new(0);
+    let sum = a.zip_with(&b, |l, r| {
+        calls.set(calls.get() + 1);
+        let n = l.and_then(Value::as_i64_checked).unwrap_or(0) + r.and_then(Value::as_i64_checked).unwrap_or(0);
+        Some(Value::Long(n))
+    });
+    assert_eq!(sum, Ok(compound! { "x" => 1i64, "y" => 22i64, "z" => 30i64 }));
+    assert_eq!(calls.get(), 3);
+
+    let both = a.zip_with(&b, |l, r| r.and(l).cloned());
+    assert_eq!(both, Ok(compound! { "y" => 2 }));
+    assert_eq!(a.zip_with(&Value::Int(1), |l, _| l.cloned()), Err(Error::TagMismatch(TagId::Int, TagId::Compound)));
+}
+
+#[test]
+fn value_from_reader_bare() {
+    let bytes = [0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x07, 0xff, 0xff, 0xff, 0xff];
+    assert_eq!(Value::from_reader_bare(0x0b, &mut &bytes[..]), Ok(Value::IntArray(vec![7, -1])));
+    assert_eq!(Value::from_reader_bare(0x00, &mut &bytes[..]), Err(Error::InvalidTypeId(0x00)));
+    assert_eq!(Value::from_reader_bare(0x0d, &mut &bytes[..]), Err(Error::InvalidTypeId(0x0d)));
+}
+
+#[test]
+fn compound_batch_insert() {
+    let mut value = compound! { "a" => 1 };
+    assert_eq!(value.batch_insert(vec![("a", 10), ("b", 2), ("c", 3)]), Ok(2));
+    assert_eq!(value, compound! { "a" => 10, "b" => 2, "c" => 3 });
+    assert_eq!(value.batch_insert(Vec::<(String, Value)>::new()), Ok(0));
+    assert_eq!(Value::Int(0).batch_insert(vec![("a", 1)]), Err(Error::TagMismatch(TagId::Int, TagId::Compound)));
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn typed_json_roundtrip() {
+    let value = compound! {
+        "b" => 42i8, "s" => -3i16, "i" => 7, "l" => i64::MAX,
+        "f" => 0.1f32, "d" => f64::NEG_INFINITY,
+        "ba" => Value::ByteArray(vec![-1, 2]), "ia" => Value::IntArray(vec![]),
+        "la" => Value::LongArray(vec![i64::MIN]), "str" => "text",
+        "list" => Value::List(vec![Value::Short(1), Value::Short(2)]),
+        "nested" => compound! { "__nbt_type" => "byte", "value" => 1i8 }
+    };
+    let json = value.to_typed_json();
+    let text = json.to_string();
+    let parsed: ::serde_json::Value = ::serde_json::from_str(&text).unwrap();
+    assert_eq!(Value::from_typed_json(&parsed), Ok(value.clone()));
+    assert_eq!(Value::from_json_map(&value.to_json_map().unwrap()), Ok(value));
+
+    let nan = Value::from_typed_json(&Value::Float(f32::NAN).to_typed_json()).unwrap();
+    assert!(matches!(nan, Value::Float(v) if v.is_nan()));
+
+    let parse = |s: &str| Value::from_typed_json(&::serde_json::from_str(s).unwrap());
+    assert_eq!(parse(r#"{"__nbt_type": "byte", "value": 300}"#), Err(Error::NumericOutOfRange(300)));
+    assert!(matches!(parse(r#"{"__nbt_type": "nope", "value": 1}"#), Err(Error::InvalidTypedJson(_))));
+    assert!(matches!(parse("1"), Err(Error::InvalidTypedJson(_))));
+    assert!(matches!(parse(r#"[1, "a"]"#), Err(Error::InvalidTypedJson(_))));
+    assert!(matches!(parse(r#"["a", []]"#), Err(Error::HeterogeneousList { index: 1, .. })));
+    assert_eq!(Value::Int(1).to_json_map(), Err(Error::TagMismatch(TagId::Int, TagId::Compound)));
+}
+
+#[test]
+fn list_iter_as() {
+    let list = Value::List(vec![Value::Long(1), Value::Long(2)]);
+    let iter = list.iter_as::<i64>().unwrap();
+    assert_eq!(iter.len(), 2);
+    assert_eq!(iter.collect::<Result<Vec<_>, _>>(), Ok(vec![1, 2]));
+
+    let mut iter = list.iter_as::<String>().unwrap();
+    assert_eq!(iter.next(), Some(Err(Error::InvalidListElement(0, Box::new(Error::TagMismatch(TagId::Long, TagId::String))))));
+    assert!(Value::Int(1).iter_as::<i32>().is_err());
+}
+
+#[test]
+fn compound_difference() {
+    let old = compound! { "x" => 1, "y" => 2, "z" => 3 };
+    let new = compound! { "y" => 20, "w" => 4 };
+    assert_eq!(old.difference(&new), Ok(compound! { "x" => 1, "z" => 3 }));
+    assert_eq!(new.difference(&old), Ok(compound! { "w" => 4 }));
+    assert_eq!(old.difference(&Value::Int(1)), Err(Error::TagMismatch(TagId::Int, TagId::Compound)));
+}
+
+#[test]
+fn read_string_with_lone_surrogate() {
+    // "a" followed by the encoding of a high surrogate with no low one.
+    let bytes = [0x00, 0x04, b'a', 0xed, 0xa0, 0x80];
+    assert_eq!(Value::from_reader(TagId::String, &mut &bytes[..]), Err(Error::InvalidUtf8));
+    // A proper surrogate pair decodes to the supplementary character.
+    let bytes = [0x00, 0x06, 0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80];
+    assert_eq!(Value::from_reader(TagId::String, &mut &bytes[..]), Ok(Value::String("\u{1F600}".to_string())));
+}
+
+#[test]
+fn value_get_path_or_insert() {
+    let mut value = compound! {
+        "Level" => compound! { "xPos" => 3, "Sections" => Value::List(vec![compound! { "Y" => 0i8 }]) }
+    };
+    assert_eq!(value.get_path_or_insert("Level.xPos", 0), Ok(&mut Value::Int(3)));
+    assert_eq!(value.get_path_or_insert("Level.Sections.0.Y", 5i8), Ok(&mut Value::Byte(0)));
+    assert_eq!(value.get_path_or_insert("Level.Sections.0.Light", 15i8), Ok(&mut Value::Byte(15)));
+    assert_eq!(value.get_path_or_insert("A.B", compound! {}), Ok(&mut compound! {}));
+    assert_eq!(value.get("A"), Some(&compound! { "B" => compound! {} }));
+
+    assert_eq!(value.get_path_or_insert("Level.Sections.1.Y", 0i8),
+               Err(Error::PathNotFound("Level.Sections.1.Y".to_string())));
+    assert_eq!(value.get_path_or_insert("Level.xPos.z", 0), Err(Error::TagMismatch(TagId::Int, TagId::Compound)));
+    assert_eq!(value.get_path("Level.xPos.z"), None);
+}
+
+#[test]
+fn value_contains_path() {
+    let value = compound! {
+        "Level" => compound! { "Sections" => Value::List(vec![compound! { "Y" => 0i8 }]) }
+    };
+    assert!(value.contains_path("Level"));
+    assert!(value.contains_path("Level.Sections.0.Y"));
+    assert!(!value.contains_path("Level.Sections.1"));
+    assert!(!value.contains_path("Level.Sections.0.Y.Z"));
+    assert!(!Value::Int(1).contains_path("a"));
+}
+
+#[test]
+fn value_to_writer_reports_partial_writes() {
+    // A destination with room for only a few bytes.
+    struct Full(usize);
+    impl io::Write for Full {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0 == 0 {
+                return Err(io::Error::new(io::ErrorKind::Other, "disk full"));
+            }
+            let n = buf.len().min(self.0);
+            self.0 -= n;
+            Ok(n)
+        }
+        fn flush(&mut self) -> io::Result<()> { Ok(()) }
+    }
+
+    let value = compound! { "a" => 1i32 };
+    match value.to_writer(&mut Full(5)) {
+        Err(Error::WriteError { ref io, bytes_written }) => {
+            assert_eq!(io.to_string(), "disk full");
+            assert_eq!(bytes_written, 5);
+        },
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(value.to_writer(&mut Full(100)), Ok(9));
+}
+
+#[test]
+fn value_kind_predicates() {
+    let values = vec![
+        (Value::Byte(1), true, true, false, false),
+        (Value::Long(1), true, true, false, false),
+        (Value::Float(1.0), true, false, true, false),
+        (Value::Double(1.0), true, false, true, false),
+        (Value::String("1".to_string()), false, false, false, false),
+        (Value::IntArray(vec![]), false, false, false, true),
+        (Value::List(vec![]), false, false, false, true),
+        (compound! {}, false, false, false, true),
+    ];
+    for (value, numeric, integer, floating, collection) in values {
+        assert_eq!(value.is_numeric(), numeric, "{:?}", value);
+        assert_eq!(value.is_integer(), integer, "{:?}", value);
+        assert_eq!(value.is_floating_point(), floating, "{:?}", value);
+        assert_eq!(value.is_collection(), collection, "{:?}", value);
+    }
+}
+
+#[test]
+fn compound_for_each_key() {
+    use std::ops::ControlFlow;
+
+    let value = compound! { "a" => 1, "b" => 2, "c" => 3 };
+    let mut seen = 0;
+    let found = value.for_each_key(|k, _| {
+        seen += 1;
+        ControlFlow::Break(k.to_string())
+    });
+    assert_eq!(seen, 1);
+    assert!(["a", "b", "c"].contains(&&found.unwrap()[..]));
+
+    let mut count = 0;
+    assert_eq!(value.for_each_key(|_, _| -> ControlFlow<()> { count += 1; ControlFlow::Continue(()) }), None);
+    assert_eq!(count, 3);
+    assert_eq!(Value::Int(1).for_each_key(|_, _| ControlFlow::Break(())), None);
+}
@@ -0,0 +1,41 @@
+//! Conversions between UUIDs and their `TAG_IntArray` representation.
+//!
+//! Since Minecraft 1.16, UUIDs are stored as an array of four ints, starting
+//! with the most significant. Each int holds four bytes of the UUID in
+//! big-endian order.
+
+use std::convert::TryFrom;
+
+use uuid::Uuid;
+
+use error::{Error, Result};
+use tag::TagId;
+use value::Value;
+
+impl TryFrom<Value> for Uuid {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Uuid> {
+        match value {
+            Value::IntArray(ref ints) if ints.len() == 4 => {
+                let mut bytes = [0u8; 16];
+                for (chunk, int) in bytes.chunks_mut(4).zip(ints) {
+                    chunk.copy_from_slice(&int.to_be_bytes());
+                }
+                Ok(Uuid::from_bytes(bytes))
+            },
+            Value::IntArray(ref ints) => Err(Error::InvalidUuidLength(ints.len())),
+            ref other => Err(Error::TagMismatch(other.tag_id(), TagId::IntArray)),
+        }
+    }
+}
+
+impl From<Uuid> for Value {
+    fn from(uuid: Uuid) -> Value {
+        let ints = uuid.as_bytes()
+            .chunks(4)
+            .map(|c| i32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+        Value::IntArray(ints)
+    }
+}
@@ -1,16 +1,38 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::convert::TryFrom;
 use std::fmt;
 use std::io;
+use std::mem;
+use std::ops;
+use std::ops::ControlFlow;
+use std::result::Result as StdResult;
+use std::slice;
+use std::vec;
 
 use byteorder::{BigEndian, WriteBytesExt, ReadBytesExt};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+#[cfg(feature = "serde")]
+use serde::{self, de::{MapAccess, SeqAccess, Visitor}};
 
+use config::ParseConfig;
 use error::{Error, Result};
+use order;
 use raw;
+use tag::TagId;
 
 /// Values which can be represented in the Named Binary Tag format.
-#[derive(Clone, Debug, PartialEq)]
+///
+/// There is no variant for `TAG_End`: on the wire it only terminates a
+/// compound, so it is consumed while parsing and never stored as an entry.
+/// Since no `Value` has that type, none can be inserted into a compound by
+/// mistake either; `TagId::End` only appears as the element type of empty
+/// lists.
+#[derive(Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize))]
-#[cfg_attr(feature = "serde", derive(Deserialize))]
 #[cfg_attr(feature = "serde", serde(untagged))]
 pub enum Value {
     Byte(i8),
@@ -27,7 +49,43 @@ pub enum Value {
     LongArray(Vec<i64>),
 }
 
+/// Generates methods returning the element of a `List` at an index, when it
+/// has the given tag type.
+macro_rules! list_getters {
+    ($($name:ident, $variant:ident, $tag:expr, $ty:ty, |$v:ident| $conv:expr;)*) => {
+        $(
+            #[doc = concat!("Returns element `index` of a `List` of `", $tag, "`s, or `None` if")]
+            /// this is not such a list or the index is out of bounds.
+            pub fn $name(&self, index: usize) -> Option<$ty> {
+                match self.list_element(index) {
+                    Some(&Value::$variant(ref $v)) => Some($conv),
+                    _ => None,
+                }
+            }
+        )*
+    };
+}
+
+/// The largest number of elements allocated up front when reading a list.
+const MAX_LIST_PREALLOCATION: usize = 4096;
+
 impl Value {
+    /// Creates an empty `List` with space for at least `n` elements.
+    ///
+    /// Lists take their element type from their first element, so it does
+    /// not need to be given here.
+    pub fn list_with_capacity(n: usize) -> Value {
+        Value::List(Vec::with_capacity(n))
+    }
+
+    /// Creates an empty `Compound` with space for at least `n` entries.
+    ///
+    /// Binary NBT does not record the number of entries in a compound, so
+    /// this is only useful when a caller knows roughly how many to expect.
+    pub fn compound_with_capacity(n: usize) -> Value {
+        Value::Compound(HashMap::with_capacity(n))
+    }
+
     /// The type ID of this `Value`, which is a single byte in the range
     /// `0x01` to `0x0b`.
     pub fn id(&self) -> u8 {
@@ -47,26 +105,1265 @@ impl Value {
         }
     }
 
-    /// A string representation of this tag.
-    pub fn tag_name(&self) -> &str {
+    /// The type of this `Value`, as a `TagId`.
+    pub fn tag_id(&self) -> TagId {
+        match *self {
+            Value::Byte(_)      => TagId::Byte,
+            Value::Short(_)     => TagId::Short,
+            Value::Int(_)       => TagId::Int,
+            Value::Long(_)      => TagId::Long,
+            Value::Float(_)     => TagId::Float,
+            Value::Double(_)    => TagId::Double,
+            Value::ByteArray(_) => TagId::ByteArray,
+            Value::String(_)    => TagId::String,
+            Value::List(_)      => TagId::List,
+            Value::Compound(_)  => TagId::Compound,
+            Value::IntArray(_)  => TagId::IntArray,
+            Value::LongArray(_) => TagId::LongArray,
+        }
+    }
+
+    /// The type of the elements of a `List`, or `None` for other tags.
+    ///
+    /// Lists do not store their element type separately, so this is the type
+    /// of the first element, or `TagId::End` for an empty list, matching what
+    /// `to_writer` writes.
+    pub fn element_tag_id(&self) -> Option<TagId> {
+        match *self {
+            Value::List(ref vals) => Some(vals.first().map_or(TagId::End, Value::tag_id)),
+            _ => None,
+        }
+    }
+
+    /// A string representation of this tag.
+    pub fn tag_name(&self) -> &str {
+        match *self {
+            Value::Byte(_)      => "TAG_Byte",
+            Value::Short(_)     => "TAG_Short",
+            Value::Int(_)       => "TAG_Int",
+            Value::Long(_)      => "TAG_Long",
+            Value::Float(_)     => "TAG_Float",
+            Value::Double(_)    => "TAG_Double",
+            Value::ByteArray(_) => "TAG_ByteArray",
+            Value::String(_)    => "TAG_String",
+            Value::List(_)      => "TAG_List",
+            Value::Compound(_)  => "TAG_Compound",
+            Value::IntArray(_)  => "TAG_IntArray",
+            Value::LongArray(_) => "TAG_LongArray",
+        }
+    }
+
+    /// Converts a scalar tag to a string in the way Minecraft does for
+    /// display (e.g. `Byte(1)` becomes `"1"` and `Float(3.14)` becomes
+    /// `"3.14"`), without the tag type suffixes used by SNBT.
+    ///
+    /// Floating-point values are formatted as by Java's `Double.toString`,
+    /// with the shortest digits that read back as the same value. Magnitudes
+    /// from 10^-3 up to 10^7 are written as decimals, keeping a trailing `.0`
+    /// if there is no fractional part, and others in scientific notation,
+    /// e.g. `1.0E10` or `2.5E-4`. The non-finite values are `NaN`, `Infinity`
+    /// and `-Infinity`.
+    ///
+    /// Returns `None` for compounds, lists and arrays.
+    pub fn coerce_string(&self) -> Option<String> {
+        fn float(v: f64, plain: String, sci: String) -> String {
+            if v.is_nan() {
+                return "NaN".to_string();
+            } else if v.is_infinite() {
+                return if v > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+            }
+            let with_point = |s: &str| if s.contains('.') { s.to_string() } else { format!("{}.0", s) };
+            if v == 0.0 || (v.abs() >= 1e-3 && v.abs() < 1e7) {
+                with_point(&plain)
+            } else {
+                let (mantissa, exponent) = sci.split_at(sci.find('e').unwrap());
+                format!("{}E{}", with_point(mantissa), &exponent[1..])
+            }
+        }
+
+        match *self {
+            Value::Byte(v)   => Some(v.to_string()),
+            Value::Short(v)  => Some(v.to_string()),
+            Value::Int(v)    => Some(v.to_string()),
+            Value::Long(v)   => Some(v.to_string()),
+            Value::Float(v)  => Some(float(v as f64, v.to_string(), format!("{:e}", v))),
+            Value::Double(v) => Some(float(v, v.to_string(), format!("{:e}", v))),
+            Value::String(ref v) => Some(v.clone()),
+            Value::ByteArray(_) | Value::IntArray(_) | Value::LongArray(_) |
+            Value::List(_) | Value::Compound(_) => None,
+        }
+    }
+
+    /// Returns `true` for the integer and floating-point tags.
+    pub fn is_numeric(&self) -> bool {
+        self.is_integer() || self.is_floating_point()
+    }
+
+    /// Returns `true` for `Byte`, `Short`, `Int` and `Long`.
+    pub fn is_integer(&self) -> bool {
+        matches!(*self, Value::Byte(_) | Value::Short(_) | Value::Int(_) | Value::Long(_))
+    }
+
+    /// Returns `true` for `Float` and `Double`.
+    pub fn is_floating_point(&self) -> bool {
+        matches!(*self, Value::Float(_) | Value::Double(_))
+    }
+
+    /// Returns `true` for lists, compounds and the array tags.
+    pub fn is_collection(&self) -> bool {
+        matches!(*self, Value::ByteArray(_) | Value::IntArray(_) | Value::LongArray(_) |
+                        Value::List(_) | Value::Compound(_))
+    }
+
+    /// Interprets a `Byte` of `0` or `1` as a boolean, as Minecraft does.
+    /// Returns `None` for any other value.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Byte(0) => Some(false),
+            Value::Byte(1) => Some(true),
+            _ => None,
+        }
+    }
+
+    /// Widens any integer tag to an `i64`, which is always exact. Returns
+    /// `None` for `Float` and `Double`, which may not convert exactly, and
+    /// for non-numeric tags.
+    pub fn as_i64_checked(&self) -> Option<i64> {
+        match *self {
+            Value::Byte(v)  => Some(v as i64),
+            Value::Short(v) => Some(v as i64),
+            Value::Int(v)   => Some(v as i64),
+            Value::Long(v)  => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Widens any numeric tag to an `f64`, or returns `None` for strings,
+    /// arrays, lists and compounds.
+    ///
+    /// `Long` values beyond 2^53 in magnitude are rounded to the nearest
+    /// representable `f64`.
+    pub fn as_f64_lossy(&self) -> Option<f64> {
+        match *self {
+            Value::Byte(v)   => Some(v as f64),
+            Value::Short(v)  => Some(v as f64),
+            Value::Int(v)    => Some(v as f64),
+            Value::Long(v)   => Some(v as f64),
+            Value::Float(v)  => Some(v as f64),
+            Value::Double(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Converts any numeric tag to an `f32`, or returns `None` for strings,
+    /// arrays, lists and compounds.
+    ///
+    /// `Int` and `Long` values beyond 2^24 in magnitude, and `Double` values
+    /// in general, are rounded to the nearest representable `f32`.
+    pub fn as_f32_lossy(&self) -> Option<f32> {
+        match *self {
+            Value::Byte(v)   => Some(v as f32),
+            Value::Short(v)  => Some(v as f32),
+            Value::Int(v)    => Some(v as f32),
+            Value::Long(v)   => Some(v as f32),
+            Value::Float(v)  => Some(v),
+            Value::Double(v) => Some(v as f32),
+            _ => None,
+        }
+    }
+
+    /// Views the contents of a `ByteArray` as unsigned bytes, or returns
+    /// `None` for other tags.
+    pub fn byte_slice(&self) -> Option<&[u8]> {
+        match *self {
+            // This is safe because `i8` and `u8` have the same size and
+            // alignment, and every bit pattern is valid for both.
+            Value::ByteArray(ref v) => Some(unsafe {
+                slice::from_raw_parts(v.as_ptr() as *const u8, v.len())
+            }),
+            _ => None,
+        }
+    }
+
+    /// Mutably views the contents of a `ByteArray` as unsigned bytes, or
+    /// returns `None` for other tags.
+    pub fn byte_slice_mut(&mut self) -> Option<&mut [u8]> {
+        match *self {
+            // Safe for the same reasons as in `byte_slice`.
+            Value::ByteArray(ref mut v) => Some(unsafe {
+                slice::from_raw_parts_mut(v.as_mut_ptr() as *mut u8, v.len())
+            }),
+            _ => None,
+        }
+    }
+
+    /// Views the contents of an `IntArray`, or returns `None` for other tags.
+    pub fn int_array_slice(&self) -> Option<&[i32]> {
+        match *self {
+            Value::IntArray(ref v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Mutably views the contents of an `IntArray`, or returns `None` for
+    /// other tags.
+    pub fn int_array_slice_mut(&mut self) -> Option<&mut [i32]> {
+        match *self {
+            Value::IntArray(ref mut v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Views the contents of a `LongArray`, or returns `None` for other tags.
+    pub fn long_array_slice(&self) -> Option<&[i64]> {
+        match *self {
+            Value::LongArray(ref v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Mutably views the contents of a `LongArray`, or returns `None` for
+    /// other tags.
+    pub fn long_array_slice_mut(&mut self) -> Option<&mut [i64]> {
+        match *self {
+            Value::LongArray(ref mut v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of entries of a `Compound`, or `None` for other
+    /// tags, including lists and arrays.
+    pub fn entry_count(&self) -> Option<usize> {
+        match *self {
+            Value::Compound(ref map) => Some(map.len()),
+            _ => None,
+        }
+    }
+
+    /// Calls `f` on each entry of a `Compound`, in no particular order,
+    /// until it returns `ControlFlow::Break`. Returns the value it broke
+    /// with, or `None` if it never did or this is not a compound.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate nbt;
+    /// # fn main() {
+    /// use std::ops::ControlFlow;
+    ///
+    /// let item = compound! { "id" => "minecraft:stone", "Count" => 64i8 };
+    /// let found = item.for_each_key(|key, value| {
+    ///     if value.is_numeric() { ControlFlow::Break(key.to_string()) } else { ControlFlow::Continue(()) }
+    /// });
+    /// assert_eq!(found, Some("Count".to_string()));
+    /// # }
+    /// ```
+    pub fn for_each_key<F, B>(&self, mut f: F) -> Option<B>
+        where F: FnMut(&str, &Value) -> ControlFlow<B>
+    {
+        match *self {
+            Value::Compound(ref map) => {
+                for (key, value) in map {
+                    if let ControlFlow::Break(b) = f(key, value) {
+                        return Some(b);
+                    }
+                }
+                None
+            },
+            _ => None,
+        }
+    }
+
+    /// Iterates over the entries of a `Compound` in key order, or returns
+    /// `None` for other tags.
+    ///
+    /// Compounds are backed by a `HashMap`, which does not remember the order
+    /// in which entries were inserted (or read), so this is the only
+    /// deterministic order available.
+    pub fn iter_sorted(&self) -> Option<impl Iterator<Item = (&str, &Value)>> {
+        match *self {
+            Value::Compound(ref map) => {
+                let entries = sorted_entries(map).into_iter().map(|(k, v)| (&k[..], v));
+                Some(entries)
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the entry `key` of a `Compound`, or `None` if it is absent or
+    /// this is not a compound.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match *self {
+            Value::Compound(ref map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the entry `key` of a `Compound`, or
+    /// `None` if it is absent or this is not a compound.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        match *self {
+            Value::Compound(ref mut map) => map.get_mut(key),
+            _ => None,
+        }
+    }
+
+    /// Removes and returns the entry `key` of a `Compound`, or returns `None`
+    /// if it is absent or this is not a compound.
+    pub fn pop(&mut self, key: &str) -> Option<Value> {
+        match *self {
+            Value::Compound(ref mut map) => map.remove(key),
+            _ => None,
+        }
+    }
+
+    /// Renames the entry `from` of a `Compound` to `to`, returning `false`
+    /// (and changing nothing) if there is no entry `from`.
+    ///
+    /// Returns `Error::DuplicateKey` if another entry is already named `to`,
+    /// and `Error::TagMismatch` if this is not a compound.
+    pub fn move_key<S>(&mut self, from: &str, to: S) -> Result<bool>
+        where S: Into<String>
+    {
+        let map = match *self {
+            Value::Compound(ref mut map) => map,
+            _ => return Err(Error::TagMismatch(self.tag_id(), TagId::Compound)),
+        };
+        let to = to.into();
+        if !map.contains_key(from) {
+            return Ok(false);
+        }
+        if to != from {
+            if map.contains_key(&to) {
+                return Err(Error::DuplicateKey(to));
+            }
+            if let Some(value) = map.remove(from) {
+                map.insert(to, value);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Stores `value` under `key` in a `Compound`, returning the value it
+    /// replaced, if any.
+    ///
+    /// Returns `Error::TagMismatch` if this is not a compound.
+    pub fn insert_or_replace<S, V>(&mut self, key: S, value: V) -> Result<Option<Value>>
+        where S: Into<String>, V: Into<Value>
+    {
+        match *self {
+            Value::Compound(ref mut map) => Ok(map.insert(key.into(), value.into())),
+            _ => Err(Error::TagMismatch(self.tag_id(), TagId::Compound)),
+        }
+    }
+
+    /// Stores every entry of `entries` in a `Compound`, replacing the values
+    /// of keys already present, and returns how many of the keys were new.
+    ///
+    /// Returns `Error::TagMismatch` if this is not a compound.
+    pub fn batch_insert<I, K, V>(&mut self, entries: I) -> Result<usize>
+        where I: IntoIterator<Item = (K, V)>, K: Into<String>, V: Into<Value>
+    {
+        match *self {
+            Value::Compound(ref mut map) => {
+                let entries = entries.into_iter();
+                map.reserve(entries.size_hint().0);
+                let before = map.len();
+                for (key, value) in entries {
+                    map.insert(key.into(), value.into());
+                }
+                Ok(map.len() - before)
+            },
+            _ => Err(Error::TagMismatch(self.tag_id(), TagId::Compound)),
+        }
+    }
+
+    /// Returns the entry `key` of a `Compound`, first storing `default` under
+    /// that key if it is absent, like Python's `dict.setdefault`.
+    ///
+    /// Returns `Error::TagMismatch` if this is not a compound.
+    pub fn get_or_default<V>(&mut self, key: &str, default: V) -> Result<&Value>
+        where V: Into<Value>
+    {
+        self.entry(key).map(|entry| &*entry.or_insert_with(|| default.into()))
+    }
+
+    /// Returns the entry `key` of a `Compound` converted to `T`, or `default`
+    /// if there is no such entry, it cannot be converted, or this is not a
+    /// compound.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate nbt;
+    /// # fn main() {
+    /// let chunk = compound! { "xPos" => 3, "Status" => "full" };
+    /// assert_eq!(chunk.get_or::<i32>("xPos", 0), 3);
+    /// assert_eq!(chunk.get_or::<i32>("zPos", 0), 0);
+    /// assert_eq!(chunk.get_or::<i32>("Status", 0), 0);
+    /// # }
+    /// ```
+    pub fn get_or<T>(&self, key: &str, default: T) -> T
+        where T: TryFrom<Value, Error = Error>
+    {
+        self.get(key)
+            .and_then(|value| T::try_from(value.clone()).ok())
+            .unwrap_or(default)
+    }
+
+    /// Looks up the entry `key` of a `Compound` and widens it to an `f64` as
+    /// with `as_f64_lossy`, whatever its numeric tag. Returns `None` if this
+    /// is not a compound, or the entry is absent or not numeric.
+    pub fn get_numeric(&self, key: &str) -> Option<f64> {
+        match *self {
+            Value::Compound(ref map) => map.get(key).and_then(Value::as_f64_lossy),
+            _ => None,
+        }
+    }
+
+    /// Looks up several entries of a `Compound` at once, returning them in the
+    /// order of `keys`. Every entry is `None` if this is not a compound.
+    pub fn get_many<'a>(&'a self, keys: &[&str]) -> Vec<Option<&'a Value>> {
+        match *self {
+            Value::Compound(ref map) => keys.iter().map(|&key| map.get(key)).collect(),
+            _ => keys.iter().map(|_| None).collect(),
+        }
+    }
+
+    /// Looks up several entries of a `Compound` for modification at once,
+    /// returning them in the order of `keys`.
+    ///
+    /// Returns `Error::DuplicateKey` if a key appears in `keys` more than
+    /// once, since its entry could then be borrowed mutably twice.
+    pub fn get_many_mut<'a>(&'a mut self, keys: &[&str]) -> Result<Vec<Option<&'a mut Value>>> {
+        let mut positions = HashMap::with_capacity(keys.len());
+        for (i, &key) in keys.iter().enumerate() {
+            if positions.insert(key, i).is_some() {
+                return Err(Error::DuplicateKey(key.to_string()));
+            }
+        }
+
+        let mut found: Vec<Option<&'a mut Value>> = keys.iter().map(|_| None).collect();
+        if let Value::Compound(ref mut map) = *self {
+            for (key, value) in map.iter_mut() {
+                if let Some(&i) = positions.get(&key[..]) {
+                    found[i] = Some(value);
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    /// Gets the entry for `key` in a `Compound`, for in-place insertion or
+    /// modification as with `HashMap::entry`.
+    ///
+    /// Returns `Error::TagMismatch` if this is not a compound.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate nbt;
+    /// # fn main() {
+    /// use nbt::Value;
+    ///
+    /// let mut player = compound! { "Score" => 10 };
+    /// player.entry("Score").unwrap().and_modify(|v| *v = Value::Int(11));
+    /// player.entry("XpLevel").unwrap().or_insert(Value::Int(0));
+    /// assert_eq!(player, compound! { "Score" => 11, "XpLevel" => 0 });
+    /// # }
+    /// ```
+    pub fn entry<S>(&mut self, key: S) -> Result<Entry<'_, String, Value>>
+        where S: Into<String>
+    {
+        match *self {
+            Value::Compound(ref mut map) => Ok(map.entry(key.into())),
+            _ => Err(Error::TagMismatch(self.tag_id(), TagId::Compound)),
+        }
+    }
+
+    /// Returns a compound with the entries of both `a` and `b`, taking the
+    /// value from `b` for keys present in both.
+    ///
+    /// Returns `Error::TagMismatch` if either value is not a compound.
+    pub fn union(a: &Value, b: &Value) -> Result<Value> {
+        let (a, b) = (a.compound_map()?, b.compound_map()?);
+        let mut map = a.clone();
+        map.extend(b.iter().map(|(k, v)| (k.clone(), v.clone())));
+        Ok(Value::Compound(map))
+    }
+
+    /// Returns a compound with only the entries of `a` whose keys are also
+    /// present in `b`.
+    ///
+    /// Returns `Error::TagMismatch` if either value is not a compound.
+    pub fn intersection(a: &Value, b: &Value) -> Result<Value> {
+        let (a, b) = (a.compound_map()?, b.compound_map()?);
+        Ok(Value::Compound(a.iter()
+            .filter(|&(k, _)| b.contains_key(k))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()))
+    }
+
+    /// Returns a compound with only the entries of this one whose keys are
+    /// not present in `other`, e.g. the entries a newer version of a format
+    /// removed.
+    ///
+    /// Returns `Error::TagMismatch` if either value is not a compound.
+    pub fn difference(&self, other: &Value) -> Result<Value> {
+        let (a, b) = (self.compound_map()?, other.compound_map()?);
+        Ok(Value::Compound(a.iter()
+            .filter(|&(k, _)| !b.contains_key(k))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()))
+    }
+
+    /// Combines two compounds entry by entry. For each key present in either
+    /// `self` or `other`, `f` is called with the value of the key in each,
+    /// and the result becomes the key's value in the returned compound;
+    /// returning `None` leaves the key out.
+    ///
+    /// Returns `Error::TagMismatch` if either value is not a compound.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate nbt;
+    /// # fn main() {
+    /// use nbt::Value;
+    ///
+    /// // The entries that differ, with the values of `new`.
+    /// let old = compound! { "a" => 1, "b" => 2 };
+    /// let new = compound! { "a" => 1, "b" => 3, "c" => 4 };
+    /// let changed = old.zip_with(&new, |o, n| if o == n { None } else { n.cloned() });
+    /// assert_eq!(changed, Ok(compound! { "b" => 3, "c" => 4 }));
+    /// # }
+    /// ```
+    pub fn zip_with<F>(&self, other: &Value, f: F) -> Result<Value>
+        where F: Fn(Option<&Value>, Option<&Value>) -> Option<Value>
+    {
+        let (a, b) = (self.compound_map()?, other.compound_map()?);
+        let mut map = HashMap::new();
+        for key in a.keys().chain(b.keys().filter(|&k| !a.contains_key(k))) {
+            if let Some(value) = f(a.get(key), b.get(key)) {
+                map.insert(key.clone(), value);
+            }
+        }
+        Ok(Value::Compound(map))
+    }
+
+    fn compound_map(&self) -> Result<&HashMap<String, Value>> {
+        match *self {
+            Value::Compound(ref map) => Ok(map),
+            _ => Err(Error::TagMismatch(self.tag_id(), TagId::Compound)),
+        }
+    }
+
+    /// Removes every entry of a `Compound` whose key is not in `keys`, e.g.
+    /// to strip private fields before sending data to a client. Other tags
+    /// are left as they are.
+    pub fn retain_keys(&mut self, keys: &[&str]) {
+        if let Value::Compound(ref mut map) = *self {
+            map.retain(|k, _| keys.contains(&&k[..]));
+        }
+    }
+
+    /// Exchanges the values of two entries of a `Compound`.
+    ///
+    /// Returns `Error::MissingField` if either key is absent, and
+    /// `Error::TagMismatch` if this is not a compound.
+    pub fn swap(&mut self, a: &str, b: &str) -> Result<()> {
+        let map = match *self {
+            Value::Compound(ref mut map) => map,
+            _ => return Err(Error::TagMismatch(self.tag_id(), TagId::Compound)),
+        };
+        for key in &[a, b] {
+            if !map.contains_key(*key) {
+                return Err(Error::MissingField(key.to_string()));
+            }
+        }
+        if a == b {
+            return Ok(());
+        }
+        if let Some((key_a, value_a)) = map.remove_entry(a) {
+            if let Some(slot_b) = map.get_mut(b) {
+                let value_b = mem::replace(slot_b, value_a);
+                map.insert(key_a, value_b);
+            }
+        }
+        Ok(())
+    }
+
+    /// Iterates over the elements of a `List`, converting each to `T`.
+    ///
+    /// Returns `Error::TagMismatch` if this is not a list. If an element
+    /// cannot be converted, the iterator yields the error wrapped in
+    /// `Error::InvalidListElement` along with the element's index.
+    ///
+    /// ```rust
+    /// use nbt::Value;
+    ///
+    /// let pos = Value::List(vec![Value::Int(3), Value::Int(64), Value::Int(-2)]);
+    /// let mut sum = 0;
+    /// for coord in pos.iter_as::<i32>().unwrap() {
+    ///     sum += coord.unwrap();
+    /// }
+    /// assert_eq!(sum, 65);
+    /// ```
+    pub fn iter_as<T>(&self) -> Result<impl ExactSizeIterator<Item = Result<T>> + '_>
+        where T: TryFrom<Value, Error = Error>
+    {
+        match *self {
+            Value::List(ref vals) => Ok(vals.iter().enumerate()
+                .map(|(i, v)| T::try_from(v.clone())
+                     .map_err(|e| Error::InvalidListElement(i, Box::new(e))))),
+            _ => Err(Error::TagMismatch(self.tag_id(), TagId::List)),
+        }
+    }
+
+    /// Converts each element of the list at `key` in this `Compound`.
+    ///
+    /// Returns `Error::MissingField` if there is no such entry, and
+    /// `Error::TagMismatch` if this is not a compound or the entry is not a
+    /// list. If an element cannot be converted, the error is wrapped in
+    /// `Error::InvalidListElement` along with the element's index.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate nbt;
+    /// # fn main() {
+    /// let value = compound! { "Pos" => nbt::Value::List(vec![1.0f64.into(), 64.5f64.into()]) };
+    /// let pos: Vec<f64> = value.get_list_typed("Pos").unwrap();
+    /// assert_eq!(pos, vec![1.0, 64.5]);
+    /// # }
+    /// ```
+    pub fn get_list_typed<T>(&self, key: &str) -> Result<Vec<T>>
+        where T: TryFrom<Value, Error = Error>
+    {
+        let map = match *self {
+            Value::Compound(ref map) => map,
+            _ => return Err(Error::TagMismatch(self.tag_id(), TagId::Compound)),
+        };
+        match map.get(key) {
+            Some(list) => list.iter_as()?.collect(),
+            None => Err(Error::MissingField(key.to_string())),
+        }
+    }
+
+    /// Looks up a nested compound entry by the keys leading to it, as with
+    /// chained calls to `get`. Unlike `get_path`, keys may contain dots, and
+    /// list elements cannot be selected.
+    ///
+    /// Returns `None` if any key does not exist. An empty `keys` returns this
+    /// value itself.
+    pub fn dig<'a>(&'a self, keys: &[&str]) -> Option<&'a Value> {
+        keys.iter().try_fold(self, |value, key| value.get(key))
+    }
+
+    /// Looks up a nested compound entry for modification, as for `dig`.
+    pub fn dig_mut<'a>(&'a mut self, keys: &[&str]) -> Option<&'a mut Value> {
+        keys.iter().try_fold(self, |value, key| value.get_mut(key))
+    }
+
+    /// Looks up a nested value by a dot-separated path, such as
+    /// `"Level.Sections.0.Y"`. Each segment selects an entry of a compound,
+    /// or an element of a list by its index.
+    ///
+    /// Returns `None` if any segment does not exist.
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        path.split('.').try_fold(self, |value, segment| match *value {
+            Value::Compound(ref map) => map.get(segment),
+            Value::List(ref vals) => segment.parse().ok().and_then(|i: usize| vals.get(i)),
+            _ => None,
+        })
+    }
+
+    /// Looks up a nested value for modification, by a dot-separated path as
+    /// for `get_path`.
+    pub fn get_path_mut(&mut self, path: &str) -> Option<&mut Value> {
+        path.split('.').try_fold(self, |value, segment| match *value {
+            Value::Compound(ref mut map) => map.get_mut(segment),
+            Value::List(ref mut vals) => segment.parse().ok().and_then(move |i: usize| vals.get_mut(i)),
+            _ => None,
+        })
+    }
+
+    /// Returns `true` if a value exists at a dot-separated path, as for
+    /// `get_path`.
+    pub fn contains_path(&self, path: &str) -> bool {
+        self.get_path(path).is_some()
+    }
+
+    /// Looks up a nested value for modification by a dot-separated path as
+    /// for `get_path`, first storing `default` at the end of the path if
+    /// nothing is there. Missing compounds along the way are created empty.
+    ///
+    /// Returns `Error::TagMismatch` if a segment other than the last selects
+    /// a value that is neither a compound nor a list, and
+    /// `Error::PathNotFound` if a list has no element at a segment's index.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate nbt;
+    /// # fn main() {
+    /// use nbt::Value;
+    ///
+    /// let mut chunk = compound! { "Level" => compound! {} };
+    /// *chunk.get_path_or_insert("Level.CustomData.Owner", "").unwrap() = "Steve".into();
+    /// assert_eq!(chunk.get_path("Level.CustomData.Owner"), Some(&Value::from("Steve")));
+    /// # }
+    /// ```
+    pub fn get_path_or_insert<V>(&mut self, path: &str, default: V) -> Result<&mut Value>
+        where V: Into<Value>
+    {
+        let mut segments = path.split('.').peekable();
+        let mut value = self;
+        while let Some(segment) = segments.next() {
+            let last = segments.peek().is_none();
+            value = match *value {
+                Value::Compound(ref mut map) => {
+                    let entry = map.entry(segment.to_string());
+                    if last {
+                        return Ok(entry.or_insert(default.into()));
+                    }
+                    entry.or_insert_with(|| Value::Compound(HashMap::new()))
+                },
+                Value::List(ref mut vals) => {
+                    match segment.parse().ok().and_then(move |i: usize| vals.get_mut(i)) {
+                        Some(elem) => elem,
+                        None => return Err(Error::PathNotFound(path.to_string())),
+                    }
+                },
+                ref other => return Err(Error::TagMismatch(other.tag_id(), TagId::Compound)),
+            };
+        }
+        Ok(value)
+    }
+
+    /// Looks up a nested value with `get_path`, and converts it to `T`.
+    ///
+    /// Returns `Error::PathNotFound` if there is no value at `path`, or the
+    /// conversion error (usually `Error::TagMismatch`) if it has another type.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate nbt;
+    /// # fn main() {
+    /// let chunk = compound! { "Level" => compound! { "xPos" => 3 } };
+    /// assert_eq!(chunk.get_path_typed::<i32>("Level.xPos"), Ok(3));
+    /// # }
+    /// ```
+    pub fn get_path_typed<T>(&self, path: &str) -> Result<T>
+        where T: TryFrom<Value, Error = Error>
+    {
+        match self.get_path(path) {
+            Some(value) => T::try_from(value.clone()),
+            None => Err(Error::PathNotFound(path.to_string())),
+        }
+    }
+
+    /// Replaces this value with a copy of `src`, reusing the allocation of
+    /// this value's map, vector or string where both have the same tag type.
+    pub fn copy_from(&mut self, src: &Value) {
+        match (self, src) {
+            (Value::Compound(dst), Value::Compound(src)) => {
+                dst.clear();
+                dst.extend(src.iter().map(|(k, v)| (k.clone(), v.clone())));
+            },
+            (Value::List(dst), Value::List(src)) => dst.clone_from(src),
+            (Value::String(dst), Value::String(src)) => dst.clone_from(src),
+            (Value::ByteArray(dst), Value::ByteArray(src)) => dst.clone_from(src),
+            (Value::IntArray(dst), Value::IntArray(src)) => dst.clone_from(src),
+            (Value::LongArray(dst), Value::LongArray(src)) => dst.clone_from(src),
+            (dst, src) => *dst = src.clone(),
+        }
+    }
+
+    /// Shortens a `List` to its first `len` elements, as `Vec::truncate` does.
+    /// Has no effect on other tags.
+    pub fn truncate_list(&mut self, len: usize) {
+        if let Value::List(ref mut vals) = *self {
+            vals.truncate(len);
+        }
+    }
+
+    /// Appends every element of `iter` to a `List`.
+    ///
+    /// Returns `Error::HeterogeneousList` if any new element has a different
+    /// tag type from the rest of the list, in which case the list is left as
+    /// it was, and `Error::TagMismatch` if this is not a list.
+    pub fn extend_list<I>(&mut self, iter: I) -> Result<()>
+        where I: IntoIterator<Item = Value>
+    {
+        let vals = match *self {
+            Value::List(ref mut vals) => vals,
+            _ => return Err(Error::TagMismatch(self.tag_id(), TagId::List)),
+        };
+        let start = vals.len();
+        vals.extend(iter);
+        if let Some(expected) = vals.first().map(Value::tag_id) {
+            if let Some(i) = vals[start..].iter().position(|v| v.tag_id() != expected) {
+                let got = vals[start + i].tag_id();
+                vals.truncate(start);
+                return Err(Error::HeterogeneousList { index: start + i, expected, got });
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes and returns the last element of a `List`, or returns `None` if
+    /// it is empty or this is another tag.
+    pub fn pop_list(&mut self) -> Option<Value> {
+        match *self {
+            Value::List(ref mut vals) => vals.pop(),
+            _ => None,
+        }
+    }
+
+    /// Checks that every element of a `List` has the same tag type, as the
+    /// NBT format requires, and returns `Error::HeterogeneousList` for the
+    /// first one that does not. Returns `Error::TagMismatch` for other tags.
+    ///
+    /// Lists read from binary data are always homogeneous, since the element
+    /// type is only stored once, but lists built by hand or deserialized from
+    /// other formats need not be.
+    pub fn assert_homogeneous(&self) -> Result<()> {
+        let vals = match *self {
+            Value::List(ref vals) => vals,
+            _ => return Err(Error::TagMismatch(self.tag_id(), TagId::List)),
+        };
+        if let Some(first) = vals.first() {
+            for (index, nbt) in vals.iter().enumerate() {
+                if nbt.tag_id() != first.tag_id() {
+                    return Err(Error::HeterogeneousList { index, expected: first.tag_id(), got: nbt.tag_id() });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes every heterogeneous `List` in this tree (including this value
+    /// itself) writable, by wrapping each of its elements in a compound with
+    /// an empty key, as Minecraft does for mixed lists in SNBT. Homogeneous
+    /// lists are left as they are.
+    ///
+    /// `Value` has no separate variant for mixed lists: lists read from
+    /// binary data can never be mixed, so only lists built by hand or
+    /// deserialized from other formats need wrapping before they are
+    /// written.
+    pub fn wrap_mixed_lists(&mut self) {
+        match *self {
+            Value::List(ref mut vals) => {
+                for val in vals.iter_mut() { val.wrap_mixed_lists(); }
+                if vals.iter().any(|v| v.id() != vals[0].id()) {
+                    for val in vals.iter_mut() {
+                        let mut wrapper = HashMap::with_capacity(1);
+                        wrapper.insert(String::new(), mem::take(val));
+                        *val = Value::Compound(wrapper);
+                    }
+                }
+            },
+            Value::Compound(ref mut vals) => {
+                for val in vals.values_mut() { val.wrap_mixed_lists(); }
+            },
+            _ => (),
+        }
+    }
+
+    /// Reverses `wrap_mixed_lists`, replacing every `List` in this tree whose
+    /// elements are all compounds with only an empty key by a list of their
+    /// values.
+    pub fn unwrap_mixed_lists(&mut self) {
+        match *self {
+            Value::List(ref mut vals) => {
+                let wrapped = !vals.is_empty() && vals.iter().all(|v| match *v {
+                    Value::Compound(ref map) => map.len() == 1 && map.contains_key(""),
+                    _ => false,
+                });
+                if wrapped {
+                    for val in vals.iter_mut() {
+                        if let Value::Compound(ref mut map) = *val {
+                            if let Some(inner) = map.remove("") {
+                                *val = inner;
+                            }
+                        }
+                    }
+                }
+                for val in vals.iter_mut() { val.unwrap_mixed_lists(); }
+            },
+            Value::Compound(ref mut vals) => {
+                for val in vals.values_mut() { val.unwrap_mixed_lists(); }
+            },
+            _ => (),
+        }
+    }
+
+    fn list_element(&self, index: usize) -> Option<&Value> {
+        match *self {
+            Value::List(ref vals) => vals.get(index),
+            _ => None,
+        }
+    }
+
+    list_getters! {
+        get_byte_at, Byte, "TAG_Byte", i8, |v| *v;
+        get_short_at, Short, "TAG_Short", i16, |v| *v;
+        get_int_at, Int, "TAG_Int", i32, |v| *v;
+        get_long_at, Long, "TAG_Long", i64, |v| *v;
+        get_float_at, Float, "TAG_Float", f32, |v| *v;
+        get_double_at, Double, "TAG_Double", f64, |v| *v;
+        get_byte_array_at, ByteArray, "TAG_ByteArray", &[i8], |v| &v[..];
+        get_string_at, String, "TAG_String", &str, |v| &v[..];
+        get_list_at, List, "TAG_List", &[Value], |v| &v[..];
+        get_compound_at, Compound, "TAG_Compound", &HashMap<String, Value>, |v| v;
+        get_int_array_at, IntArray, "TAG_IntArray", &[i32], |v| &v[..];
+        get_long_array_at, LongArray, "TAG_LongArray", &[i64], |v| &v[..];
+    }
+
+    /// Returns a copy of this tree in which the keys of every compound have
+    /// been transformed by `f`, e.g. to convert between naming conventions.
+    ///
+    /// If `f` maps two keys of the same compound to one name, only one of
+    /// their values is kept, and which one is unspecified.
+    pub fn rename_keys_recursive<F>(&self, f: F) -> Value
+        where F: Fn(&str) -> String
+    {
+        self.rename_keys_with(&f)
+    }
+
+    fn rename_keys_with<F>(&self, f: &F) -> Value
+        where F: Fn(&str) -> String
+    {
+        match *self {
+            Value::Compound(ref map) => {
+                Value::Compound(map.iter().map(|(k, v)| (f(k), v.rename_keys_with(f))).collect())
+            },
+            Value::List(ref vals) => {
+                Value::List(vals.iter().map(|v| v.rename_keys_with(f)).collect())
+            },
+            ref other => other.clone(),
+        }
+    }
+
+    /// Consumes a `Compound` and replaces each of its entries with the result
+    /// of calling `f` on the key and the old value. Entries are moved into
+    /// `f` rather than cloned. Other tags are returned unchanged.
+    pub fn map_values<F>(self, f: F) -> Value
+        where F: Fn(&str, Value) -> Value
+    {
+        match self {
+            Value::Compound(map) => {
+                Value::Compound(map.into_iter().map(|(k, v)| {
+                    let v = f(&k, v);
+                    (k, v)
+                }).collect())
+            },
+            other => other,
+        }
+    }
+
+    /// Promotes every `Byte`, `Short`, and `Int` in this tree (including this
+    /// value itself) to a `Long`. Array tags are left as they are.
+    pub fn widen_numeric_tags(&mut self) {
+        match *self {
+            Value::Byte(v)  => *self = Value::Long(v as i64),
+            Value::Short(v) => *self = Value::Long(v as i64),
+            Value::Int(v)   => *self = Value::Long(v as i64),
+            Value::List(ref mut vals) => {
+                for val in vals { val.widen_numeric_tags(); }
+            },
+            Value::Compound(ref mut vals) => {
+                for val in vals.values_mut() { val.widen_numeric_tags(); }
+            },
+            _ => (),
+        }
+    }
+
+    /// Narrows every `Long` in this tree (including this value itself) to an
+    /// `Int`.
+    ///
+    /// If any `Long` does not fit in an `Int`, this returns
+    /// `Error::NumericOutOfRange` and leaves the tree unchanged.
+    pub fn try_narrow_numeric_tags(&mut self) -> Result<()> {
+        self.check_narrowable()?;
+        self.narrow_numeric_tags();
+        Ok(())
+    }
+
+    fn check_narrowable(&self) -> Result<()> {
+        match *self {
+            Value::Long(v) if v < i32::MIN as i64 || v > i32::MAX as i64 =>
+                Err(Error::NumericOutOfRange(v)),
+            Value::List(ref vals) => vals.iter().try_for_each(Value::check_narrowable),
+            Value::Compound(ref vals) => vals.values().try_for_each(Value::check_narrowable),
+            _ => Ok(()),
+        }
+    }
+
+    fn narrow_numeric_tags(&mut self) {
+        match *self {
+            Value::Long(v) => *self = Value::Int(v as i32),
+            Value::List(ref mut vals) => {
+                for val in vals { val.narrow_numeric_tags(); }
+            },
+            Value::Compound(ref mut vals) => {
+                for val in vals.values_mut() { val.narrow_numeric_tags(); }
+            },
+            _ => (),
+        }
+    }
+
+    /// Applies a checked integer operation to an integer tag, keeping the tag
+    /// type of `self`.
+    fn checked_arith(&self, rhs: i64, op: fn(i128, i128) -> Option<i128>, rhs_id: TagId)
+                     -> Result<Value>
+    {
+        let lhs = match *self {
+            Value::Byte(v)  => v as i128,
+            Value::Short(v) => v as i128,
+            Value::Int(v)   => v as i128,
+            Value::Long(v)  => v as i128,
+            _ => return Err(Error::TagMismatch(self.tag_id(), rhs_id)),
+        };
+        let result = op(lhs, rhs as i128).ok_or(Error::ArithmeticOverflow)?;
+        let overflow = |_| Error::ArithmeticOverflow;
+        match *self {
+            Value::Byte(_)  => i8::try_from(result).map(Value::Byte).map_err(overflow),
+            Value::Short(_) => i16::try_from(result).map(Value::Short).map_err(overflow),
+            Value::Int(_)   => i32::try_from(result).map(Value::Int).map_err(overflow),
+            _               => i64::try_from(result).map(Value::Long).map_err(overflow),
+        }
+    }
+
+    /// Adds an integer of tag type `rhs_id` to an integer tag in place, as
+    /// for `Value + Value`.
+    fn add_integer(&mut self, rhs: i64, rhs_id: TagId) -> Result<()> {
+        match *self {
+            Value::Byte(_) | Value::Short(_) | Value::Int(_) | Value::Long(_) => {
+                *self = self.checked_arith(rhs, i128::checked_add, rhs_id)?;
+                Ok(())
+            },
+            _ => Err(Error::UnsupportedOperands(self.tag_id(), rhs_id)),
+        }
+    }
+
+    /// Counts the values of a `Compound` by tag type, without descending into
+    /// nested values. Returns an empty map for other variants.
+    pub fn value_tag_counts(&self) -> HashMap<TagId, usize> {
+        let mut counts = HashMap::new();
+        if let Value::Compound(ref vals) = *self {
+            for val in vals.values() {
+                *counts.entry(val.tag_id()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// Counts every value nested anywhere inside this `Value` (as a compound
+    /// entry or list element) by tag type. The value itself is not counted.
+    pub fn value_tag_counts_recursive(&self) -> HashMap<TagId, usize> {
+        let mut counts = HashMap::new();
+        self.add_nested_tag_counts(&mut counts);
+        counts
+    }
+
+    /// Counts the values with the given tag type in this tree, including this
+    /// value itself.
+    pub fn count_tags(&self, tag: TagId) -> usize {
+        let own = if self.tag_id() == tag { 1 } else { 0 };
+        let nested = match *self {
+            Value::List(ref vals) => vals.iter().map(|v| v.count_tags(tag)).sum(),
+            Value::Compound(ref vals) => vals.values().map(|v| v.count_tags(tag)).sum(),
+            _ => 0,
+        };
+        own + nested
+    }
+
+    /// Counts every value in this tree by tag type, including this value
+    /// itself, unlike `value_tag_counts_recursive`.
+    pub fn count_all_tags(&self) -> HashMap<TagId, usize> {
+        let mut counts = self.value_tag_counts_recursive();
+        *counts.entry(self.tag_id()).or_insert(0) += 1;
+        counts
+    }
+
+    fn add_nested_tag_counts(&self, counts: &mut HashMap<TagId, usize>) {
+        let children: Box<dyn Iterator<Item = &Value>> = match *self {
+            Value::List(ref vals) => Box::new(vals.iter()),
+            Value::Compound(ref vals) => Box::new(vals.values()),
+            _ => return,
+        };
+        for child in children {
+            *counts.entry(child.tag_id()).or_insert(0) += 1;
+            child.add_nested_tag_counts(counts);
+        }
+    }
+
+    /// Writes this `Value` as a complete, gzip-compressed document with an
+    /// empty root name, as used by `.dat` files.
+    pub fn compress_to_gzip(&self) -> Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::Default);
+        encoder.write_u8(self.id())?;
+        raw::write_bare_string(&mut encoder, "")?;
+        self.to_writer(&mut encoder)?;
+        Ok(encoder.finish()?)
+    }
+
+    /// Reads a `Value` from a complete, gzip-compressed document, such as a
+    /// `.dat` file. The root name is ignored.
+    ///
+    /// Returns `Error::NotGzip` if `bytes` is not gzip-compressed.
+    pub fn decompress_from_gzip(bytes: &[u8]) -> Result<Value> {
+        let mut decoder = GzDecoder::new(raw::check_gzip_magic(bytes)?)?;
+        let (tag, _) = raw::emit_next_header(&mut decoder)?;
+        Value::from_reader(tag, &mut decoder)
+    }
+
+    /// Writes the payload of this `Value` to an `io::Write` destination,
+    /// returning the number of bytes written.
+    ///
+    /// If the destination fails partway through, this returns
+    /// `Error::WriteError` with the number of bytes written before then. The
+    /// same holds for the other `to_writer` methods.
+    pub fn to_writer<W>(&self, dst: &mut W) -> Result<usize>
+        where W: io::Write
+    {
+        self.write_counted(dst, None, false)
+    }
+
+    /// Writes the payload of this `Value` to an `io::Write` destination, with
+    /// the entries of every compound written in key order.
+    ///
+    /// Unlike `to_writer`, the output only depends on the contents of this
+    /// `Value` and not on `HashMap` iteration order.
+    pub fn to_writer_sorted<W>(&self, dst: &mut W) -> Result<usize>
+        where W: io::Write
+    {
+        self.to_writer_ordered(dst, order::lexicographic)
+    }
+
+    /// Writes the payload of this `Value` to an `io::Write` destination, with
+    /// the entries of every compound written in the order given by `cmp`.
+    ///
+    /// See the `order` module for some common comparators.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate nbt;
+    /// # fn main() {
+    /// use nbt::order;
+    ///
+    /// let value = compound! { "a" => 1i8, "b" => 2i8 };
+    /// let mut dst = Vec::new();
+    /// value.to_writer_ordered(&mut dst, order::reverse_lexicographic).unwrap();
+    /// assert_eq!(dst, vec![0x01, 0x00, 0x01, b'b', 0x02, 0x01, 0x00, 0x01, b'a', 0x01, 0x00]);
+    /// # }
+    /// ```
+    pub fn to_writer_ordered<W, F>(&self, dst: &mut W, cmp: F) -> Result<usize>
+        where W: io::Write,
+              F: Fn(&str, &str) -> Ordering
+    {
+        self.write_counted(dst, Some(&cmp), false)
+    }
+
+    /// Writes the payload of this `Value` to an `io::Write` destination,
+    /// leaving out every compound entry that is zero or an empty string, and
+    /// returning the number of bytes written.
+    ///
+    /// This saves space when sending sparse data such as entity state over
+    /// the network, but loses the omitted entries: read the data back with
+    /// `from_reader_compact` and a schema to restore them.
+    pub fn to_writer_compact<W>(&self, dst: &mut W) -> Result<usize>
+        where W: io::Write
+    {
+        self.write_counted(dst, None, true)
+    }
+
+    fn write_counted<W>(&self, dst: &mut W, order: KeyOrder, compact: bool) -> Result<usize>
+        where W: io::Write
+    {
+        let mut counter = raw::CountingWriter::new(dst);
+        match self.write_payload(&mut counter, order, compact) {
+            Ok(()) => Ok(counter.count()),
+            Err(Error::IoError(io)) => Err(Error::WriteError { io, bytes_written: counter.count() }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes this `Value` as SNBT, the text format used by Minecraft
+    /// commands, to an `io::Write` destination. The output is prefixed by
+    /// `name` (e.g. `Level: {...}`) unless it is empty.
+    ///
+    /// With an `indent` of zero, everything is written on one line. Otherwise
+    /// each list element and compound entry is written on its own line,
+    /// indented by `indent` spaces per level. Compound entries are sorted by
+    /// key.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate nbt;
+    /// # fn main() {
+    /// let value = compound! { "id" => "minecraft:stone", "Count" => 1i8 };
+    /// let mut dst = Vec::new();
+    /// value.to_writer_pretty_snbt(&mut dst, "", 0).unwrap();
+    /// assert_eq!(String::from_utf8(dst).unwrap(), r#"{Count: 1b, id: "minecraft:stone"}"#);
+    /// # }
+    /// ```
+    pub fn to_writer_pretty_snbt<W>(&self, dst: &mut W, name: &str, indent: usize) -> Result<()>
+        where W: io::Write
+    {
+        if !name.is_empty() {
+            write_snbt_key(dst, name)?;
+            dst.write_all(b": ")?;
+        }
+        self.write_snbt(dst, indent, 0)
+    }
+
+    fn write_snbt<W>(&self, dst: &mut W, indent: usize, depth: usize) -> Result<()>
+        where W: io::Write
+    {
+        // Starts a new element, with a separator if it is not the first.
+        let next = |dst: &mut W, first: bool, depth: usize| -> Result<()> {
+            if !first {
+                dst.write_all(b",")?;
+            }
+            if indent > 0 {
+                write!(dst, "\n{:width$}", "", width = indent * depth)?;
+            } else if !first {
+                dst.write_all(b" ")?;
+            }
+            Ok(())
+        };
+
         match *self {
-            Value::Byte(_)      => "TAG_Byte",
-            Value::Short(_)     => "TAG_Short",
-            Value::Int(_)       => "TAG_Int",
-            Value::Long(_)      => "TAG_Long",
-            Value::Float(_)     => "TAG_Float",
-            Value::Double(_)    => "TAG_Double",
-            Value::ByteArray(_) => "TAG_ByteArray",
-            Value::String(_)    => "TAG_String",
-            Value::List(_)      => "TAG_List",
-            Value::Compound(_)  => "TAG_Compound",
-            Value::IntArray(_)  => "TAG_IntArray",
-            Value::LongArray(_) => "TAG_LongArray",
+            Value::Byte(v)   => write!(dst, "{}b", v)?,
+            Value::Short(v)  => write!(dst, "{}s", v)?,
+            Value::Int(v)    => write!(dst, "{}", v)?,
+            Value::Long(v)   => write!(dst, "{}L", v)?,
+            Value::Float(_)  => write!(dst, "{}f", self.coerce_string().unwrap_or_default())?,
+            Value::Double(_) => write!(dst, "{}d", self.coerce_string().unwrap_or_default())?,
+            Value::String(ref v) => write_snbt_string(dst, v)?,
+            Value::ByteArray(ref v) => write_snbt_array(dst, "B", v.iter().map(|b| format!("{}b", b)))?,
+            Value::IntArray(ref v) => write_snbt_array(dst, "I", v.iter().map(|i| i.to_string()))?,
+            Value::LongArray(ref v) => write_snbt_array(dst, "L", v.iter().map(|l| format!("{}L", l)))?,
+            Value::List(ref vals) => {
+                dst.write_all(b"[")?;
+                for (i, val) in vals.iter().enumerate() {
+                    next(dst, i == 0, depth + 1)?;
+                    val.write_snbt(dst, indent, depth + 1)?;
+                }
+                if !vals.is_empty() && indent > 0 {
+                    write!(dst, "\n{:width$}", "", width = indent * depth)?;
+                }
+                dst.write_all(b"]")?;
+            },
+            Value::Compound(ref map) => {
+                let entries = sorted_entries(map);
+                dst.write_all(b"{")?;
+                for (i, &(key, val)) in entries.iter().enumerate() {
+                    next(dst, i == 0, depth + 1)?;
+                    write_snbt_key(dst, key)?;
+                    dst.write_all(b": ")?;
+                    val.write_snbt(dst, indent, depth + 1)?;
+                }
+                if !entries.is_empty() && indent > 0 {
+                    write!(dst, "\n{:width$}", "", width = indent * depth)?;
+                }
+                dst.write_all(b"}")?;
+            },
         }
+        Ok(())
     }
 
-    /// Writes the payload of this `Value` to an `io::Write` destination.
-    pub fn to_writer<W>(&self, mut dst: &mut W) -> Result<()>
+    /// Writes the payload of this `Value`, with compound entries sorted by
+    /// `order` if given, or in `HashMap` iteration order otherwise.
+    fn write_payload<W>(&self, mut dst: &mut W, order: KeyOrder, compact: bool) -> Result<()>
         where W: io::Write
     {
         match *self {
@@ -89,22 +1386,32 @@ impl Value {
                     let first_id = vals[0].id();
                     try!(dst.write_u8(first_id));
                     try!(dst.write_i32::<BigEndian>(vals.len() as i32));
+                    // Ensure that all of the tags are the same type.
+                    self.assert_homogeneous()?;
                     for nbt in vals {
-                        // Ensure that all of the tags are the same type.
-                        if nbt.id() != first_id {
-                            return Err(Error::HeterogeneousList);
-                        }
-                        try!(nbt.to_writer(dst));
+                        nbt.write_payload(dst, order, compact)?;
                     }
                 }
                 Ok(())
             },
             Value::Compound(ref vals)  => {
-                for (name, ref nbt) in vals {
+                let write_entry = |dst: &mut W, name: &str, nbt: &Value| -> Result<()> {
                     // Write the header for the tag.
                     dst.write_u8(nbt.id())?;
                     raw::write_bare_string(dst, name)?;
-                    try!(nbt.to_writer(dst));
+                    nbt.write_payload(dst, order, compact)
+                };
+                let entries = vals.iter().filter(|&(_, nbt)| !(compact && nbt.is_compact_default()));
+                if let Some(cmp) = order {
+                    let mut entries: Vec<_> = entries.collect();
+                    entries.sort_by(|a, b| cmp(a.0, b.0));
+                    for (name, nbt) in entries {
+                        write_entry(dst, name, nbt)?;
+                    }
+                } else {
+                    for (name, nbt) in entries {
+                        write_entry(dst, name, nbt)?;
+                    }
                 }
                 raw::close_nbt(&mut dst)
             },
@@ -113,43 +1420,324 @@ impl Value {
         }
     }
 
-    /// Reads the payload of an `Value` with a given type ID from an
-    /// `io::Read` source.
-    pub fn from_reader<R>(id: u8, src: &mut R) -> Result<Value>
+    /// Whether `to_writer_compact` leaves this value out of compounds.
+    fn is_compact_default(&self) -> bool {
+        match *self {
+            Value::Byte(v) => v == 0,
+            Value::Short(v) => v == 0,
+            Value::Int(v) => v == 0,
+            Value::Long(v) => v == 0,
+            // Negative zero is written, since it would be restored as zero.
+            Value::Float(v) => v.to_bits() == 0,
+            Value::Double(v) => v.to_bits() == 0,
+            Value::String(ref v) => v.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Reads a payload written by `to_writer_compact`, restoring the entries
+    /// it left out from `schema`, a value with the same structure such as a
+    /// prototype of the data being sent.
+    ///
+    /// Each entry of a schema compound that a compound read here lacks is
+    /// filled in with zero (or an empty string) of the schema entry's tag, if
+    /// it is a number or string. Compounds in lists are restored from the
+    /// first element of the schema's list.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate nbt;
+    /// # fn main() {
+    /// use nbt::{TagId, Value};
+    ///
+    /// let schema = compound! { "Health" => 0.0f32, "Fire" => 0i16, "CustomName" => "" };
+    /// let entity = compound! { "Health" => 20.0f32, "Fire" => 0i16, "CustomName" => "" };
+    ///
+    /// let mut dst = Vec::new();
+    /// entity.to_writer_compact(&mut dst).unwrap();
+    /// assert_eq!(dst.len(), 1 + 2 + 6 + 4 + 1);
+    ///
+    /// let read = Value::from_reader_compact(TagId::Compound, &mut &dst[..], &schema).unwrap();
+    /// assert_eq!(read, entity);
+    /// # }
+    /// ```
+    pub fn from_reader_compact<R>(id: TagId, src: &mut R, schema: &Value) -> Result<Value>
+        where R: io::Read
+    {
+        let mut value = Value::from_reader(id, src)?;
+        value.restore_compact_defaults(schema);
+        Ok(value)
+    }
+
+    fn restore_compact_defaults(&mut self, schema: &Value) {
+        match (self, schema) {
+            (Value::Compound(map), Value::Compound(schema)) => {
+                for (key, prototype) in schema {
+                    if let Some(value) = map.get_mut(key) {
+                        value.restore_compact_defaults(prototype);
+                        continue;
+                    }
+                    let zero = match *prototype {
+                        Value::Byte(_) => Value::Byte(0),
+                        Value::Short(_) => Value::Short(0),
+                        Value::Int(_) => Value::Int(0),
+                        Value::Long(_) => Value::Long(0),
+                        Value::Float(_) => Value::Float(0.0),
+                        Value::Double(_) => Value::Double(0.0),
+                        Value::String(_) => Value::String(String::new()),
+                        _ => continue,
+                    };
+                    map.insert(key.clone(), zero);
+                }
+            },
+            (Value::List(vals), Value::List(schema)) => {
+                if let Some(prototype) = schema.first() {
+                    for val in vals {
+                        val.restore_compact_defaults(prototype);
+                    }
+                }
+            },
+            _ => (),
+        }
+    }
+
+    /// Reads the payload of an `Value` with a given type from an `io::Read`
+    /// source.
+    ///
+    /// Values are read a few bytes at a time, so unbuffered sources such as
+    /// a `File` or `TcpStream` should be wrapped in an `io::BufReader`.
+    pub fn from_reader<R>(id: TagId, src: &mut R) -> Result<Value>
+        where R: io::Read
+    {
+        Value::from_reader_with_config(id, src, &ParseConfig::default())
+    }
+
+    /// Reads the payload of a value whose tag type is given as a raw byte,
+    /// for formats that store a bare list or array with no header before it.
+    /// This is `from_reader` for callers that have not decoded the tag type.
+    ///
+    /// Returns `Error::InvalidTypeId` if `tag_id` is not a known tag type, or
+    /// is `TAG_End`, which has no payload.
+    ///
+    /// ```rust
+    /// use nbt::Value;
+    ///
+    /// let bytes = vec![0x01, 0x00, 0x00, 0x00, 0x02, 0x05, 0x06];
+    /// assert_eq!(Value::from_reader_bare(0x09, &mut &bytes[..]).unwrap(),
+    ///            Value::List(vec![Value::Byte(5), Value::Byte(6)]));
+    /// ```
+    pub fn from_reader_bare<R>(tag_id: u8, src: &mut R) -> Result<Value>
+        where R: io::Read
+    {
+        Value::from_reader(TagId::try_from(tag_id)?, src)
+    }
+
+    /// Reads the payload of an `Value` with a given type from an `io::Read`
+    /// source, using the given `ParseConfig`.
+    pub fn from_reader_with_config<R>(id: TagId, src: &mut R, config: &ParseConfig)
+                                      -> Result<Value>
+        where R: io::Read
+    {
+        Value::read_nested(id, src, config, 1)
+    }
+
+    /// Reads a complete document, i.e. the header and payload of its root,
+    /// from an `io::Read` source, consuming at most `max_bytes` bytes. Returns
+    /// the name of the root, the root itself, and the number of bytes read.
+    ///
+    /// If the limit is reached in the middle of a compound or list, this
+    /// returns `Error::Truncated` with the entries and elements that had been
+    /// read completely. Entries and elements are always complete, but may
+    /// themselves be partially read compounds and lists.
+    ///
+    /// ```rust
+    /// use nbt::{Error, Value};
+    ///
+    /// // A root compound with the entries "a" and "b", cut off inside "b".
+    /// let bytes = vec![
+    ///     0x0a, 0x00, 0x00,
+    ///     0x01, 0x00, 0x01, b'a', 0x01,
+    ///     0x03, 0x00, 0x01, b'b', 0x00, 0x00, 0x00, 0x02,
+    ///     0x00,
+    /// ];
+    /// match Value::from_reader_partial(&bytes[..], 10) {
+    ///     Err(Error::Truncated { partial, bytes_read }) => {
+    ///         assert_eq!(partial, Value::Compound(vec![("a".to_string(), Value::Byte(1))].into_iter().collect()));
+    ///         assert_eq!(bytes_read, 10);
+    ///     },
+    ///     other => panic!("{:?}", other),
+    /// }
+    /// ```
+    pub fn from_reader_partial<R>(src: R, max_bytes: usize) -> Result<(String, Value, usize)>
+        where R: io::Read
+    {
+        let mut src = src.take(max_bytes as u64);
+        let (id, name) = raw::emit_next_header(&mut src)?;
+        let result = Value::read_partial(id, &mut src);
+        let bytes_read = max_bytes - src.limit() as usize;
+        match result {
+            Ok(value) => Ok((name, value, bytes_read)),
+            Err((_, Some(partial))) if src.limit() == 0 => Err(Error::Truncated { partial, bytes_read }),
+            Err((e, _)) => Err(e),
+        }
+    }
+
+    /// Reads a payload as for `from_reader`, but on failure also returns the
+    /// partially read compound or list, if any.
+    fn read_partial<R>(id: TagId, src: &mut R) -> StdResult<Value, (Error, Option<Value>)>
         where R: io::Read
     {
         match id {
-            0x01 => Ok(Value::Byte(raw::read_bare_byte(src)?)),
-            0x02 => Ok(Value::Short(raw::read_bare_short(src)?)),
-            0x03 => Ok(Value::Int(raw::read_bare_int(src)?)),
-            0x04 => Ok(Value::Long(raw::read_bare_long(src)?)),
-            0x05 => Ok(Value::Float(raw::read_bare_float(src)?)),
-            0x06 => Ok(Value::Double(raw::read_bare_double(src)?)),
-            0x07 => Ok(Value::ByteArray(raw::read_bare_byte_array(src)?)),
-            0x08 => Ok(Value::String(raw::read_bare_string(src)?)),
-            0x09 => { // List
-                let id = try!(src.read_u8());
-                let len = try!(src.read_i32::<BigEndian>()) as usize;
-                let mut buf = Vec::with_capacity(len);
+            TagId::List => {
+                let header = src.read_u8().map_err(Error::from)
+                    .and_then(TagId::try_from)
+                    .and_then(|id| Ok((id, src.read_i32::<BigEndian>()?.max(0) as usize)));
+                let (id, len) = header.map_err(|e| (e, Some(Value::List(Vec::new()))))?;
+                let mut buf = Vec::with_capacity(len.min(MAX_LIST_PREALLOCATION));
                 for _ in 0..len {
-                    buf.push(try!(Value::from_reader(id, src)));
+                    match Value::read_partial(id, src) {
+                        Ok(tag) => buf.push(tag),
+                        Err((e, partial)) => {
+                            buf.extend(partial);
+                            return Err((e, Some(Value::List(buf))));
+                        },
+                    }
+                }
+                Ok(Value::List(buf))
+            },
+            TagId::Compound => {
+                let mut buf = HashMap::new();
+                loop {
+                    let (id, name) = match raw::emit_next_header(src) {
+                        Ok(header) => header,
+                        Err(e) => return Err((e, Some(Value::Compound(buf)))),
+                    };
+                    if id == TagId::End { break; }
+                    match Value::read_partial(id, src) {
+                        Ok(tag) => { buf.insert(name, tag); },
+                        Err((e, partial)) => {
+                            if let Some(partial) = partial {
+                                buf.insert(name, partial);
+                            }
+                            return Err((e, Some(Value::Compound(buf))));
+                        },
+                    }
+                }
+                Ok(Value::Compound(buf))
+            },
+            _ => Value::from_reader(id, src).map_err(|e| (e, None)),
+        }
+    }
+
+    /// Reads a payload as for `from_reader_with_config`, where `depth` is the
+    /// nesting level that a compound or list read here would have.
+    fn read_nested<R>(id: TagId, src: &mut R, config: &ParseConfig, depth: usize)
+                      -> Result<Value>
+        where R: io::Read
+    {
+        match id {
+            TagId::Byte => Ok(Value::Byte(raw::read_bare_byte(src)?)),
+            TagId::Short => Ok(Value::Short(raw::read_bare_short(src)?)),
+            TagId::Int => Ok(Value::Int(raw::read_bare_int(src)?)),
+            TagId::Long => Ok(Value::Long(raw::read_bare_long(src)?)),
+            TagId::Float => Ok(Value::Float(raw::read_bare_float(src)?)),
+            TagId::Double => Ok(Value::Double(raw::read_bare_double(src)?)),
+            TagId::ByteArray => Ok(Value::ByteArray(raw::read_bare_byte_array(src, config.max_array_len)?)),
+            TagId::String => Ok(Value::String(raw::read_bare_string(src)?)),
+            TagId::List => {
+                raw::check_depth(depth, config.max_depth)?;
+                let id = TagId::try_from(src.read_u8()?)?;
+                // As for arrays, negative lengths are read as empty lists.
+                let len = src.read_i32::<BigEndian>()?.max(0) as usize;
+                raw::check_len(len, config.max_array_len)?;
+                // Trust the length prefix for preallocation only up to a
+                // point, so that a corrupt one cannot exhaust memory.
+                let mut buf = Vec::with_capacity(len.min(MAX_LIST_PREALLOCATION));
+                for i in 0..len {
+                    let elem = Value::read_nested(id, src, config, depth + 1)
+                        .map_err(|e| within(e, config, || i.to_string()))?;
+                    buf.push(elem);
                 }
                 Ok(Value::List(buf))
             },
-            0x0a => { // Compound
+            TagId::Compound => {
+                raw::check_depth(depth, config.max_depth)?;
                 let mut buf = HashMap::new();
                 loop {
                     let (id, name) = try!(raw::emit_next_header(src));
-                    if id == 0x00 { break; }
-                    let tag = try!(Value::from_reader(id, src));
+                    if id == TagId::End { break; }
+                    if config.reject_duplicate_keys && buf.contains_key(&name) {
+                        return Err(Error::DuplicateKey(name));
+                    }
+                    let tag = Value::read_nested(id, src, config, depth + 1)
+                        .map_err(|e| within(e, config, || name.clone()))?;
                     buf.insert(name, tag);
                 }
                 Ok(Value::Compound(buf))
             },
-            0x0b => Ok(Value::IntArray(raw::read_bare_int_array(src)?)),
-            0x0c => Ok(Value::LongArray(raw::read_bare_long_array(src)?)),
-            e => Err(Error::InvalidTypeId(e))
+            TagId::IntArray => Ok(Value::IntArray(raw::read_bare_int_array(src, config.max_array_len)?)),
+            TagId::LongArray => Ok(Value::LongArray(raw::read_bare_long_array(src, config.max_array_len)?)),
+            TagId::End => Err(Error::InvalidTypeId(0x00)),
+        }
+    }
+
+    /// Renders this `Value` as an indented tree in the style of NBTExplorer
+    /// and the original NBT specification, e.g.
+    ///
+    /// ```text
+    /// TAG_Compound(''): 1 entry
+    /// {
+    ///   TAG_Int('x'): 5
+    /// }
+    /// ```
+    ///
+    /// The root is given an empty name. Compound entries are sorted by key, so
+    /// the output is suitable for comparing against test fixtures.
+    pub fn to_nbt_tree_string(&self) -> String {
+        let mut out = String::new();
+        self.write_tree(&mut out, Some(""), 0)
+            .expect("writing to a String cannot fail");
+        out
+    }
+
+    fn write_tree(&self, out: &mut String, name: Option<&str>, indent: usize) -> fmt::Result {
+        use std::fmt::Write;
+
+        let label = match name {
+            Some(name) => format!("'{}'", name),
+            None => "None".to_string(),
+        };
+        let entries = |n: usize| if n == 1 { "1 entry".to_string() } else { format!("{} entries", n) };
+        write!(out, "{:width$}{}({}): ", "", self.tag_name(), label, width = indent)?;
+        match *self {
+            Value::Byte(v)   => write!(out, "{}", v)?,
+            Value::Short(v)  => write!(out, "{}", v)?,
+            Value::Int(v)    => write!(out, "{}", v)?,
+            Value::Long(v)   => write!(out, "{}", v)?,
+            Value::Float(v)  => write!(out, "{}", v)?,
+            Value::Double(v) => write!(out, "{}", v)?,
+            Value::String(ref v) => out.write_str(v)?,
+            Value::ByteArray(ref v) => write!(out, "[{} bytes]", v.len())?,
+            Value::IntArray(ref v) => write!(out, "[{} ints]", v.len())?,
+            Value::LongArray(ref v) => write!(out, "[{} longs]", v.len())?,
+            Value::List(ref v) => {
+                write!(out, "{}\n{:width$}{{\n", entries(v.len()), "", width = indent)?;
+                for tag in v {
+                    tag.write_tree(out, None, indent + 2)?;
+                    out.push('\n');
+                }
+                write!(out, "{:width$}}}", "", width = indent)?;
+            },
+            Value::Compound(ref v) => {
+                let sorted = sorted_entries(v);
+                write!(out, "{}\n{:width$}{{\n", entries(v.len()), "", width = indent)?;
+                for (name, tag) in sorted {
+                    tag.write_tree(out, Some(name), indent + 2)?;
+                    out.push('\n');
+                }
+                write!(out, "{:width$}}}", "", width = indent)?;
+            },
         }
+        Ok(())
     }
 
     pub fn print(&self, f: &mut fmt::Formatter, offset: usize) -> fmt::Result {
@@ -192,12 +1780,379 @@ impl Value {
     }
 }
 
+/// Debug output annotates every value with its tag name, e.g.
+/// `TAG_Compound { "x": TAG_Int(1) }`. Compound entries are sorted by key so
+/// that the output is deterministic.
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Byte(v)   => write!(f, "TAG_Byte({:?})", v),
+            Value::Short(v)  => write!(f, "TAG_Short({:?})", v),
+            Value::Int(v)    => write!(f, "TAG_Int({:?})", v),
+            Value::Long(v)   => write!(f, "TAG_Long({:?})", v),
+            Value::Float(v)  => write!(f, "TAG_Float({:?})", v),
+            Value::Double(v) => write!(f, "TAG_Double({:?})", v),
+            Value::ByteArray(ref v) => write!(f, "TAG_ByteArray({:?})", v),
+            Value::String(ref v) => write!(f, "TAG_String({:?})", v),
+            Value::IntArray(ref v) => write!(f, "TAG_IntArray({:?})", v),
+            Value::LongArray(ref v) => write!(f, "TAG_LongArray({:?})", v),
+            Value::List(ref v) => {
+                write!(f, "TAG_List ")?;
+                f.debug_list().entries(v.iter()).finish()
+            },
+            Value::Compound(ref v) => {
+                let entries = sorted_entries(v);
+                write!(f, "TAG_Compound ")?;
+                if f.alternate() || entries.is_empty() {
+                    return f.debug_map().entries(entries).finish();
+                }
+                write!(f, "{{ ")?;
+                for (i, (name, tag)) in entries.into_iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{:?}: {:?}", name, tag)?;
+                }
+                write!(f, " }}")
+            },
+        }
+    }
+}
+
+/// An optional comparator for the keys of compounds being written.
+type KeyOrder<'a> = Option<&'a dyn Fn(&str, &str) -> Ordering>;
+
+/// Prefixes the path of an error raised while reading the entry or element
+/// `segment`, if `ParseConfig::track_paths` is set.
+fn within<F>(e: Error, config: &ParseConfig, segment: F) -> Error
+    where F: FnOnce() -> String
+{
+    if !config.track_paths {
+        return e;
+    }
+    match e {
+        Error::AtPath(path, e) => Error::AtPath(format!("{}.{}", segment(), path), e),
+        e => Error::AtPath(segment(), Box::new(e)),
+    }
+}
+
+/// The entries of a compound, in key order.
+pub fn sorted_entries(map: &HashMap<String, Value>) -> Vec<(&String, &Value)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+}
+
+/// `Value`s have a total order, so that lists of them can be sorted
+/// deterministically.
+///
+/// Numeric tags are ordered by their value: integers are widened to `i64`,
+/// and compared with floats as `f64` using `f64::total_cmp`. Ties between
+/// different numeric tags are broken by tag ID. Strings, arrays and lists are
+/// ordered lexicographically, and compounds by their entries in key order.
+/// Any other pair of tag types is ordered by tag ID.
+///
+/// Values of the same floating-point tag are compared with `total_cmp`, so
+/// this agrees with `PartialEq`: `NaN` equals itself, and `-0.0` is ordered
+/// before `0.0`.
+impl Ord for Value {
+    fn cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Float(a), Value::Float(b)) => a.total_cmp(b),
+            (Value::Double(a), Value::Double(b)) => a.total_cmp(b),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::ByteArray(a), Value::ByteArray(b)) => a.cmp(b),
+            (Value::IntArray(a), Value::IntArray(b)) => a.cmp(b),
+            (Value::LongArray(a), Value::LongArray(b)) => a.cmp(b),
+            (Value::List(a), Value::List(b)) => a.cmp(b),
+            (Value::Compound(a), Value::Compound(b)) => sorted_entries(a).cmp(&sorted_entries(b)),
+            _ => numeric_cmp(self, other)
+                .unwrap_or(Ordering::Equal)
+                .then(self.id().cmp(&other.id())),
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Values are equal if they have the same tag and payload. Floats are
+/// compared bit for bit, as they are stored, rather than with `==`: `NaN`
+/// equals itself, and `0.0` and `-0.0` are distinct. This makes equality
+/// reflexive, as `Eq` requires, and consistent with `Ord`.
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Byte(a), Value::Byte(b)) => a == b,
+            (Value::Short(a), Value::Short(b)) => a == b,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Long(a), Value::Long(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+            (Value::Double(a), Value::Double(b)) => a.to_bits() == b.to_bits(),
+            (Value::ByteArray(a), Value::ByteArray(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::List(a), Value::List(b)) => a == b,
+            (Value::Compound(a), Value::Compound(b)) => a == b,
+            (Value::IntArray(a), Value::IntArray(b)) => a == b,
+            (Value::LongArray(a), Value::LongArray(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+/// Writes a compound key for SNBT, quoting it unless it only contains
+/// characters allowed in unquoted keys.
+fn write_snbt_key<W: io::Write>(dst: &mut W, key: &str) -> io::Result<()> {
+    let bare = !key.is_empty() && key.chars().all(|c| {
+        c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' || c == '+'
+    });
+    if bare {
+        dst.write_all(key.as_bytes())
+    } else {
+        write_snbt_string(dst, key)
+    }
+}
+
+/// Writes a double-quoted SNBT string, escaping quotes and backslashes.
+fn write_snbt_string<W: io::Write>(dst: &mut W, s: &str) -> io::Result<()> {
+    dst.write_all(b"\"")?;
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            dst.write_all(b"\\")?;
+        }
+        write!(dst, "{}", c)?;
+    }
+    dst.write_all(b"\"")
+}
+
+/// Writes an SNBT array such as `[I; 1, 2]`.
+fn write_snbt_array<W, I>(dst: &mut W, prefix: &str, elements: I) -> io::Result<()>
+    where W: io::Write, I: Iterator<Item = String>
+{
+    write!(dst, "[{};", prefix)?;
+    for (i, element) in elements.enumerate() {
+        dst.write_all(if i == 0 { b" " } else { b", " })?;
+        dst.write_all(element.as_bytes())?;
+    }
+    dst.write_all(b"]")
+}
+
+/// Compares two numeric values, or returns `None` if either is not numeric.
+fn numeric_cmp(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a.as_i64_checked(), b.as_i64_checked()) {
+        (Some(a), Some(b)) => Some(a.cmp(&b)),
+        _ => match (a.as_f64_lossy(), b.as_f64_lossy()) {
+            (Some(a), Some(b)) => Some(a.total_cmp(&b)),
+            _ => None,
+        },
+    }
+}
+
+/// A consuming iterator over the elements of a `List` or array tag, created
+/// by `Value::into_iter`. Array elements are yielded as `Byte`, `Int`, or
+/// `Long` values.
+#[derive(Debug)]
+pub struct NbtListIntoIter {
+    inner: IntoIterInner,
+}
+
+#[derive(Debug)]
+enum IntoIterInner {
+    List(vec::IntoIter<Value>),
+    Bytes(vec::IntoIter<i8>),
+    Ints(vec::IntoIter<i32>),
+    Longs(vec::IntoIter<i64>),
+}
+
+impl Iterator for NbtListIntoIter {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        match self.inner {
+            IntoIterInner::List(ref mut it) => it.next(),
+            IntoIterInner::Bytes(ref mut it) => it.next().map(Value::Byte),
+            IntoIterInner::Ints(ref mut it) => it.next().map(Value::Int),
+            IntoIterInner::Longs(ref mut it) => it.next().map(Value::Long),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.inner {
+            IntoIterInner::List(ref it) => it.size_hint(),
+            IntoIterInner::Bytes(ref it) => it.size_hint(),
+            IntoIterInner::Ints(ref it) => it.size_hint(),
+            IntoIterInner::Longs(ref it) => it.size_hint(),
+        }
+    }
+}
+
+impl ExactSizeIterator for NbtListIntoIter {}
+
+/// Iterates over the elements of a `List`, `ByteArray`, `IntArray`, or
+/// `LongArray`.
+///
+/// # Panics
+///
+/// Panics if this is any other tag.
+impl IntoIterator for Value {
+    type Item = Value;
+    type IntoIter = NbtListIntoIter;
+
+    fn into_iter(self) -> NbtListIntoIter {
+        let inner = match self {
+            Value::List(vals) => IntoIterInner::List(vals.into_iter()),
+            Value::ByteArray(vals) => IntoIterInner::Bytes(vals.into_iter()),
+            Value::IntArray(vals) => IntoIterInner::Ints(vals.into_iter()),
+            Value::LongArray(vals) => IntoIterInner::Longs(vals.into_iter()),
+            other => panic!("cannot iterate over a {}", other.tag_name()),
+        };
+        NbtListIntoIter { inner }
+    }
+}
+
+/// Iterates over references to the elements of a `List`.
+///
+/// The elements of arrays are not stored as `Value`s, so they can only be
+/// iterated over by value.
+///
+/// # Panics
+///
+/// Panics if this is not a `List`.
+impl<'a> IntoIterator for &'a Value {
+    type Item = &'a Value;
+    type IntoIter = slice::Iter<'a, Value>;
+
+    fn into_iter(self) -> slice::Iter<'a, Value> {
+        match *self {
+            Value::List(ref vals) => vals.iter(),
+            ref other => panic!("cannot iterate over references into a {}", other.tag_name()),
+        }
+    }
+}
+
+/// The default `Value` is an empty `Compound`.
+impl Default for Value {
+    fn default() -> Value {
+        Value::Compound(HashMap::new())
+    }
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.print(f, 0)
     }
 }
 
+/// Implements checked arithmetic with `i32` and `i64` operands for the integer
+/// tags. The result keeps the tag type of the left-hand side, and is
+/// `Err(Error::ArithmeticOverflow)` if it would not fit in that type (or on
+/// division by zero). Non-integer tags produce `Error::TagMismatch`.
+macro_rules! impl_checked_arith {
+    ($($op:ident, $method:ident, $checked:ident;)*) => {
+        $(
+            impl ops::$op<i32> for Value {
+                type Output = Result<Value>;
+
+                fn $method(self, rhs: i32) -> Result<Value> {
+                    self.checked_arith(rhs as i64, i128::$checked, TagId::Int)
+                }
+            }
+
+            impl ops::$op<i64> for Value {
+                type Output = Result<Value>;
+
+                fn $method(self, rhs: i64) -> Result<Value> {
+                    self.checked_arith(rhs, i128::$checked, TagId::Long)
+                }
+            }
+        )*
+    };
+}
+
+impl_checked_arith! {
+    Add, add, checked_add;
+    Sub, sub, checked_sub;
+    Mul, mul, checked_mul;
+    Div, div, checked_div;
+}
+
+/// Adds an integer tag to another as for `+` with an `i32` or `i64` operand,
+/// concatenates two lists or two arrays of the same type, or merges two
+/// compounds, with the entries of the right-hand side replacing those of the
+/// left-hand side.
+///
+/// Returns `Error::UnsupportedOperands` for any other combination of tags,
+/// and `Error::HeterogeneousList` if two non-empty lists have different element
+/// types. Integer sums are checked as for `+` with an integer operand.
+impl ops::Add<Value> for Value {
+    type Output = Result<Value>;
+
+    fn add(mut self, rhs: Value) -> Result<Value> {
+        add_in_place(&mut self, rhs)?;
+        Ok(self)
+    }
+}
+
+/// Concatenates or merges in place, as for `+`.
+///
+/// # Panics
+///
+/// Panics if `+` would return an error, in which case `self` is left
+/// unchanged.
+impl ops::AddAssign<Value> for Value {
+    fn add_assign(&mut self, rhs: Value) {
+        if let Err(e) = add_in_place(self, rhs) {
+            panic!("cannot add values: {}", e);
+        }
+    }
+}
+
+/// Adds `rhs` to `lhs` as for `+`, leaving `lhs` unchanged on error.
+fn add_in_place(lhs: &mut Value, rhs: Value) -> Result<()> {
+    let rhs = match rhs {
+        Value::Byte(v)  => return lhs.add_integer(v as i64, TagId::Byte),
+        Value::Short(v) => return lhs.add_integer(v as i64, TagId::Short),
+        Value::Int(v)   => return lhs.add_integer(v as i64, TagId::Int),
+        Value::Long(v)  => return lhs.add_integer(v, TagId::Long),
+        rhs => rhs,
+    };
+    match (lhs, rhs) {
+        (&mut Value::List(ref mut a), Value::List(b)) => {
+            let start = a.len();
+            a.extend(b);
+            // Check the whole list, as `Value::assert_homogeneous` does.
+            if let Some(first) = a.first().map(Value::tag_id) {
+                if let Some(index) = a.iter().position(|v| v.tag_id() != first) {
+                    let got = a[index].tag_id();
+                    a.truncate(start);
+                    return Err(Error::HeterogeneousList { index, expected: first, got });
+                }
+            }
+            Ok(())
+        },
+        (&mut Value::ByteArray(ref mut a), Value::ByteArray(b)) => {
+            a.extend(b);
+            Ok(())
+        },
+        (&mut Value::IntArray(ref mut a), Value::IntArray(b)) => {
+            a.extend(b);
+            Ok(())
+        },
+        (&mut Value::LongArray(ref mut a), Value::LongArray(b)) => {
+            a.extend(b);
+            Ok(())
+        },
+        (&mut Value::Compound(ref mut a), Value::Compound(b)) => {
+            a.extend(b);
+            Ok(())
+        },
+        (a, b) => Err(Error::UnsupportedOperands(a.tag_id(), b.tag_id())),
+    }
+}
+
 impl From<i8> for Value {
     fn from(t: i8) -> Value { Value::Byte(t) }
 }
@@ -253,3 +2208,148 @@ impl From<Vec<i64>> for Value {
 impl<'a> From<&'a [i64]> for Value {
     fn from(t: &'a [i64]) -> Value { Value::LongArray(t.into()) }
 }
+
+/// The element type of a list is that of its first element, so the vector
+/// is not checked for homogeneity until the list is written.
+impl From<Vec<Value>> for Value {
+    fn from(t: Vec<Value>) -> Value { Value::List(t) }
+}
+
+impl From<HashMap<String, Value>> for Value {
+    fn from(t: HashMap<String, Value>) -> Value { Value::Compound(t) }
+}
+
+impl From<bool> for Value {
+    fn from(t: bool) -> Value { Value::Byte(t as i8) }
+}
+
+/// Booleans are stored as a `Byte` of `0` or `1`. Other bytes produce
+/// `Error::NonBooleanByte`, and other tags `Error::TagMismatch`.
+impl TryFrom<Value> for bool {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<bool> {
+        match value {
+            Value::Byte(0) => Ok(false),
+            Value::Byte(1) => Ok(true),
+            Value::Byte(b) => Err(Error::NonBooleanByte(b)),
+            other => Err(Error::TagMismatch(other.tag_id(), TagId::Byte)),
+        }
+    }
+}
+
+/// Implements `TryFrom<Value>` for the Rust type held by each tag, producing
+/// `Error::TagMismatch` for any other tag.
+macro_rules! impl_try_from_value {
+    ($($t:ty, $variant:ident, $id:expr;)*) => {
+        $(
+            impl TryFrom<Value> for $t {
+                type Error = Error;
+
+                fn try_from(value: Value) -> Result<$t> {
+                    match value {
+                        Value::$variant(v) => Ok(v),
+                        other => Err(Error::TagMismatch(other.tag_id(), $id)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from_value! {
+    i8, Byte, TagId::Byte;
+    i16, Short, TagId::Short;
+    i32, Int, TagId::Int;
+    i64, Long, TagId::Long;
+    f32, Float, TagId::Float;
+    f64, Double, TagId::Double;
+    Vec<i8>, ByteArray, TagId::ByteArray;
+    String, String, TagId::String;
+    Vec<Value>, List, TagId::List;
+    HashMap<String, Value>, Compound, TagId::Compound;
+    Vec<i32>, IntArray, TagId::IntArray;
+    Vec<i64>, LongArray, TagId::LongArray;
+}
+
+/// Values are deserialized as the type the format reports, so that a
+/// `TAG_Int` read by `de::from_reader` becomes an `Int` even when serde
+/// buffers it first, as it does for `#[serde(flatten)]` fields. Unsigned
+/// integers become the narrowest tag that holds them.
+///
+/// Serde does not distinguish arrays from lists, so non-empty sequences of
+/// bytes become a `ByteArray` and all other sequences a `List`.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> serde::export::Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+#[cfg(feature = "serde")]
+struct ValueVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an NBT value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> serde::export::Result<Value, E> { Ok(Value::from(v)) }
+    fn visit_i8<E>(self, v: i8) -> serde::export::Result<Value, E> { Ok(Value::Byte(v)) }
+    fn visit_i16<E>(self, v: i16) -> serde::export::Result<Value, E> { Ok(Value::Short(v)) }
+    fn visit_i32<E>(self, v: i32) -> serde::export::Result<Value, E> { Ok(Value::Int(v)) }
+    fn visit_i64<E>(self, v: i64) -> serde::export::Result<Value, E> { Ok(Value::Long(v)) }
+    fn visit_f32<E>(self, v: f32) -> serde::export::Result<Value, E> { Ok(Value::Float(v)) }
+    fn visit_f64<E>(self, v: f64) -> serde::export::Result<Value, E> { Ok(Value::Double(v)) }
+
+    fn visit_u64<E>(self, v: u64) -> serde::export::Result<Value, E>
+        where E: serde::de::Error
+    {
+        if let Ok(b) = i8::try_from(v) {
+            Ok(Value::Byte(b))
+        } else if let Ok(s) = i16::try_from(v) {
+            Ok(Value::Short(s))
+        } else if let Ok(i) = i32::try_from(v) {
+            Ok(Value::Int(i))
+        } else if let Ok(l) = i64::try_from(v) {
+            Ok(Value::Long(l))
+        } else {
+            Err(E::custom("integer is too large for any NBT tag"))
+        }
+    }
+
+    fn visit_str<E>(self, v: &str) -> serde::export::Result<Value, E> { Ok(Value::from(v)) }
+    fn visit_string<E>(self, v: String) -> serde::export::Result<Value, E> { Ok(Value::String(v)) }
+
+    fn visit_seq<A>(self, mut seq: A) -> serde::export::Result<Value, A::Error>
+        where A: SeqAccess<'de>
+    {
+        let mut vals = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(MAX_LIST_PREALLOCATION));
+        while let Some(val) = seq.next_element()? {
+            vals.push(val);
+        }
+        let bytes: Option<Vec<i8>> = vals.iter()
+            .map(|v| match *v { Value::Byte(b) => Some(b), _ => None })
+            .collect();
+        match bytes {
+            Some(bytes) if !bytes.is_empty() => Ok(Value::ByteArray(bytes)),
+            _ => Ok(Value::List(vals)),
+        }
+    }
+
+    fn visit_map<A>(self, mut map: A) -> serde::export::Result<Value, A::Error>
+        where A: MapAccess<'de>
+    {
+        let mut vals = HashMap::with_capacity(map.size_hint().unwrap_or(0).min(MAX_LIST_PREALLOCATION));
+        while let Some((key, val)) = map.next_entry()? {
+            vals.insert(key, val);
+        }
+        Ok(Value::Compound(vals))
+    }
+}
@@ -0,0 +1,248 @@
+//! Configurable writing of NBT data.
+
+use std::io;
+
+use byteorder::WriteBytesExt;
+use flate2;
+use flate2::write::{GzEncoder, ZlibEncoder};
+
+use error::Result;
+use raw;
+use tag::TagId;
+use value::Value;
+
+/// The compression applied to a whole document by an `NbtWriter`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// Write the document as it is.
+    None,
+    /// Compress the document with gzip, as for `.dat` files.
+    Gzip,
+    /// Compress the document with zlib, as for region file chunks.
+    Zlib,
+}
+
+/// Configures an `NbtWriter`, which writes `Value`s as complete documents.
+///
+/// Documents are always written big-endian, with strings in Modified UTF-8,
+/// as the Java edition expects.
+///
+/// ```rust
+/// # #[macro_use] extern crate nbt;
+/// # fn main() {
+/// use nbt::writer::{Compression, NbtWriterBuilder};
+///
+/// let writer = NbtWriterBuilder::new()
+///     .sort_keys(true)
+///     .compression(Compression::None)
+///     .build();
+///
+/// let mut dst = Vec::new();
+/// writer.write(&mut dst, "root", &compound! { "b" => 1i8, "a" => 2i8 }).unwrap();
+/// assert_eq!(dst, vec![
+///     0x0a, 0x00, 0x04, b'r', b'o', b'o', b't',
+///         0x01, 0x00, 0x01, b'a', 0x02,
+///         0x01, 0x00, 0x01, b'b', 0x01,
+///     0x00,
+/// ]);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct NbtWriterBuilder {
+    sort_keys: bool,
+    compression: Compression,
+    root_name: bool,
+    validate_strings: bool,
+}
+
+impl Default for NbtWriterBuilder {
+    fn default() -> NbtWriterBuilder {
+        NbtWriterBuilder {
+            sort_keys: false,
+            compression: Compression::None,
+            root_name: true,
+            validate_strings: false,
+        }
+    }
+}
+
+impl NbtWriterBuilder {
+    /// Creates a builder for a writer that behaves like `Value::to_writer`
+    /// with a root header: unsorted, uncompressed, and with a root name.
+    pub fn new() -> NbtWriterBuilder {
+        NbtWriterBuilder::default()
+    }
+
+    /// Writes the entries of every compound in key order, as for
+    /// `Value::to_writer_sorted`.
+    pub fn sort_keys(mut self, sort: bool) -> NbtWriterBuilder {
+        self.sort_keys = sort;
+        self
+    }
+
+    /// Compresses the whole document.
+    pub fn compression(mut self, compression: Compression) -> NbtWriterBuilder {
+        self.compression = compression;
+        self
+    }
+
+    /// Whether to write the name of the root value after its tag type. Some
+    /// formats, such as the network protocol of recent Minecraft versions,
+    /// omit it.
+    pub fn root_name(mut self, write: bool) -> NbtWriterBuilder {
+        self.root_name = write;
+        self
+    }
+
+    /// Whether to check, before writing anything, that the root name and
+    /// every key and string fit their length prefix once encoded as Modified
+    /// UTF-8, returning `Error::StringTooLong` if one does not. Otherwise the
+    /// length of such a string is silently truncated and the document is
+    /// corrupt.
+    pub fn validate_strings(mut self, validate: bool) -> NbtWriterBuilder {
+        self.validate_strings = validate;
+        self
+    }
+
+    /// Creates the configured writer.
+    pub fn build(self) -> NbtWriter {
+        NbtWriter { config: self }
+    }
+}
+
+/// Writes `Value`s as complete documents, as configured by an
+/// `NbtWriterBuilder`.
+#[derive(Clone, Debug)]
+pub struct NbtWriter {
+    config: NbtWriterBuilder,
+}
+
+impl NbtWriter {
+    /// Writes `value` as the root of a document named `name` to an
+    /// `io::Write` destination. The name is ignored if the writer was
+    /// configured not to write root names.
+    pub fn write<W>(&self, dst: &mut W, name: &str, value: &Value) -> Result<()>
+        where W: io::Write
+    {
+        if self.config.validate_strings {
+            if self.config.root_name {
+                raw::check_string_len(name)?;
+            }
+            check_strings(value)?;
+        }
+        match self.config.compression {
+            Compression::None => self.write_document(dst, name, value),
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(dst, flate2::Compression::Default);
+                self.write_document(&mut encoder, name, value)?;
+                encoder.finish()?;
+                Ok(())
+            },
+            Compression::Zlib => {
+                let mut encoder = ZlibEncoder::new(dst, flate2::Compression::Default);
+                self.write_document(&mut encoder, name, value)?;
+                encoder.finish()?;
+                Ok(())
+            },
+        }
+    }
+
+    fn write_document<W>(&self, dst: &mut W, name: &str, value: &Value) -> Result<()>
+        where W: io::Write
+    {
+        dst.write_u8(value.id())?;
+        if self.config.root_name {
+            raw::write_bare_string(dst, name)?;
+        }
+        if self.config.sort_keys {
+            value.to_writer_sorted(dst)?;
+        } else {
+            value.to_writer(dst)?;
+        }
+        Ok(())
+    }
+}
+
+fn check_strings(value: &Value) -> Result<()> {
+    match *value {
+        Value::String(ref s) => raw::check_string_len(s),
+        Value::List(ref vals) => vals.iter().try_for_each(check_strings),
+        Value::Compound(ref map) => map.iter().try_for_each(|(key, val)| {
+            raw::check_string_len(key)?;
+            check_strings(val)
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// A writer which emits NBT data one tag at a time, the counterpart of
+/// `reader::NbtReader`.
+///
+/// Compounds are opened by writing a `TagId::Compound` header and closed
+/// with `write_end`. Tags whose payloads are already serialized, such as
+/// those copied from another file, can be written with `write_raw_tag`
+/// without being parsed.
+///
+/// ```rust
+/// use nbt::writer::NbtEncoder;
+/// use nbt::{TagId, Value};
+///
+/// let mut encoder = NbtEncoder::new(Vec::new());
+/// encoder.write_header(TagId::Compound, "").unwrap();
+/// encoder.write_tag("x", &Value::Int(5)).unwrap();
+/// encoder.write_raw_tag(TagId::Byte, "y", &[0x01]).unwrap();
+/// encoder.write_end().unwrap();
+/// assert_eq!(encoder.into_inner(), vec![
+///     0x0a, 0x00, 0x00,
+///         0x03, 0x00, 0x01, b'x', 0x00, 0x00, 0x00, 0x05,
+///         0x01, 0x00, 0x01, b'y', 0x01,
+///     0x00,
+/// ]);
+/// ```
+pub struct NbtEncoder<W> {
+    dst: W,
+}
+
+impl<W> NbtEncoder<W> where W: io::Write {
+    /// Creates an encoder writing to the given `io::Write` destination.
+    pub fn new(dst: W) -> NbtEncoder<W> {
+        NbtEncoder { dst }
+    }
+
+    /// Returns the underlying destination.
+    pub fn into_inner(self) -> W {
+        self.dst
+    }
+
+    /// Writes the tag type and name of an entry, which must be followed by
+    /// its payload.
+    pub fn write_header(&mut self, tag: TagId, name: &str) -> Result<()> {
+        self.dst.write_u8(tag.into())?;
+        raw::write_bare_string(&mut self.dst, name)
+    }
+
+    /// Writes the payload of a value.
+    pub fn write_value(&mut self, value: &Value) -> Result<()> {
+        value.to_writer(&mut self.dst)?;
+        Ok(())
+    }
+
+    /// Writes an entry named `name` holding `value`.
+    pub fn write_tag(&mut self, name: &str, value: &Value) -> Result<()> {
+        self.write_header(value.tag_id(), name)?;
+        self.write_value(value)
+    }
+
+    /// Writes an entry named `name` whose payload has already been
+    /// serialized. The payload is copied verbatim, and is not checked
+    /// against `tag`.
+    pub fn write_raw_tag(&mut self, tag: TagId, name: &str, payload: &[u8]) -> Result<()> {
+        self.write_header(tag, name)?;
+        self.dst.write_all(payload).map_err(From::from)
+    }
+
+    /// Closes the innermost open compound.
+    pub fn write_end(&mut self) -> Result<()> {
+        raw::close_nbt(&mut self.dst)
+    }
+}
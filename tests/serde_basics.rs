@@ -472,3 +472,272 @@ fn roundtrip_hashmap() {
 
     assert_roundtrip_eq(nbt, &bytes, None);
 }
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct RenamedNbt {
+    #[serde(rename = "Count")]
+    count: i8,
+}
+
+#[test]
+fn roundtrip_renamed_field() {
+    let nbt = RenamedNbt { count: 64 };
+
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x01,
+                0x00, 0x05,
+                0x43, 0x6f, 0x75, 0x6e, 0x74, // "Count"
+                0x40,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, None);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PascalCaseNbt {
+    custom_model_data: i32,
+}
+
+#[test]
+fn roundtrip_rename_all() {
+    let nbt = PascalCaseNbt { custom_model_data: 1 };
+
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x03,
+                0x00, 0x0f,
+                // "CustomModelData"
+                0x43, 0x75, 0x73, 0x74, 0x6f, 0x6d, 0x4d, 0x6f, 0x64, 0x65,
+                0x6c, 0x44, 0x61, 0x74, 0x61,
+                0x00, 0x00, 0x00, 0x01,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, None);
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct SkippedNbt {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<i8>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    list: Vec<i16>,
+}
+
+#[test]
+fn serialize_skipped_fields() {
+    let nbt = SkippedNbt { data: None, list: vec![] };
+
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            // Neither field is included.
+        0x00
+    ];
+
+    let mut dst = Vec::new();
+    nbt::ser::to_writer(&mut dst, &nbt, None).unwrap();
+    assert_eq!(&bytes[..], &dst[..]);
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct DefaultedNbt {
+    data: i8,
+    #[serde(default)]
+    extra: i32,
+}
+
+#[test]
+fn deserialize_default_field() {
+    let nbt = DefaultedNbt { data: 100, extra: 0 };
+
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x01,
+                0x00, 0x04,
+                0x64, 0x61, 0x74, 0x61,
+                0x64,
+            // "extra" is missing.
+        0x00
+    ];
+
+    let read: DefaultedNbt = from_reader(&bytes[..]).unwrap();
+    assert_eq!(read, nbt)
+}
+
+#[test]
+fn deserialize_end_only() {
+    // A lone TAG_End is an empty document.
+    let bytes = vec![0x00];
+
+    let read: HashMap<String, i8> = from_reader(&bytes[..]).unwrap();
+    assert!(read.is_empty());
+}
+
+/// Stores a `SystemTime` as a `Long` of milliseconds since the Unix epoch.
+mod epoch_millis {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(t: &SystemTime, s: S) -> Result<S::Ok, S::Error> {
+        let millis = t.duration_since(UNIX_EPOCH).unwrap().as_millis();
+        s.serialize_i64(millis as i64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<SystemTime, D::Error> {
+        let millis = i64::deserialize(d)?;
+        Ok(UNIX_EPOCH + Duration::from_millis(millis as u64))
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct TimestampNbt {
+    #[serde(with = "epoch_millis")]
+    last_played: std::time::SystemTime,
+}
+
+#[test]
+fn roundtrip_field_with_module() {
+    let last_played = std::time::UNIX_EPOCH + std::time::Duration::from_millis(0x0102);
+    let nbt = TimestampNbt { last_played };
+
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x04,
+                0x00, 0x0b,
+                0x6c, 0x61, 0x73, 0x74, 0x5f, 0x70, 0x6c, 0x61, 0x79, 0x65, 0x64,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x02,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, None);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct TagTypeNbt {
+    #[serde(with = "nbt::tag_type::short")]
+    id: u8,
+    #[serde(with = "nbt::tag_type::long")]
+    count: i8,
+    #[serde(with = "nbt::tag_type::double")]
+    scale: f32,
+}
+
+#[test]
+fn roundtrip_tag_type_override() {
+    let nbt = TagTypeNbt { id: 200, count: -1, scale: 0.5 };
+
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x02,
+                0x00, 0x02,
+                0x69, 0x64,
+                0x00, 0xc8,
+            0x04,
+                0x00, 0x05,
+                0x63, 0x6f, 0x75, 0x6e, 0x74,
+                0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0x06,
+                0x00, 0x05,
+                0x73, 0x63, 0x61, 0x6c, 0x65,
+                0x3f, 0xe0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, None);
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ByteOverrideNbt {
+    #[serde(with = "nbt::tag_type::byte")]
+    data: u16,
+}
+
+#[test]
+fn tag_type_override_out_of_range() {
+    let mut dst = Vec::new();
+    assert!(nbt::ser::to_writer(&mut dst, &ByteOverrideNbt { data: 300 }, None).is_err());
+
+    // A negative byte cannot be read into an unsigned field.
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x01,
+                0x00, 0x04,
+                0x64, 0x61, 0x74, 0x61,
+                0xff,
+        0x00
+    ];
+    let read: Result<ByteOverrideNbt, _> = from_reader(&bytes[..]);
+    assert!(read.is_err());
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct FlattenNbt {
+    name: String,
+    #[serde(flatten)]
+    rest: HashMap<String, nbt::Value>,
+}
+
+#[test]
+fn roundtrip_flatten() {
+    let mut rest = HashMap::new();
+    rest.insert("count".to_string(), nbt::Value::Int(3));
+    let nbt = FlattenNbt { name: "Herobrine".to_string(), rest };
+
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x08,
+                0x00, 0x04,
+                0x6e, 0x61, 0x6d, 0x65,
+                0x00, 0x09,
+                0x48, 0x65, 0x72, 0x6f, 0x62, 0x72, 0x69, 0x6e, 0x65,
+            0x03,
+                0x00, 0x05,
+                0x63, 0x6f, 0x75, 0x6e, 0x74,
+                0x00, 0x00, 0x00, 0x03,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, None);
+}
+
+#[test]
+fn deserialize_skips_unknown_fields() {
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x09,
+                0x00, 0x05,
+                0x65, 0x78, 0x74, 0x72, 0x61, // "extra"
+                0x0a,
+                0x00, 0x00, 0x00, 0x02,
+                    0x01,
+                        0x00, 0x01, 0x61,
+                        0x01,
+                    0x00,
+                    0x07,
+                        0x00, 0x01, 0x62,
+                        0x00, 0x00, 0x00, 0x02,
+                        0x01, 0x02,
+                    0x00,
+            0x01,
+                0x00, 0x04,
+                0x64, 0x61, 0x74, 0x61,
+                0x05,
+        0x00
+    ];
+
+    let read: ByteNbt = from_reader(&bytes[..]).unwrap();
+    assert_eq!(read, ByteNbt { data: 5 });
+}
@@ -4,7 +4,7 @@ extern crate serde;
 
 extern crate nbt;
 
-use nbt::de::from_reader;
+use nbt::de::{from_gzip_reader, from_reader};
 use nbt::ser::to_writer;
 use nbt::{Error, Result};
 
@@ -115,3 +115,37 @@ fn non_boolean_byte() {
         _ => panic!("encountered an unexpected error"),
     }
 }
+
+#[test]
+fn missing_field() {
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+        0x00
+    ];
+
+    let read: Result<ByteNbt> = from_reader(&bytes[..]);
+
+    assert!(read.is_err());
+    match read.unwrap_err() {
+        Error::Serde(msg) => assert_eq!(&msg, "missing field `data`"),
+        _ => panic!("encountered an unexpected error"),
+    }
+}
+
+#[test]
+fn gzip_reader_uncompressed() {
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+        0x00
+    ];
+
+    let read: Result<ByteNbt> = from_gzip_reader(&bytes[..]);
+
+    assert!(read.is_err());
+    match read.unwrap_err() {
+        Error::NotGzip => (),
+        _ => panic!("encountered an unexpected error"),
+    }
+}